@@ -49,7 +49,7 @@ run_test() {
     
     # Step 1: Compile Python to LLVM IR
     echo -e "  📝 Compiling to LLVM IR..."
-    if ! ./target/release/pytc --input "$test_file" > /dev/null 2>&1; then
+    if ! ./target/release/pytc --input "$test_file" --check-roundtrip > /dev/null 2>&1; then
         echo -e "${RED}  ❌ Failed to compile to LLVM IR${NC}"
         ((FAILED_TESTS++))
         return 1
@@ -3,6 +3,7 @@ use clap::Parser;
 
 mod utils;
 use utils::pycachereader::PyCacheReader;
+use utils::pycachewriter::PyCacheWriter;
 use utils::llvm::LlvmCompiler;
 use utils::pycachegenerator::PyCacheGenerator;
 
@@ -13,6 +14,10 @@ struct Args {
     /// Input Python file
     #[arg(short, long)]
     input: String,
+
+    /// Verify that the .pyc file written back from the parsed bytecode is identical to the original
+    #[arg(long)]
+    check_roundtrip: bool,
 }
 
 fn validate_input(input_path: &Path) -> Result<(), String> {
@@ -27,7 +32,30 @@ fn validate_input(input_path: &Path) -> Result<(), String> {
     Ok(())
 }
 
-fn compile_and_run(input_path: &Path) -> Result<String, String> {
+fn check_roundtrip(reader: &mut PyCacheReader) -> Result<(), String> {
+    let code = reader.read_file_raw()
+        .map_err(|e| format!("Couldn't parse the .pyc file: {}", e))?;
+    let header = reader.get_header().expect("header to be read along with the file");
+
+    let mut writer = PyCacheWriter::new(header.version);
+    writer.write_file(header, &code);
+    let written = writer.get_contents();
+    let original = reader.get_contents();
+
+    if let Some(idx) = original.iter().zip(written.iter()).position(|(a, b)| a != b) {
+        return Err(format!("Round trip of the .pyc file differs at byte {}", idx));
+    }
+    if original.len() != written.len() {
+        return Err(format!(
+            "Round trip of the .pyc file has {} bytes instead of {}",
+            written.len(),
+            original.len()
+        ));
+    }
+    Ok(())
+}
+
+fn compile_and_run(input_path: &Path, roundtrip: bool) -> Result<String, String> {
     // Compile .py to .pyc
     let pyc_generator = PyCacheGenerator::new();
     let pyc_path = pyc_generator.compile_py_to_pyc(input_path)
@@ -36,13 +64,17 @@ fn compile_and_run(input_path: &Path) -> Result<String, String> {
     // Read and process the .pyc file
     let mut reader = PyCacheReader::from_file(&pyc_path)
         .map_err(|e| format!("Couldn't read the .pyc file: {}", e))?;
+    if roundtrip {
+        check_roundtrip(&mut reader)?;
+    }
     let code = reader.read_file()
-        .ok_or_else(|| "Couldn't parse the .pyc file".to_string())?;
-    let refs = reader.get_refs().clone();
+        .map_err(|e| format!("Couldn't parse the .pyc file: {}", e))?;
+    let version = reader.get_header().expect("header to be read along with the file").version;
 
     // Generate the LLVM IR
-    let llvm_compiler = LlvmCompiler::new(code, refs);
-    let llvm_ir = llvm_compiler.generate_ir();
+    let llvm_compiler = LlvmCompiler::new(code, version);
+    let llvm_ir = llvm_compiler.generate_ir()
+        .map_err(|e| format!("Failed to generate the LLVM IR: {}", e))?;
     llvm_compiler.save_to_file(&pyc_path, &llvm_ir);
 
     // Generate assembly code from LLVM IR (llc)
@@ -72,7 +104,7 @@ fn main() {
         std::process::exit(1);
     }
 
-    match compile_and_run(input_path) {
+    match compile_and_run(input_path, args.check_roundtrip) {
         Ok(output) => println!("\n==== OUTPUT FROM THE EXECUTABLE ====\n{}", output),
         Err(e) => {
             eprintln!("{}", e);
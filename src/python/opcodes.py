@@ -1,16 +1,136 @@
-import opcode
-from re import sub
-
-def camel_case(s):
-    s = sub(r"(_|-)+", " ", s).title().replace(" ", "")
-    return ''.join([s[0], s[1:]])
-
-print(opcode.opmap)
-
-FILE = open('./opcodes', 'w')
-
-for key in opcode.opmap:
-    val = opcode.opmap[key]
-    key = camel_case(key)
-    # FILE.write(f"{key}(u8),\n")
-    FILE.write(f"{val} => Some(Operation::{key}(next_byte)),\n")
\ No newline at end of file
+"""Generates src/utils/opcodes.rs from the opcode maps of the installed CPython interpreters.
+
+Usage: python3 src/python/opcodes.py [interpreter ...]
+Without arguments, python3.8 up to python3.13 are looked up in PATH.
+"""
+import json
+import subprocess
+import sys
+from pathlib import Path
+from re import sub
+
+INTERPRETERS = [f"python3.{minor}" for minor in range(8, 14)]
+OUT_FILE = Path(__file__).resolve().parents[1] / "utils" / "opcodes.rs"
+
+# Executed by every interpreter to dump its opcode map
+DUMP_SCRIPT = """
+import importlib.util, json, opcode, sys
+# A list indexed by opcode in 3.11 and 3.12, a dict keyed by name since 3.13
+caches = getattr(opcode, "_inline_cache_entries", {})
+if isinstance(caches, list):
+    caches = {opcode.opname[op]: count for op, count in enumerate(caches)}
+ops = []
+for name, op in opcode.opmap.items():
+    # Pseudo and instrumented opcodes never end up in a .pyc file
+    if op >= 256 or name.startswith("INSTRUMENTED_"):
+        continue
+    ops.append((op, name, caches.get(name, 0)))
+print(json.dumps({
+    "version": list(sys.version_info[:2]),
+    "magic": int.from_bytes(importlib.util.MAGIC_NUMBER[:2], "little"),
+    "ops": sorted(ops),
+}))
+"""
+
+
+def camel_case(s):
+    return sub(r"(_|-)+", " ", s).title().replace(" ", "")
+
+
+def dump_opcodes(interpreter):
+    output = subprocess.run([interpreter, "-c", DUMP_SCRIPT], capture_output=True, text=True, check=True)
+    return json.loads(output.stdout)
+
+
+def generate(tables):
+    names = sorted({name for table in tables for _, name, _ in table["ops"] if name != "EXTENDED_ARG"})
+    out = [
+        "// Generated by src/python/opcodes.py from the opcode maps of CPython - do not edit by hand",
+        "use super::version::PythonVersion;",
+        "",
+        "// Opcodes of all the supported versions, except EXTENDED_ARG which is folded into arguments",
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq)]",
+        "pub enum Opcode {",
+    ]
+    out += [f"    {camel_case(name)}," for name in names]
+    out += [
+        "}",
+        "",
+        "// Magic numbers (first 2 bytes of a .pyc file) of the supported CPython versions",
+        f"pub const MAGIC_NUMBERS: [(u16, PythonVersion); {len(tables)}] = [",
+    ]
+    for table in tables:
+        major, minor = table["version"]
+        out.append(f"    ({table['magic']}, PythonVersion {{ major: {major}, minor: {minor} }}),")
+    out += [
+        "];",
+        "",
+        "// Opcode of EXTENDED_ARG, which is folded into the argument of the following operation",
+        "pub fn extended_arg(version: &PythonVersion) -> Option<u8> {",
+        "    match (version.major, version.minor) {",
+    ]
+    for table in tables:
+        major, minor = table["version"]
+        opcode = next(op for op, name, _ in table["ops"] if name == "EXTENDED_ARG")
+        out.append(f"        ({major}, {minor}) => Some({opcode}),")
+    out += [
+        "        _ => None,",
+        "    }",
+        "}",
+        "",
+        "// Number of inline CACHE code units following an operation (3.11+)",
+        "pub fn inline_cache_entries(version: &PythonVersion, opcode: u8) -> usize {",
+        "    match (version.major, version.minor) {",
+    ]
+    for table in tables:
+        major, minor = table["version"]
+        cached = [(op, name, caches) for op, name, caches in table["ops"] if caches]
+        if not cached:
+            continue
+        out.append(f"        ({major}, {minor}) => match opcode {{")
+        for op, name, caches in cached:
+            out.append(f"            {op} => {caches}, // {name}")
+        out += [
+            "            _ => 0,",
+            "        },",
+        ]
+    out += [
+        "        _ => 0,",
+        "    }",
+        "}",
+        "",
+        "pub fn decode(version: &PythonVersion, opcode: u8) -> Option<Opcode> {",
+        "    match (version.major, version.minor) {",
+    ]
+    for table in tables:
+        major, minor = table["version"]
+        out.append(f"        ({major}, {minor}) => decode_{major}_{minor}(opcode),")
+    out += [
+        "        _ => None,",
+        "    }",
+        "}",
+    ]
+    for table in tables:
+        major, minor = table["version"]
+        out += [
+            "",
+            f"fn decode_{major}_{minor}(opcode: u8) -> Option<Opcode> {{",
+            "    match opcode {",
+        ]
+        for op, name, _ in table["ops"]:
+            if name == "EXTENDED_ARG":
+                continue
+            out.append(f"        {op} => Some(Opcode::{camel_case(name)}),")
+        out += [
+            "        _ => None,",
+            "    }",
+            "}",
+        ]
+    return "\n".join(out) + "\n"
+
+
+if __name__ == "__main__":
+    interpreters = sys.argv[1:] or INTERPRETERS
+    tables = sorted((dump_opcodes(interpreter) for interpreter in interpreters), key=lambda t: t["version"])
+    OUT_FILE.write_text(generate(tables))
+    print(f"Written opcode tables of {len(tables)} Python versions to {OUT_FILE}")
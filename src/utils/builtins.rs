@@ -1,19 +1,49 @@
-use inkwell::types::BasicTypeEnum;
+use inkwell::types::{BasicTypeEnum, StructType};
 use inkwell::builder::Builder;
-use inkwell::module::Module;
-
-use super::llvm::LlvmVariable;
+use inkwell::context::Context;
+use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, StructValue};
+use inkwell::IntPredicate;
 
 // Global constants for supported builtin functions
 pub const PRINT: &str = "print";
+pub const RANGE: &str = "range";
 // Add more builtin functions here as needed
 // pub const LEN: &str = "len";
-// pub const RANGE: &str = "range";
 // pub const INPUT: &str = "input";
 
+// Names of the builtins module in any supported Python version, which a global name falls back to
+const PYTHON_BUILTINS: &[&str] = &[
+    "ArithmeticError", "AssertionError", "AttributeError", "BaseException", "BaseExceptionGroup",
+    "BlockingIOError", "BrokenPipeError", "BufferError", "BytesWarning", "ChildProcessError",
+    "ConnectionAbortedError", "ConnectionError", "ConnectionRefusedError", "ConnectionResetError",
+    "DeprecationWarning", "EOFError", "Ellipsis", "EncodingWarning", "EnvironmentError", "Exception",
+    "ExceptionGroup", "FileExistsError", "FileNotFoundError", "FloatingPointError", "FutureWarning",
+    "GeneratorExit", "IOError", "ImportError", "ImportWarning", "IndentationError", "IndexError",
+    "InterruptedError", "IsADirectoryError", "KeyError", "KeyboardInterrupt", "LookupError", "MemoryError",
+    "ModuleNotFoundError", "NameError", "NotADirectoryError", "NotImplemented", "NotImplementedError",
+    "OSError", "OverflowError", "PendingDeprecationWarning", "PermissionError", "ProcessLookupError",
+    "PythonFinalizationError", "RecursionError", "ReferenceError", "ResourceWarning", "RuntimeError",
+    "RuntimeWarning", "StopAsyncIteration", "StopIteration", "SyntaxError", "SyntaxWarning", "SystemError",
+    "SystemExit", "TabError", "TimeoutError", "TypeError", "UnboundLocalError", "UnicodeDecodeError",
+    "UnicodeEncodeError", "UnicodeError", "UnicodeTranslateError", "UnicodeWarning", "UserWarning",
+    "ValueError", "Warning", "ZeroDivisionError", "__build_class__", "__debug__", "__import__", "abs",
+    "aiter", "all", "anext", "any", "ascii", "bin", "bool", "breakpoint", "bytearray", "bytes", "callable",
+    "chr", "classmethod", "compile", "complex", "copyright", "credits", "delattr", "dict", "dir", "divmod",
+    "enumerate", "eval", "exec", "exit", "filter", "float", "format", "frozenset", "getattr", "globals",
+    "hasattr", "hash", "help", "hex", "id", "input", "int", "isinstance", "issubclass", "iter", "len",
+    "license", "list", "locals", "map", "max", "memoryview", "min", "next", "object", "oct", "open", "ord",
+    "pow", "print", "property", "quit", "range", "repr", "reversed", "round", "set", "setattr", "slice",
+    "sorted", "staticmethod", "str", "sum", "super", "tuple", "type", "vars", "zip",
+];
+
 /// Check if a function name is a supported builtin
 pub fn is_builtin(name: &str) -> bool {
-    matches!(name, PRINT)
+    matches!(name, PRINT | RANGE)
+}
+
+/// Check if a name is found in Python's builtins module, whether or not it's supported
+pub fn is_python_builtin(name: &str) -> bool {
+    PYTHON_BUILTINS.contains(&name)
 }
 
 
@@ -26,26 +56,50 @@ pub fn get_print_format_string(arg_type: &BasicTypeEnum) -> &'static str {
     }
 }
 
-/// Handle the print builtin function - now a regular function!
-pub fn handle_print_builtin<'a>(
-    builder: &'a Builder,
-    module: &'a Module,
-    arg: &LlvmVariable<'a>,
+/// Handle the print builtin function by emitting a printf call for the runtime value
+pub fn handle_print_builtin<'ctx>(
+    builder: &Builder<'ctx>,
+    printf_func: FunctionValue<'ctx>,
+    arg: &BasicValueEnum<'ctx>,
 ) {
-    // Get the printf function from the module
-    let printf_func = module.get_function("printf").expect("printf function should be declared");
-    
     // Create format string for the value
-    let format_str_text = get_print_format_string(&arg.v_type);
+    let format_str_text = get_print_format_string(&arg.get_type());
     let format_str = builder.build_global_string_ptr(format_str_text, "print_format")
         .expect("failed to create format string");
     
     // Call printf with the format string and the value
     let _ = builder.build_call(
         printf_func, 
-        &[format_str.as_pointer_value().into(), arg.value.into()], 
+        &[format_str.as_pointer_value().into(), (*arg).into()], 
         "print_result"
     );
-    
-    println!("DEBUG: Generated LLVM IR for print function call");
+}
+
+/// Type of ranges and of their iterators: the start (or the next value of the iterator), the stop and the step
+pub fn range_type(context: &Context) -> StructType<'_> {
+    let i32_type = context.i32_type();
+    context.struct_type(&[i32_type.into(), i32_type.into(), i32_type.into()], false)
+}
+
+/// Whether the range iterator yields another value, depending on the direction of its step
+pub fn build_range_has_next<'ctx>(builder: &Builder<'ctx>, iterator: StructValue<'ctx>) -> IntValue<'ctx> {
+    let field = |idx: u32, name: &str| {
+        builder
+            .build_extract_value(iterator, idx, name)
+            .expect("llvm to extract a field of the range iterator")
+            .into_int_value()
+    };
+    let (next, stop, step) = (field(0, "next"), field(1, "stop"), field(2, "step"));
+    let compare = |predicate: IntPredicate, a: IntValue<'ctx>, b: IntValue<'ctx>, name: &str| {
+        builder
+            .build_int_compare(predicate, a, b, name)
+            .expect("llvm to build a comparison")
+    };
+    let ascending = compare(IntPredicate::SGT, step, step.get_type().const_zero(), "ascending");
+    let below_stop = compare(IntPredicate::SLT, next, stop, "below_stop");
+    let above_stop = compare(IntPredicate::SGT, next, stop, "above_stop");
+    builder
+        .build_select(ascending, below_stop, above_stop, "has_next")
+        .expect("llvm to build a select instruction")
+        .into_int_value()
 }
@@ -0,0 +1,245 @@
+use super::{
+    code::CodeBlock,
+    instruction::Instruction,
+    operations::{InstructionIndex, Operation},
+};
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+pub type BlockIndex = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Fallthrough, // to the block following in the bytecode
+    Jump,        // to the target of the block's last operation
+    Exception,   // to the handler of exceptions raised in the block
+}
+
+// Straight-line run of instructions, entered only at its first one and left only after its last one
+// (or by an exception)
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub instructions: Range<InstructionIndex>,
+    pub successors: Vec<(BlockIndex, EdgeKind)>,
+    pub predecessors: Vec<(BlockIndex, EdgeKind)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>, // in the order of the bytecode, the entry block first
+    block_of: Vec<BlockIndex>,   // block of every instruction
+}
+
+// Whether the operation never continues with the following instruction
+fn is_terminator(op: &Operation) -> bool {
+    matches!(
+        op,
+        Operation::Jump(_)
+            | Operation::ReturnValue
+            | Operation::ReturnConst(_)
+            | Operation::RaiseVarargs(_)
+            | Operation::Reraise
+    )
+}
+
+// Up to 3.10 the handler of a protected block is pushed by an operation at its start instead of being
+// in the exception table
+fn is_setup(op: &Operation) -> bool {
+    matches!(
+        op,
+        Operation::SetupFinally(_) | Operation::SetupWith(_) | Operation::SetupAsyncWith(_)
+    )
+}
+
+// Index of the first instruction at or after the offset
+fn index_of_offset(instructions: &[Instruction], offset: u32) -> InstructionIndex {
+    instructions.partition_point(|instruction| instruction.offset < offset)
+}
+
+// Handler of the exceptions raised by every instruction, from the exception table since 3.11
+fn table_handlers(code: &CodeBlock) -> Vec<Option<InstructionIndex>> {
+    let instructions = code.get_instructions();
+    instructions
+        .iter()
+        .map(|instruction| {
+            code.exception_handler_for_offset(instruction.offset)
+                .map(|entry| index_of_offset(instructions, entry.target))
+        })
+        .collect()
+}
+
+// Handler of the exceptions raised by every instruction up to 3.10, where the handlers are kept on a block
+// stack at run time: SETUP_* operations push their target and POP_BLOCK pops it, while a handler starts
+// with the stack the SETUP_* operation found. Unreachable instructions have no handler.
+fn block_stack_handlers(instructions: &[Instruction]) -> Vec<Option<InstructionIndex>> {
+    let mut block_stacks: Vec<Option<Vec<InstructionIndex>>> = vec![None; instructions.len()];
+    let mut worklist: Vec<InstructionIndex> = Vec::new();
+    if !instructions.is_empty() {
+        block_stacks[0] = Some(Vec::new());
+        worklist.push(0);
+    }
+
+    while let Some(i) = worklist.pop() {
+        let block_stack = block_stacks[i].clone().expect("instructions to be walked once reached");
+        let op = &instructions[i].op;
+        let mut next = block_stack.clone();
+        let mut successors: Vec<(InstructionIndex, Vec<InstructionIndex>)> = Vec::new();
+        if let Some(target) = op.jump_target() {
+            if is_setup(op) {
+                next.push(target);
+            }
+            successors.push((target, block_stack));
+        }
+        if let Operation::PopBlock = op {
+            next.pop();
+        }
+        if !is_terminator(op) && i + 1 < instructions.len() {
+            successors.push((i + 1, next));
+        }
+
+        for (successor, successor_stack) in successors {
+            if block_stacks[successor].is_none() {
+                block_stacks[successor] = Some(successor_stack);
+                worklist.push(successor);
+            }
+        }
+    }
+    block_stacks
+        .iter()
+        .map(|block_stack| block_stack.as_ref().and_then(|block_stack| block_stack.last().copied()))
+        .collect()
+}
+
+impl ControlFlowGraph {
+    pub fn new(code: &CodeBlock) -> Self {
+        let instructions = code.get_instructions();
+        let handlers = if code.get_exception_table().is_empty() {
+            block_stack_handlers(instructions)
+        } else {
+            table_handlers(code)
+        };
+
+        // Blocks start at the first instruction, at jump targets, after branches,
+        // and wherever the handler of exceptions changes so that a block has a single one
+        let mut leaders: BTreeSet<InstructionIndex> = BTreeSet::new();
+        leaders.insert(0);
+        for (i, instruction) in instructions.iter().enumerate() {
+            let op = &instruction.op;
+            if let Some(target) = op.jump_target() {
+                leaders.insert(target);
+                leaders.insert(i + 1);
+            }
+            if is_terminator(op) {
+                leaders.insert(i + 1);
+            }
+        }
+        for i in 1..instructions.len() {
+            if handlers[i] != handlers[i - 1] {
+                leaders.insert(i);
+            }
+        }
+        leaders.extend(handlers.iter().flatten().copied());
+        leaders.retain(|leader| *leader < instructions.len());
+
+        let starts: Vec<InstructionIndex> = leaders.into_iter().collect();
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        let mut block_of: Vec<BlockIndex> = vec![0; instructions.len()];
+        for (block, start) in starts.iter().enumerate() {
+            let end = starts.get(block + 1).copied().unwrap_or(instructions.len());
+            block_of[*start..end].fill(block);
+            blocks.push(BasicBlock {
+                instructions: *start..end,
+                successors: Vec::new(),
+                predecessors: Vec::new(),
+            });
+        }
+
+        let mut cfg = ControlFlowGraph { blocks, block_of };
+        for block in 0..cfg.blocks.len() {
+            for (successor, kind) in cfg.outgoing_edges(block, instructions, &handlers) {
+                cfg.add_edge(block, successor, kind);
+            }
+        }
+        cfg
+    }
+
+    fn outgoing_edges(
+        &self,
+        block: BlockIndex,
+        instructions: &[Instruction],
+        handlers: &[Option<InstructionIndex>],
+    ) -> Vec<(BlockIndex, EdgeKind)> {
+        let range = &self.blocks[block].instructions;
+        let last = &instructions[range.end - 1];
+
+        let mut edges: Vec<(BlockIndex, EdgeKind)> = Vec::new();
+        if !is_terminator(&last.op) && range.end < instructions.len() {
+            edges.push((block + 1, EdgeKind::Fallthrough));
+        }
+        // The target of SETUP_* is only reached by exceptions raised in the block it protects
+        if let Some(target) = last.op.jump_target().filter(|_| !is_setup(&last.op)) {
+            edges.push((self.block_of[target], EdgeKind::Jump));
+        }
+        if let Some(handler) = handlers[range.start].filter(|handler| *handler < instructions.len()) {
+            edges.push((self.block_of[handler], EdgeKind::Exception));
+        }
+        edges
+    }
+
+    fn add_edge(&mut self, from: BlockIndex, to: BlockIndex, kind: EdgeKind) {
+        if !self.blocks[from].successors.contains(&(to, kind)) {
+            self.blocks[from].successors.push((to, kind));
+            self.blocks[to].predecessors.push((from, kind));
+        }
+    }
+
+    pub fn entry(&self) -> BlockIndex {
+        0
+    }
+
+    pub fn block_of(&self, instruction: InstructionIndex) -> BlockIndex {
+        self.block_of[instruction]
+    }
+
+    pub fn successors(&self, block: BlockIndex) -> impl Iterator<Item = BlockIndex> + '_ {
+        self.blocks[block].successors.iter().map(|(successor, _)| *successor)
+    }
+
+    pub fn predecessors(&self, block: BlockIndex) -> impl Iterator<Item = BlockIndex> + '_ {
+        self.blocks[block].predecessors.iter().map(|(predecessor, _)| *predecessor)
+    }
+
+    // Blocks reachable from the entry, each one after all of its predecessors except for loop back edges.
+    // This is the order in which forward data-flow analyses converge fastest.
+    pub fn reverse_postorder(&self) -> Vec<BlockIndex> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder: Vec<BlockIndex> = Vec::new();
+        if self.blocks.is_empty() {
+            return postorder;
+        }
+
+        // Iterative depth-first search, as deeply nested code could overflow the call stack
+        let mut stack: Vec<(BlockIndex, usize)> = vec![(self.entry(), 0)];
+        visited[self.entry()] = true;
+        while let Some((block, next_edge)) = stack.pop() {
+            match self.blocks[block].successors.get(next_edge) {
+                Some((successor, _)) => {
+                    stack.push((block, next_edge + 1));
+                    if !visited[*successor] {
+                        visited[*successor] = true;
+                        stack.push((*successor, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+        postorder.reverse();
+        postorder
+    }
+
+    // Whether the edge goes back to a block which is not later in the bytecode,
+    // which CPython only emits for loops
+    pub fn is_back_edge(&self, from: BlockIndex, to: BlockIndex) -> bool {
+        to <= from
+    }
+}
@@ -1,16 +1,25 @@
-use super::{operations::Operation, var::Var};
-use inkwell::context::Context;
-use inkwell::types::{BasicType, BasicTypeEnum};
+use super::{
+    exceptiontable::ExceptionTableEntry, instruction::Instruction, linetable::LineEntry, operations::Operation, var::Var,
+};
+use std::ops::Range;
 
+// Kinds of variables in co_localspluskinds (3.11+), a name may have a few of them
+pub const CO_FAST_LOCAL: u8 = 0x20;
+pub const CO_FAST_CELL: u8 = 0x40;
+pub const CO_FAST_FREE: u8 = 0x80;
+
+// Code object fields of both layouts: the one of 3.8 - 3.10 and the one of 3.11+,
+// where varnames, cellvars and freevars were merged into co_localsplusnames.
+// For 3.11+ the former are derived from the latter, so they can be used with any version.
 #[derive(Default, Debug, Clone)]
 pub struct CodeBlock {
     pub co_argcount: i32,           // number of arguments (not including keyword only arguments, * or ** args)
     pub co_posonlyargcount: i32,    // number of positional only arguments
     pub co_kwonlyargcount: i32,     // number of keyword only arguments (not including ** arg)
-    pub co_nlocals: i32,            // number of local variables
+    pub co_nlocals: i32,            // number of local variables (not stored since 3.11)
     pub co_stacksize: i32,          // virtual machine stack space required
     pub co_flags: i32,              // bitmap of CO_* flags
-    pub co_code: Vec<Operation>,    // string of raw compiled bytecode
+    pub co_code: Box<Var>,          // string of raw compiled bytecode
     pub co_const: Box<Var>,         // tuple of constants used in the bytecode
     pub co_names: Box<Var>,         // tuple of names other than arguments and function locals
     pub co_varnames: Box<Var>,      // tuple of names of arguments and local variables
@@ -19,101 +28,155 @@ pub struct CodeBlock {
     pub co_filename: Box<Var>,      // name of file in which this code object was created
     pub co_name: Box<Var>,          // name with which this code object was defined
     pub co_firstlineno: i32,        // number of first line in Python source code
-    pub co_lnotab: Box<Var>,        // bytecode address-to-line information
-    // pub co_exceptiontable: Box<Var>, // exception handling information
-    // pub co_qualname: Box<Var>,       // fully qualified name with which this code object was defined
+    pub co_lnotab: Box<Var>,        // bytecode address-to-line information, co_linetable since 3.10
+    pub co_qualname: Box<Var>,      // 3.11+, fully qualified name with which this code object was defined
+    pub co_exceptiontable: Box<Var>, // 3.11+, exception handling information
+    pub co_localsplusnames: Box<Var>, // 3.11+, tuple of names of local, cell and free variables
+    pub co_localspluskinds: Box<Var>, // 3.11+, bytes with the CO_FAST_* kinds of co_localsplusnames
+    pub instructions: Vec<Instruction>,  // co_code decoded into instructions with resolved operands
+    pub lines: Vec<LineEntry>,           // co_lnotab decoded into source locations of co_code ranges
+    pub exception_table: Vec<ExceptionTableEntry>, // co_exceptiontable decoded, empty before 3.11
 }
 
 impl CodeBlock {
-    pub fn get_name(&self, refs: &Vec<Var>) -> String {
-        match &*self.co_name {
-            Var::Ref(i) => match &refs[*i as usize] {
-                Var::String(s) | Var::ShortAscii(s) | Var::ShortAsciiInterned(s) => s.clone(),
-                _ => panic!("co_name value is not a string"),
-            },
-            Var::String(s) | Var::ShortAscii(s) | Var::ShortAsciiInterned(s) => s.clone(),
-            _ => panic!("co_name value is not a String or a Ref to a string"),
+    // Copy of the code object with all of its Var fields resolved
+    pub fn resolve(&self, refs: &[Var]) -> CodeBlock {
+        let resolve = |var: &Var| Box::new(var.resolve(refs));
+        CodeBlock {
+            co_code: resolve(&self.co_code),
+            co_const: resolve(&self.co_const),
+            co_names: resolve(&self.co_names),
+            co_varnames: resolve(&self.co_varnames),
+            co_freevars: resolve(&self.co_freevars),
+            co_cellvars: resolve(&self.co_cellvars),
+            co_filename: resolve(&self.co_filename),
+            co_name: resolve(&self.co_name),
+            co_lnotab: resolve(&self.co_lnotab),
+            co_qualname: resolve(&self.co_qualname),
+            co_exceptiontable: resolve(&self.co_exceptiontable),
+            co_localsplusnames: resolve(&self.co_localsplusnames),
+            co_localspluskinds: resolve(&self.co_localspluskinds),
+            instructions: self.instructions.clone(),
+            lines: self.lines.clone(),
+            exception_table: self.exception_table.clone(),
+            ..*self
         }
     }
 
-    fn get_deref_vec<'a>(&'a self, refs: &'a Vec<Var>, vec: &'a Box<Var>) -> Vec<&Var> {
-        let mut vars: Vec<&Var> = Vec::new();
-        if let Var::SmallTuple(vars_temp) = &**vec {
-            for var in vars_temp {
-                match var {
-                    Var::Ref(i) => vars.push(&refs[*i as usize]),
-                    _ => vars.push(var),
-                }
-            }
-            vars
-        } else {
-            panic!("{:?} is not a Tuple", vec)
+    // Fills co_varnames, co_cellvars, co_freevars and co_nlocals from the resolved
+    // co_localsplusnames and co_localspluskinds of a 3.11+ code object
+    pub fn derive_from_localsplus(&mut self, localsplusnames: &Var, localspluskinds: &Var) {
+        let names = localsplusnames.as_tuple().cloned().unwrap_or_default();
+        let kinds = localspluskinds.as_bytes().unwrap_or_default();
+        let with_kind = |kind: u8| -> Vec<Var> {
+            names
+                .iter()
+                .zip(kinds.iter())
+                .filter(|(_, name_kind)| *name_kind & kind != 0)
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+
+        let varnames = with_kind(CO_FAST_LOCAL);
+        self.co_nlocals = varnames.len() as i32;
+        *self.co_varnames = Var::Tuple(varnames);
+        *self.co_cellvars = Var::Tuple(with_kind(CO_FAST_CELL));
+        *self.co_freevars = Var::Tuple(with_kind(CO_FAST_FREE));
+    }
+
+    pub fn get_name(&self) -> String {
+        self.co_name
+            .as_string()
+            .expect("co_name value is not a string")
+    }
+
+    fn get_tuple<'a>(&'a self, vec: &'a Var) -> Vec<&'a Var> {
+        match vec.as_tuple() {
+            Some(vars) => vars.iter().collect(),
+            None => panic!("{:?} is not a Tuple", vec),
         }
     }
 
-    pub fn get_consts<'a>(&'a self, refs: &'a Vec<Var>) -> Vec<&Var> {
-        self.get_deref_vec(refs, &self.co_const)
+    pub fn get_consts(&self) -> Vec<&Var> {
+        self.get_tuple(&self.co_const)
     }
 
-    pub fn get_names<'a>(&'a self, refs: &'a Vec<Var>) -> Vec<String> {
-        let names_as_vars = self.get_deref_vec(refs, &self.co_names);
+    pub fn get_names(&self) -> Vec<String> {
+        let names_as_vars = self.get_tuple(&self.co_names);
         let mut names: Vec<String> = vec![];
         for var in names_as_vars {
-            match var {
-                Var::String(s) | Var::ShortAscii(s) | Var::ShortAsciiInterned(s) => {
-                    names.push(s.clone())
-                }
-                _ => panic!("Found non-string value in co_names vector"),
+            match var.as_string() {
+                Some(s) => names.push(s),
+                None => panic!("Found non-string value in co_names vector"),
             }
         }
         names
     }
 
-    pub fn get_code_blocks<'a>(&'a self, refs: &'a Vec<Var>) -> Vec<&CodeBlock> {
+    // Names of the arguments, in the order of the parameters, followed by those of the other local variables
+    pub fn get_varnames(&self) -> Vec<String> {
+        self.get_tuple(&self.co_varnames)
+            .into_iter()
+            .map(|var| var.as_string().expect("Found non-string value in co_varnames vector"))
+            .collect()
+    }
+
+    pub fn get_code_blocks(&self) -> Vec<&CodeBlock> {
         let mut code_blocks: Vec<&CodeBlock> = vec![self];
-        for const_var in self.get_consts(refs) {
+        for const_var in self.get_consts() {
             match const_var {
-                Var::Code(c) => code_blocks.push(&c),
+                Var::Code(c) => code_blocks.push(c),
                 _ => continue,
             }
         }
         code_blocks
     }
 
-    pub fn get_operations(&self) -> &Vec<Operation> {
-        &self.co_code
-    }
-
-    pub fn get_op_type<'a>(
-        &'a self,
-        refs: &'a Vec<Var>,
-        ctx: &'a Context,
-        operation: &'a Operation,
-    ) -> BasicTypeEnum {
-        match operation {
-            Operation::LoadConstArg(i) => {
-                let consts = self.get_consts(refs);
-                let var = consts[*i as usize];
-                match var {
-                    Var::None => ctx.i32_type().as_basic_type_enum(),
-                    Var::Int(_) | Var::Long(_) => ctx.i32_type().as_basic_type_enum(),
-                    _ => todo!("{:?} as return value", var),
-                }
-            }
-            Operation::ReturnValue => {
-                // ReturnValue returns the type of the value on top of the stack
-                // For now, assume it's an integer (most common case)
-                ctx.i32_type().as_basic_type_enum()
-            }
-            _ => todo!("operation {:?} as return value", operation),
+    // Operations of the code objects nested in this one at any depth, e.g. of functions defined in a module
+    pub fn get_nested_operations(&self) -> Vec<&Operation> {
+        let mut operations: Vec<&Operation> = vec![];
+        for code_block in self.get_code_blocks().into_iter().skip(1) {
+            operations.extend(code_block.get_operations());
+            operations.extend(code_block.get_nested_operations());
         }
+        operations
+    }
+
+    // Whether this is the code of a module rather than of a function or a class body
+    pub fn is_module(&self) -> bool {
+        self.get_name() == "<module>"
+    }
+
+    pub fn get_instructions(&self) -> &Vec<Instruction> {
+        &self.instructions
+    }
+
+    pub fn get_operations(&self) -> Vec<&Operation> {
+        self.instructions.iter().map(|instruction| &instruction.op).collect()
+    }
+
+    pub fn get_line_entries(&self) -> &Vec<LineEntry> {
+        &self.lines
+    }
+
+    // Ranges of co_code offsets along with their line, in the order of the bytecode
+    pub fn lines(&self) -> impl Iterator<Item = (Range<u32>, Option<i32>)> + '_ {
+        self.lines.iter().map(|entry| (entry.offsets.clone(), entry.line))
+    }
+
+    pub fn line_for_offset(&self, offset: u32) -> Option<i32> {
+        self.lines
+            .iter()
+            .find(|entry| entry.offsets.contains(&offset))
+            .and_then(|entry| entry.line)
+    }
+
+    pub fn get_exception_table(&self) -> &Vec<ExceptionTableEntry> {
+        &self.exception_table
     }
 
-    // TODO: This may cause problems in case of more complex programs
-    // Because the return type is the type of the Var on top of the stack
-    pub fn get_return_type<'a>(&'a self, refs: &'a Vec<Var>, ctx: &'a Context) -> BasicTypeEnum {
-        let operations = self.get_operations();
-        let ret_op = &operations[operations.len() - 2];
-        self.get_op_type(refs, ctx, ret_op)
+    // Entry handling exceptions raised by the operation at the given offset, if any
+    pub fn exception_handler_for_offset(&self, offset: u32) -> Option<&ExceptionTableEntry> {
+        self.exception_table.iter().find(|entry| entry.contains(offset))
     }
 }
@@ -0,0 +1,122 @@
+use super::{
+    operations::{BinOp, CmpKind, Name, UnaryOp},
+    stackdepth::StackDepthError,
+    typeinference::InferredType,
+};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileErrorKind {
+    MixedReturnTypes,        // returning values of different types, e.g. None when falling off its end
+    MixedParamTypes(Name),   // parameter passed arguments of different types by the calls
+    MixedTypes,              // values of different types meeting where paths join
+    NestedFunction(Name),    // function defined inside another one
+    RuntimeDefaults(Name),   // function whose default values aren't constants
+    RuntimeArguments,        // call unpacking arguments only known at run time, e.g. f(*args)
+    RuntimeCallee,           // call to a callable only known at run time
+    BuiltinKeywords(Name),   // call passing keyword arguments to a builtin
+    VariableArguments(Name), // *args or **kwargs parameter read by the function
+    MixedGlobalTypes(Name),  // module variable shared with the functions assigned values of different types
+    ShadowedBuiltin(Name),   // name read where it may be a builtin or a variable of the same name
+    ModuleAttribute(Name),   // module attribute set up by the interpreter, e.g. __name__
+    DictKeys,                // dict built with keys only known at run time
+    Operation(String),       // bytecode operation without a lowering, e.g. LIST_APPEND
+    Constant(String),        // constant of a type without a native representation, e.g. a float
+    Print(InferredType),     // print() of a value of a type without a native way to print it
+    Builtin(Name),           // call to a builtin without a native implementation
+    RangeArgument(InferredType),
+    Iteration(InferredType), // iteration over a value other than a range
+    TruthValue(InferredType),
+    UnaryOperation(UnaryOp, InferredType),
+    Comparison(CmpKind, InferredType, InferredType),
+    // operation on values of types without a native implementation of it
+    BinaryOperation(BinOp, InferredType, InferredType),
+    // bytecode whose stack depth isn't consistent across the paths to its instructions
+    StackUsage(StackDepthError),
+    // types inferred over the IR contradicting those inferred over the bytecode
+    InconsistentTypes(String),
+}
+
+// Python code the compiler can't lower to native code yet, pointing at the code object it's found in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub kind: CompileErrorKind,
+    pub function: String, // name of the code object, <module> for the module
+}
+
+impl CompileError {
+    pub fn new(function: &str, kind: CompileErrorKind) -> Self {
+        CompileError {
+            kind,
+            function: function.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CompileErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileErrorKind::MixedReturnTypes => {
+                write!(f, "it returns values of different types, or of a type only known at run time")
+            }
+            CompileErrorKind::MixedParamTypes(param) => write!(
+                f,
+                "its parameter '{}' is passed arguments of different types, or of a type only known at run time",
+                param
+            ),
+            CompileErrorKind::MixedTypes => write!(f, "values of different types meet where its paths join"),
+            CompileErrorKind::NestedFunction(name) => write!(f, "it defines the nested function '{}'", name),
+            CompileErrorKind::RuntimeDefaults(name) => {
+                write!(f, "the default values of '{}' are computed at run time", name)
+            }
+            CompileErrorKind::RuntimeArguments => write!(f, "it unpacks arguments computed at run time"),
+            CompileErrorKind::RuntimeCallee => write!(f, "it calls a value computed at run time"),
+            CompileErrorKind::BuiltinKeywords(name) => {
+                write!(f, "it passes keyword arguments to the builtin '{}'", name)
+            }
+            CompileErrorKind::VariableArguments(param) => {
+                write!(f, "it reads its variable arguments '{}'", param)
+            }
+            CompileErrorKind::MixedGlobalTypes(name) => {
+                write!(f, "the global '{}' is assigned values of different types", name)
+            }
+            CompileErrorKind::ShadowedBuiltin(name) => {
+                write!(f, "the builtin '{}' may be shadowed by a variable", name)
+            }
+            CompileErrorKind::BinaryOperation(op, lhs, rhs) => {
+                write!(f, "it applies {:?} to values of types {:?} and {:?}", op, lhs, rhs)
+            }
+            CompileErrorKind::ModuleAttribute(name) => write!(f, "it reads the module attribute '{}'", name),
+            CompileErrorKind::DictKeys => write!(f, "it builds a dict with keys computed at run time"),
+            CompileErrorKind::Operation(op) => write!(f, "it uses the operation {}", op),
+            CompileErrorKind::Constant(value) => write!(f, "it uses the constant {}", value),
+            CompileErrorKind::Print(value_type) => write!(f, "it prints a value of type {:?}", value_type),
+            CompileErrorKind::Builtin(name) => write!(f, "it calls the builtin '{}'", name),
+            CompileErrorKind::RangeArgument(arg_type) => write!(f, "it passes a {:?} to range()", arg_type),
+            CompileErrorKind::Iteration(iterable_type) => write!(f, "it iterates over a {:?}", iterable_type),
+            CompileErrorKind::TruthValue(value_type) => {
+                write!(f, "it takes the truth value of a {:?}", value_type)
+            }
+            CompileErrorKind::UnaryOperation(op, operand) => {
+                write!(f, "it applies {:?} to a value of type {:?}", op, operand)
+            }
+            CompileErrorKind::Comparison(kind, lhs, rhs) => {
+                write!(f, "it compares values of types {:?} and {:?} with {:?}", lhs, rhs, kind)
+            }
+            CompileErrorKind::StackUsage(err) => write!(f, "its bytecode uses the stack inconsistently: {}", err),
+            CompileErrorKind::InconsistentTypes(err) => {
+                write!(f, "the types inferred for it are inconsistent: {}", err)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "can't compile {:?}: {}", self.function, self.kind)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+pub type CompileResult<T> = Result<T, CompileError>;
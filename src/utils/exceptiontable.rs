@@ -0,0 +1,124 @@
+// Entry of the exception table of 3.11+, which replaced the SETUP_* and POP_BLOCK operations.
+// An exception raised by an operation in start..end jumps to target, after popping the stack
+// down to depth values and, if lasti is set, pushing the offset of the raising operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExceptionTableEntry {
+    pub start: u32, // byte offsets in co_code
+    pub end: u32,
+    pub target: u32,
+    pub depth: u32,
+    pub lasti: bool,
+}
+
+impl ExceptionTableEntry {
+    pub fn contains(&self, offset: u32) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+// Marks the first byte of every entry
+const START_FLAG: u8 = 0x80;
+
+struct ExceptionTableReader<'a> {
+    table: &'a [u8],
+    idx: usize,
+}
+
+impl ExceptionTableReader<'_> {
+    // Big endian chunks of 6 bits, where bit 6 marks that another chunk follows.
+    // Only the first chunk of the first varint of an entry has the start flag set.
+    fn read_varint(&mut self, is_start: bool) -> Result<u32, &'static str> {
+        let mut value: u32 = 0;
+        let mut is_first_chunk = true;
+        loop {
+            let byte = *self.table.get(self.idx).ok_or("truncated entry")?;
+            if (byte & START_FLAG != 0) != (is_start && is_first_chunk) {
+                return Err("misplaced start of an entry");
+            }
+            self.idx += 1;
+            is_first_chunk = false;
+            value = value.checked_mul(64).ok_or("varint too long")? | (byte & 0x3F) as u32;
+            if byte & 0x40 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    // Offsets are stored in code units
+    fn read_offset(&mut self, is_start: bool) -> Result<u32, &'static str> {
+        self.read_varint(is_start)?.checked_mul(2).ok_or("offset too large")
+    }
+}
+
+pub fn decode(table: &[u8]) -> Result<Vec<ExceptionTableEntry>, &'static str> {
+    let mut reader = ExceptionTableReader { table, idx: 0 };
+    let mut entries: Vec<ExceptionTableEntry> = Vec::new();
+    while reader.idx < table.len() {
+        let start = reader.read_offset(true)?;
+        let length = reader.read_offset(false)?;
+        let target = reader.read_offset(false)?;
+        let depth_lasti = reader.read_varint(false)?;
+        entries.push(ExceptionTableEntry {
+            start,
+            end: start.checked_add(length).ok_or("offset too large")?,
+            target,
+            depth: depth_lasti >> 1,
+            lasti: depth_lasti & 1 != 0,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(start: u32, end: u32, target: u32, depth: u32, lasti: bool) -> ExceptionTableEntry {
+        ExceptionTableEntry {
+            start,
+            end,
+            target,
+            depth,
+            lasti,
+        }
+    }
+
+    // def f(a):
+    //     try:
+    //         return (a +
+    //                 1)
+    //     finally:
+    //         pass
+    #[test]
+    fn try_finally() {
+        let table = [130, 4, 7, 0, 135, 2, 9, 3];
+        let expected = vec![entry(4, 12, 14, 0, false), entry(14, 18, 18, 1, true)];
+        assert_eq!(decode(&table), Ok(expected));
+    }
+
+    // def f(a):
+    //     try:
+    //         a = a + 0
+    //         ...
+    //         a = a + 39
+    //     except ValueError:
+    //         a = 0
+    //     return a
+    #[test]
+    fn multi_chunk_varints() {
+        let table = [130, 67, 8, 67, 12, 0, 195, 12, 11, 67, 27, 3, 195, 26, 1, 67, 27, 3];
+        let expected = vec![
+            entry(4, 404, 408, 0, false),
+            entry(408, 430, 438, 1, true),
+            entry(436, 438, 438, 1, true),
+        ];
+        assert_eq!(decode(&table), Ok(expected));
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(decode(&[130, 4, 7]), Err("truncated entry"));
+        assert_eq!(decode(&[2, 4, 7, 0]), Err("misplaced start of an entry"));
+        assert_eq!(decode(&[130, 132, 7, 0]), Err("misplaced start of an entry"));
+    }
+}
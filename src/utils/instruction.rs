@@ -0,0 +1,367 @@
+use super::{
+    code::{CodeBlock, CO_FAST_CELL, CO_FAST_FREE},
+    opcodes::Opcode,
+    operations::{BinOp, CmpKind, ConstRef, InstructionIndex, Name, Operation, RawInstruction, UnaryOp},
+    var::Var,
+    version::PythonVersion,
+};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub offset: u32,       // byte offset in co_code, shared by operations decoded from one opcode
+    pub line: Option<i32>, // None for code without a line, e.g. artificial returns
+    pub op: Operation,
+}
+
+// Resolves the arguments of raw operations into operands, using the tables of a code object
+// whose fields have already been resolved. Errors come with the offset of the operation.
+pub struct InstructionBuilder<'a> {
+    version: PythonVersion,
+    code: &'a CodeBlock,
+    consts: Vec<Var>,
+    names: Vec<Option<String>>,
+    local_names: Vec<Option<String>>, // LOAD_FAST and friends
+    deref_names: Vec<Option<String>>, // LOAD_DEREF and friends
+    local_kinds: Vec<u8>,             // CO_FAST_* kinds of co_localsplusnames since 3.11
+    indices: HashMap<u32, InstructionIndex>,
+}
+
+type BuildResult<T> = Result<T, String>;
+
+fn strings(var: &Var) -> Vec<Option<String>> {
+    var.as_tuple()
+        .map(|vars| vars.iter().map(|var| var.as_string()).collect())
+        .unwrap_or_default()
+}
+
+impl<'a> InstructionBuilder<'a> {
+    pub fn new(version: PythonVersion, code: &'a CodeBlock) -> Self {
+        // Since 3.11 all of them index co_localsplusnames, while before cell and free
+        // variables had a separate table
+        let (local_names, deref_names) = if version.at_least(3, 11) {
+            (strings(&code.co_localsplusnames), strings(&code.co_localsplusnames))
+        } else {
+            let mut deref_names = strings(&code.co_cellvars);
+            deref_names.extend(strings(&code.co_freevars));
+            (strings(&code.co_varnames), deref_names)
+        };
+
+        InstructionBuilder {
+            version,
+            code,
+            consts: code.co_const.as_tuple().cloned().unwrap_or_default(),
+            names: strings(&code.co_names),
+            local_names,
+            deref_names,
+            local_kinds: code.co_localspluskinds.as_bytes().unwrap_or_default(),
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn build(&mut self, raw_instructions: &[RawInstruction]) -> Result<Vec<Instruction>, (u32, String)> {
+        // Jump targets are byte offsets, so the index of each offset is needed beforehand
+        let mut index: InstructionIndex = 0;
+        for raw in raw_instructions {
+            self.indices.entry(raw.offset).or_insert(index);
+            index += Self::expanded_len(raw.opcode);
+        }
+
+        let mut instructions: Vec<Instruction> = Vec::new();
+        for raw in raw_instructions {
+            let line = self.code.line_for_offset(raw.offset);
+            for op in self.build_ops(raw).map_err(|msg| (raw.offset, msg))? {
+                instructions.push(Instruction {
+                    offset: raw.offset,
+                    line,
+                    op,
+                });
+            }
+        }
+        Ok(instructions)
+    }
+
+    // Superinstructions of 3.13 are split into the operations they are made of
+    fn expanded_len(opcode: Opcode) -> usize {
+        match opcode {
+            Opcode::LoadFastLoadFast | Opcode::StoreFastLoadFast | Opcode::StoreFastStoreFast => 2,
+            _ => 1,
+        }
+    }
+
+    fn table_entry(table: &[Option<String>], i: u32, what: &str) -> BuildResult<Name> {
+        match table.get(i as usize) {
+            Some(Some(name)) => Ok(name.clone()),
+            Some(None) => Err(format!("{} {} is not a string", what, i)),
+            None => Err(format!("{} {} out of range of {} entries", what, i, table.len())),
+        }
+    }
+
+    fn name(&self, i: u32) -> BuildResult<Name> {
+        Self::table_entry(&self.names, i, "name")
+    }
+
+    fn local_name(&self, i: u32) -> BuildResult<Name> {
+        Self::table_entry(&self.local_names, i, "local variable")
+    }
+
+    fn is_cell(&self, i: u32) -> bool {
+        self.local_kinds
+            .get(i as usize)
+            .is_some_and(|kind| kind & (CO_FAST_CELL | CO_FAST_FREE) != 0)
+    }
+
+    fn deref_name(&self, i: u32) -> BuildResult<Name> {
+        Self::table_entry(&self.deref_names, i, "cell or free variable")
+    }
+
+    fn constant(&self, i: u32) -> BuildResult<ConstRef> {
+        match self.consts.get(i as usize) {
+            Some(value) => Ok(ConstRef {
+                index: i,
+                value: value.clone(),
+            }),
+            None => Err(format!("constant {} out of range of {} entries", i, self.consts.len())),
+        }
+    }
+
+    fn jump(&self, raw: &RawInstruction) -> BuildResult<InstructionIndex> {
+        // Arguments count code units since 3.10, bytes before
+        let distance = if self.version.at_least(3, 10) {
+            raw.arg as i64 * 2
+        } else {
+            raw.arg as i64
+        };
+        let is_absolute = !self.version.at_least(3, 11)
+            && matches!(
+                raw.opcode,
+                Opcode::JumpAbsolute
+                    | Opcode::JumpIfFalseOrPop
+                    | Opcode::JumpIfTrueOrPop
+                    | Opcode::PopJumpIfFalse
+                    | Opcode::PopJumpIfTrue
+                    | Opcode::JumpIfNotExcMatch
+            );
+        let is_backward = matches!(
+            raw.opcode,
+            Opcode::JumpBackward
+                | Opcode::JumpBackwardNoInterrupt
+                | Opcode::PopJumpBackwardIfFalse
+                | Opcode::PopJumpBackwardIfTrue
+                | Opcode::PopJumpBackwardIfNone
+                | Opcode::PopJumpBackwardIfNotNone
+        );
+
+        // Relative jumps count from the end of the operation, including its inline caches
+        let target = if is_absolute {
+            distance
+        } else if is_backward {
+            raw.end as i64 - distance
+        } else {
+            raw.end as i64 + distance
+        };
+        u32::try_from(target)
+            .ok()
+            .and_then(|target| self.indices.get(&target))
+            .copied()
+            .ok_or_else(|| format!("jump target {} is not an operation", target))
+    }
+
+    fn build_ops(&self, raw: &RawInstruction) -> BuildResult<Vec<Operation>> {
+        let arg = raw.arg;
+        let at_least = |minor: u8| self.version.at_least(3, minor);
+
+        let op = match raw.opcode {
+            Opcode::Nop | Opcode::Resume => Operation::Nop,
+
+            Opcode::PopTop => Operation::PopTop,
+            Opcode::DupTop => Operation::Copy(1),
+            Opcode::Copy => Operation::Copy(arg),
+            Opcode::DupTopTwo => Operation::DupTopTwo,
+            Opcode::RotTwo => Operation::Rotate(2),
+            Opcode::RotThree => Operation::Rotate(3),
+            Opcode::RotFour => Operation::Rotate(4),
+            Opcode::RotN => Operation::Rotate(arg),
+            Opcode::Swap => Operation::Swap(arg),
+            Opcode::PushNull => Operation::PushNull,
+
+            Opcode::LoadConst => Operation::LoadConst(self.constant(arg)?),
+            Opcode::LoadName => Operation::LoadName(self.name(arg)?),
+            Opcode::StoreName => Operation::StoreName(self.name(arg)?),
+            Opcode::DeleteName => Operation::DeleteName(self.name(arg)?),
+            // Since 3.11 the lowest bit tells whether to push NULL
+            Opcode::LoadGlobal if at_least(11) => Operation::LoadGlobal {
+                name: self.name(arg >> 1)?,
+                push_null: arg & 1 != 0,
+            },
+            Opcode::LoadGlobal => Operation::LoadGlobal {
+                name: self.name(arg)?,
+                push_null: false,
+            },
+            Opcode::StoreGlobal => Operation::StoreGlobal(self.name(arg)?),
+            Opcode::DeleteGlobal => Operation::DeleteGlobal(self.name(arg)?),
+            // Since 3.13 LOAD_CLOSURE is emitted as LOAD_FAST of the cell
+            Opcode::LoadFast if self.is_cell(arg) => Operation::LoadClosure(self.deref_name(arg)?),
+            Opcode::LoadFast | Opcode::LoadFastCheck => Operation::LoadFast(self.local_name(arg)?),
+            Opcode::LoadFastAndClear => Operation::LoadFastAndClear(self.local_name(arg)?),
+            Opcode::StoreFast => Operation::StoreFast(self.local_name(arg)?),
+            Opcode::DeleteFast => Operation::DeleteFast(self.local_name(arg)?),
+            // Two 4-bit indices, the first one used first
+            Opcode::LoadFastLoadFast => {
+                return Ok(vec![
+                    Operation::LoadFast(self.local_name(arg >> 4)?),
+                    Operation::LoadFast(self.local_name(arg & 15)?),
+                ]);
+            }
+            Opcode::StoreFastLoadFast => {
+                return Ok(vec![
+                    Operation::StoreFast(self.local_name(arg >> 4)?),
+                    Operation::LoadFast(self.local_name(arg & 15)?),
+                ]);
+            }
+            Opcode::StoreFastStoreFast => {
+                return Ok(vec![
+                    Operation::StoreFast(self.local_name(arg >> 4)?),
+                    Operation::StoreFast(self.local_name(arg & 15)?),
+                ]);
+            }
+            Opcode::LoadDeref | Opcode::LoadClassderef => Operation::LoadDeref(self.deref_name(arg)?),
+            Opcode::StoreDeref => Operation::StoreDeref(self.deref_name(arg)?),
+            Opcode::DeleteDeref => Operation::DeleteDeref(self.deref_name(arg)?),
+            Opcode::LoadClosure => Operation::LoadClosure(self.deref_name(arg)?),
+            Opcode::MakeCell => Operation::MakeCell(self.deref_name(arg)?),
+            // Since 3.12 the lowest bit tells whether to load a method
+            Opcode::LoadAttr if at_least(12) => Operation::LoadAttr {
+                name: self.name(arg >> 1)?,
+                method: arg & 1 != 0,
+            },
+            Opcode::LoadAttr => Operation::LoadAttr {
+                name: self.name(arg)?,
+                method: false,
+            },
+            Opcode::LoadMethod => Operation::LoadMethod(self.name(arg)?),
+            Opcode::StoreAttr => Operation::StoreAttr(self.name(arg)?),
+            Opcode::DeleteAttr => Operation::DeleteAttr(self.name(arg)?),
+            Opcode::ImportName => Operation::ImportName(self.name(arg)?),
+            Opcode::ImportFrom => Operation::ImportFrom(self.name(arg)?),
+            Opcode::ImportStar => Operation::ImportStar,
+
+            Opcode::UnaryPositive => Operation::UnaryOp(UnaryOp::Positive),
+            Opcode::UnaryNegative => Operation::UnaryOp(UnaryOp::Negative),
+            Opcode::UnaryNot => Operation::UnaryOp(UnaryOp::Not),
+            Opcode::UnaryInvert => Operation::UnaryOp(UnaryOp::Invert),
+            Opcode::CallIntrinsic1 => match UnaryOp::from_intrinsic(arg) {
+                Some(op) => Operation::UnaryOp(op),
+                None if arg == 6 => Operation::ListToTuple, // INTRINSIC_LIST_TO_TUPLE
+                None if arg == 2 => Operation::ImportStar, // INTRINSIC_IMPORT_STAR
+                None => Operation::Other {
+                    opcode: raw.opcode,
+                    arg,
+                },
+            },
+            Opcode::BinaryOp => match BinOp::from_binary_op(arg) {
+                Some((op, false)) => Operation::BinaryOp(op),
+                Some((op, true)) => Operation::InplaceOp(op),
+                None => return Err(format!("unknown binary operator {}", arg)),
+            },
+            // Since 3.12 the lowest bits hold flags for the specialising interpreter
+            Opcode::CompareOp => {
+                let cmp_op = if at_least(13) {
+                    arg >> 5
+                } else if at_least(12) {
+                    arg >> 4
+                } else {
+                    arg
+                };
+                // Up to 3.8 the comparison also covered the later IS_OP and CONTAINS_OP
+                match cmp_op {
+                    6 | 7 if !at_least(9) => Operation::ContainsOp { negated: cmp_op == 7 },
+                    8 | 9 if !at_least(9) => Operation::IsOp { negated: cmp_op == 9 },
+                    10 if at_least(9) => return Err(format!("unknown comparison {}", cmp_op)),
+                    _ => Operation::CompareOp(
+                        CmpKind::from_cmp_op(cmp_op).ok_or_else(|| format!("unknown comparison {}", cmp_op))?,
+                    ),
+                }
+            }
+            Opcode::IsOp => Operation::IsOp { negated: arg == 1 },
+            Opcode::ContainsOp => Operation::ContainsOp { negated: arg == 1 },
+            Opcode::ToBool => Operation::ToBool,
+            Opcode::BinarySubscr => Operation::BinarySubscr,
+            Opcode::StoreSubscr => Operation::StoreSubscr,
+            Opcode::DeleteSubscr => Operation::DeleteSubscr,
+
+            Opcode::BuildTuple => Operation::BuildTuple(arg),
+            Opcode::BuildList => Operation::BuildList(arg),
+            Opcode::BuildSet => Operation::BuildSet(arg),
+            Opcode::BuildMap => Operation::BuildMap(arg),
+            Opcode::BuildConstKeyMap => Operation::BuildConstKeyMap(arg),
+            Opcode::BuildString => Operation::BuildString(arg),
+            Opcode::BuildSlice => Operation::BuildSlice(arg),
+            Opcode::ListAppend => Operation::ListAppend(arg),
+            Opcode::ListExtend => Operation::ListExtend(arg),
+            Opcode::ListToTuple => Operation::ListToTuple,
+            Opcode::SetAdd => Operation::SetAdd(arg),
+            Opcode::SetUpdate => Operation::SetUpdate(arg),
+            Opcode::MapAdd => Operation::MapAdd(arg),
+            Opcode::DictMerge => Operation::DictMerge(arg),
+            Opcode::DictUpdate => Operation::DictUpdate(arg),
+            Opcode::UnpackSequence => Operation::UnpackSequence(arg),
+            Opcode::UnpackEx => Operation::UnpackEx(arg),
+
+            Opcode::JumpForward
+            | Opcode::JumpAbsolute
+            | Opcode::JumpBackward
+            | Opcode::JumpBackwardNoInterrupt => Operation::Jump(self.jump(raw)?),
+            Opcode::PopJumpIfFalse | Opcode::PopJumpForwardIfFalse | Opcode::PopJumpBackwardIfFalse => {
+                Operation::PopJumpIfFalse(self.jump(raw)?)
+            }
+            Opcode::PopJumpIfTrue | Opcode::PopJumpForwardIfTrue | Opcode::PopJumpBackwardIfTrue => {
+                Operation::PopJumpIfTrue(self.jump(raw)?)
+            }
+            Opcode::PopJumpIfNone | Opcode::PopJumpForwardIfNone | Opcode::PopJumpBackwardIfNone => {
+                Operation::PopJumpIfNone(self.jump(raw)?)
+            }
+            Opcode::PopJumpIfNotNone | Opcode::PopJumpForwardIfNotNone | Opcode::PopJumpBackwardIfNotNone => {
+                Operation::PopJumpIfNotNone(self.jump(raw)?)
+            }
+            Opcode::JumpIfFalseOrPop => Operation::JumpIfFalseOrPop(self.jump(raw)?),
+            Opcode::JumpIfTrueOrPop => Operation::JumpIfTrueOrPop(self.jump(raw)?),
+            Opcode::JumpIfNotExcMatch => Operation::JumpIfNotExcMatch(self.jump(raw)?),
+            Opcode::GetIter => Operation::GetIter,
+            Opcode::ForIter => Operation::ForIter(self.jump(raw)?),
+            Opcode::EndFor => Operation::EndFor,
+            Opcode::Send => Operation::Send(self.jump(raw)?),
+            Opcode::SetupFinally => Operation::SetupFinally(self.jump(raw)?),
+            Opcode::SetupWith => Operation::SetupWith(self.jump(raw)?),
+            Opcode::SetupAsyncWith => Operation::SetupAsyncWith(self.jump(raw)?),
+            Opcode::CallFinally => Operation::CallFinally(self.jump(raw)?),
+            Opcode::PopBlock => Operation::PopBlock,
+            Opcode::ReturnValue => Operation::ReturnValue,
+            Opcode::ReturnConst => Operation::ReturnConst(self.constant(arg)?),
+            Opcode::RaiseVarargs => Operation::RaiseVarargs(arg),
+            Opcode::Reraise => Operation::Reraise,
+            Opcode::PushExcInfo => Operation::PushExcInfo,
+            Opcode::PopExcept => Operation::PopExcept,
+            Opcode::CheckExcMatch => Operation::CheckExcMatch,
+
+            Opcode::CallFunction => Operation::CallFunction(arg),
+            Opcode::CallFunctionKw => Operation::CallFunctionKw(arg),
+            Opcode::CallMethod => Operation::CallMethod(arg),
+            Opcode::Precall => Operation::Precall(arg),
+            Opcode::Call => Operation::Call(arg),
+            Opcode::KwNames => Operation::KwNames(self.constant(arg)?),
+            Opcode::CallKw => Operation::CallKw(arg),
+            Opcode::CallFunctionEx => Operation::CallFunctionEx { has_kwargs: arg & 1 != 0 },
+            Opcode::MakeFunction if at_least(13) => Operation::MakeFunction(0),
+            Opcode::MakeFunction => Operation::MakeFunction(arg),
+            Opcode::SetFunctionAttribute => Operation::SetFunctionAttribute(arg),
+
+            opcode => match BinOp::from_opcode(opcode) {
+                Some((op, false)) => Operation::BinaryOp(op),
+                Some((op, true)) => Operation::InplaceOp(op),
+                None => Operation::Other { opcode, arg },
+            },
+        };
+        Ok(vec![op])
+    }
+}
@@ -0,0 +1,489 @@
+use super::{
+    builtins,
+    liveness::Variable,
+    operations::{BinOp, CmpKind, Name, UnaryOp},
+    typeinference::InferredType,
+    var::Var,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+pub type ValueId = usize;
+pub type BlockId = usize;
+pub type FunctionId = usize; // index of the code object in CodeBlock::get_code_blocks of the module
+
+// Computation of a single SSA value
+#[derive(Debug, Clone)]
+pub enum Inst {
+    Phi(Vec<(BlockId, ValueId)>), // value coming from the predecessor the block was entered from
+    Undefined(Variable),          // value of a variable before it's assigned
+    Param(usize),                 // value of a parameter, keyword-only ones following the positional ones
+    Const(Var),
+    Code(FunctionId), // code object of a function, as loaded for MAKE_FUNCTION
+    // Function made from the code object, with the constant default values of its parameters
+    Function {
+        function: FunctionId,
+        defaults: Vec<Var>,           // of the last positional parameters
+        kwdefaults: Vec<(Name, Var)>, // of keyword-only parameters
+    },
+    Builtin(Name), // builtin function loaded by name, e.g. print
+    // Module variables shared with the functions are kept in LLVM globals. Reading or deleting one that's
+    // unassigned raises a NameError.
+    LoadGlobal(Name),
+    StoreGlobal(Name, ValueId),
+    DeleteGlobal(Name),
+    // Raises the error, a NameError or UnboundLocalError, when the value read or deleted is that of an unassigned
+    // variable. Phis may take such values on some paths.
+    CheckAssigned(ValueId, String),
+    Null,          // NULL pushed along with the callable since 3.11
+    Unary(UnaryOp, ValueId),
+    ToBool(ValueId), // truth value of any value
+    Binary {
+        op: BinOp,
+        inplace: bool,
+        lhs: ValueId,
+        rhs: ValueId,
+    },
+    Compare(CmpKind, ValueId, ValueId),
+    Call {
+        callee: ValueId,
+        args: Vec<ValueId>,
+    },
+    // Containers only known at compile time, as built to pass arguments or default values
+    Tuple(Vec<ValueId>),
+    List(Vec<ValueId>),
+    Dict(Vec<(Name, ValueId)>), // with string keys, in insertion order
+    // range() with the defaults of its missing arguments filled in
+    Range {
+        start: ValueId,
+        stop: ValueId,
+        step: ValueId,
+    },
+    GetIter(ValueId),
+    // A for loop computes all three from the iterator, using the last two only if the first one is true
+    IterHasNext(ValueId),
+    IterValue(ValueId),   // value the iterator yields next
+    IterAdvance(ValueId), // iterator past the value it yields next
+}
+
+// How control leaves a block
+#[derive(Debug, Clone)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: ValueId, // tested for its truth value
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(ValueId),
+    Error(String), // uncaught exception ending the program, with its message
+    Unreachable,   // block no path enters
+}
+
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub inst: Inst,
+    pub value_type: Option<InferredType>, // None until it's known, e.g. for the results of recursive calls
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub values: Vec<ValueId>, // phis first, then the other values in the order they are computed
+    pub terminator: Terminator,
+}
+
+// Code object in static single assignment form: every stack slot and variable of the bytecode becomes
+// the value last computed for it, with phis where paths with different values meet
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub blocks: Vec<Block>, // in reverse postorder, the entry block first
+    pub values: Vec<Value>,
+    pub param_types: Vec<Option<InferredType>>, // joined over the calls, None while no call is known
+    pub return_type: Option<InferredType>,      // None if the function never returns
+    pub stores: Vec<(Variable, ValueId)>,       // values assigned to the variables, including deletions
+    // Types the inference over the bytecode gives the values and the return value, see check_types
+    pub bytecode_types: BTreeMap<ValueId, InferredType>,
+    pub bytecode_return_type: Option<InferredType>,
+}
+
+// Functions of a module, those not compiled being None
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub functions: Vec<Option<Function>>, // by FunctionId
+    // Types of the module variables kept in LLVM globals, joined over the values stored in them
+    pub global_types: BTreeMap<Name, InferredType>,
+}
+
+impl Inst {
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Inst::Phi(incoming) => incoming.iter().map(|(_, value)| *value).collect(),
+            Inst::Undefined(_)
+            | Inst::Param(_)
+            | Inst::Const(_)
+            | Inst::Code(_)
+            | Inst::Function { .. }
+            | Inst::Builtin(_)
+            | Inst::LoadGlobal(_)
+            | Inst::DeleteGlobal(_)
+            | Inst::Null => vec![],
+            Inst::Unary(_, operand)
+            | Inst::ToBool(operand)
+            | Inst::StoreGlobal(_, operand)
+            | Inst::CheckAssigned(operand, _)
+            | Inst::GetIter(operand)
+            | Inst::IterHasNext(operand)
+            | Inst::IterValue(operand)
+            | Inst::IterAdvance(operand) => vec![*operand],
+            Inst::Binary { lhs, rhs, .. } | Inst::Compare(_, lhs, rhs) => vec![*lhs, *rhs],
+            Inst::Call { callee, args } => std::iter::once(*callee).chain(args.iter().copied()).collect(),
+            Inst::Tuple(items) | Inst::List(items) => items.clone(),
+            Inst::Dict(items) => items.iter().map(|(_, value)| *value).collect(),
+            Inst::Range { start, stop, step } => vec![*start, *stop, *step],
+        }
+    }
+
+    fn operands_mut(&mut self) -> Vec<&mut ValueId> {
+        match self {
+            Inst::Phi(incoming) => incoming.iter_mut().map(|(_, value)| value).collect(),
+            Inst::Undefined(_)
+            | Inst::Param(_)
+            | Inst::Const(_)
+            | Inst::Code(_)
+            | Inst::Function { .. }
+            | Inst::Builtin(_)
+            | Inst::LoadGlobal(_)
+            | Inst::DeleteGlobal(_)
+            | Inst::Null => vec![],
+            Inst::Unary(_, operand)
+            | Inst::ToBool(operand)
+            | Inst::StoreGlobal(_, operand)
+            | Inst::CheckAssigned(operand, _)
+            | Inst::GetIter(operand)
+            | Inst::IterHasNext(operand)
+            | Inst::IterValue(operand)
+            | Inst::IterAdvance(operand) => vec![operand],
+            Inst::Binary { lhs, rhs, .. } | Inst::Compare(_, lhs, rhs) => vec![lhs, rhs],
+            Inst::Call { callee, args } => std::iter::once(callee).chain(args.iter_mut()).collect(),
+            Inst::Tuple(items) | Inst::List(items) => items.iter_mut().collect(),
+            Inst::Dict(items) => items.iter_mut().map(|(_, value)| value).collect(),
+            Inst::Range { start, stop, step } => vec![start, stop, step],
+        }
+    }
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch { then, otherwise, .. } => vec![*then, *otherwise],
+            Terminator::Return(_) | Terminator::Error(_) | Terminator::Unreachable => vec![],
+        }
+    }
+}
+
+impl Function {
+    pub fn new(name: String, param_count: usize) -> Self {
+        Function {
+            name,
+            blocks: Vec::new(),
+            values: Vec::new(),
+            param_types: vec![None; param_count],
+            return_type: None,
+            stores: Vec::new(),
+            bytecode_types: BTreeMap::new(),
+            bytecode_return_type: None,
+        }
+    }
+
+    pub fn add_block(&mut self) -> BlockId {
+        self.blocks.push(Block {
+            values: Vec::new(),
+            terminator: Terminator::Unreachable,
+        });
+        self.blocks.len() - 1
+    }
+
+    // Appends the computation to the block, its type being found once the function is complete
+    pub fn add_value(&mut self, block: BlockId, inst: Inst) -> ValueId {
+        self.values.push(Value { inst, value_type: None });
+        let value = self.values.len() - 1;
+        self.blocks[block].values.push(value);
+        value
+    }
+
+    pub fn inst(&self, value: ValueId) -> &Inst {
+        &self.values[value].inst
+    }
+
+    // Values whose type isn't known can be anything
+    pub fn value_type(&self, value: ValueId) -> InferredType {
+        self.values[value].value_type.unwrap_or(InferredType::Dynamic)
+    }
+
+    // Rewrites every operand through the replacements, which may be chained
+    pub fn replace_values(&mut self, replacements: &[ValueId]) {
+        let resolve = |mut value: ValueId| {
+            while replacements[value] != value {
+                value = replacements[value];
+            }
+            value
+        };
+        for value in &mut self.values {
+            for operand in value.inst.operands_mut() {
+                *operand = resolve(*operand);
+            }
+        }
+        for block in &mut self.blocks {
+            if let Terminator::Return(value) | Terminator::Branch { condition: value, .. } = &mut block.terminator {
+                *value = resolve(*value);
+            }
+        }
+        for (_, value) in &mut self.stores {
+            *value = resolve(*value);
+        }
+        for (value, value_type) in std::mem::take(&mut self.bytecode_types) {
+            self.bytecode_types
+                .entry(resolve(value))
+                .and_modify(|own| *own = own.join(value_type))
+                .or_insert(value_type);
+        }
+    }
+
+    // Types of the values, following the same rules as the inference over the bytecode. Phis join the types
+    // of their incoming values, leaving out those of unassigned variables, until none of them changes.
+    // Calls to the functions of the program give their return types, and loads of globals the types stored in
+    // them, as far as they are known.
+    pub fn infer_types(
+        &mut self,
+        return_types: &[Option<InferredType>],
+        global_types: &BTreeMap<Name, InferredType>,
+    ) {
+        let mut types: Vec<Option<InferredType>> = vec![None; self.values.len()];
+        let join = |a: Option<InferredType>, b: InferredType| Some(a.map_or(b, |a| a.join(b)));
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in &self.blocks {
+                for value in &block.values {
+                    let value_type = match &self.values[*value].inst {
+                        Inst::Phi(incoming) => incoming
+                            .iter()
+                            .filter(|(_, incoming)| !matches!(self.values[*incoming].inst, Inst::Undefined(_)))
+                            .filter_map(|(_, incoming)| types[*incoming])
+                            .fold(None, join),
+                        Inst::Undefined(_) | Inst::Builtin(_) | Inst::Null => Some(InferredType::Dynamic),
+                        Inst::LoadGlobal(name) => global_types.get(name).copied(),
+                        Inst::StoreGlobal(..) | Inst::DeleteGlobal(_) | Inst::CheckAssigned(..) => {
+                            Some(InferredType::None)
+                        }
+                        Inst::Param(idx) => self.param_types[*idx],
+                        Inst::Const(var) => Some(InferredType::of_constant(var)),
+                        Inst::Code(_) | Inst::Function { .. } => Some(InferredType::Dynamic),
+                        Inst::Unary(op, operand) => types[*operand].map(|operand| InferredType::unary(*op, operand)),
+                        Inst::ToBool(_) => Some(InferredType::Bool),
+                        Inst::Binary { op, inplace, lhs, rhs } => types[*lhs]
+                            .zip(types[*rhs])
+                            .map(|(lhs, rhs)| InferredType::binary(*op, *inplace, lhs, rhs)),
+                        // Comparisons of builtin types give bools, others may return anything
+                        Inst::Compare(_, lhs, rhs) => types[*lhs].zip(types[*rhs]).map(|types| match types {
+                            (InferredType::Dynamic, _) | (_, InferredType::Dynamic) => InferredType::Dynamic,
+                            _ => InferredType::Bool,
+                        }),
+                        Inst::Call { callee, .. } => match &self.values[*callee].inst {
+                            Inst::Builtin(name) if name == builtins::PRINT => Some(InferredType::None),
+                            Inst::Function { function, .. } => return_types[*function],
+                            _ => Some(InferredType::Dynamic),
+                        },
+                        Inst::Tuple(_) | Inst::List(_) | Inst::Dict(_) => Some(InferredType::Dynamic),
+                        Inst::Range { .. } => Some(InferredType::Range),
+                        Inst::GetIter(iterable) => types[*iterable].map(|iterable| match iterable {
+                            InferredType::Range => InferredType::RangeIterator,
+                            _ => InferredType::Dynamic,
+                        }),
+                        Inst::IterHasNext(_) => Some(InferredType::Bool),
+                        Inst::IterValue(iterator) => types[*iterator].map(|iterator| match iterator {
+                            InferredType::RangeIterator => InferredType::Int,
+                            _ => InferredType::Dynamic,
+                        }),
+                        Inst::IterAdvance(iterator) => types[*iterator],
+                    };
+                    if value_type.is_some() && value_type != types[*value] {
+                        types[*value] = value_type;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        for (value, value_type) in self.values.iter_mut().zip(types.iter()) {
+            value.value_type = *value_type;
+        }
+        self.return_type = self
+            .blocks
+            .iter()
+            .filter_map(|block| match block.terminator {
+                Terminator::Return(value) => types[value],
+                _ => None,
+            })
+            .fold(None, join);
+    }
+
+    // Checks the types against those the inference over the bytecode found. That one knows nothing about
+    // arguments, calls and globals, so the two may only differ where one of them is Dynamic.
+    pub fn check_types(&self) -> Result<(), String> {
+        let differ = |value_type: Option<InferredType>, bytecode_type: InferredType| {
+            bytecode_type != InferredType::Dynamic
+                && value_type.is_some_and(|value_type| value_type != bytecode_type && value_type != InferredType::Dynamic)
+        };
+        for (value, bytecode_type) in &self.bytecode_types {
+            if differ(self.values[*value].value_type, *bytecode_type) {
+                return Err(format!(
+                    "%{} is {:?} but the bytecode gives {:?}",
+                    value,
+                    self.value_type(*value),
+                    bytecode_type
+                ));
+            }
+        }
+        match self.bytecode_return_type {
+            Some(bytecode_type) if differ(self.return_type, bytecode_type) => Err(format!(
+                "the return type is {:?} but the bytecode gives {:?}",
+                self.return_type, bytecode_type
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Phis that may take the value of an unassigned variable, directly or through other phis
+    pub fn maybe_unassigned(&self) -> BTreeSet<ValueId> {
+        let mut phis = BTreeSet::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for value in self.blocks.iter().flat_map(|block| &block.values) {
+                let Inst::Phi(incoming) = self.inst(*value) else {
+                    continue;
+                };
+                let unassigned = incoming
+                    .iter()
+                    .any(|(_, incoming)| matches!(self.inst(*incoming), Inst::Undefined(_)) || phis.contains(incoming));
+                if unassigned && phis.insert(*value) {
+                    changed = true;
+                }
+            }
+        }
+        phis
+    }
+
+    // Values the function stores in the globals
+    pub fn global_stores(&self) -> impl Iterator<Item = (&Name, ValueId)> + '_ {
+        self.values.iter().filter_map(|value| match &value.inst {
+            Inst::StoreGlobal(name, stored) => Some((name, *stored)),
+            _ => None,
+        })
+    }
+
+    pub fn global_loads(&self) -> impl Iterator<Item = &Name> + '_ {
+        self.values.iter().filter_map(|value| match &value.inst {
+            Inst::LoadGlobal(name) => Some(name),
+            _ => None,
+        })
+    }
+
+    // Calls of the function to the functions of the program, with their arguments
+    pub fn calls(&self) -> impl Iterator<Item = (FunctionId, &[ValueId])> + '_ {
+        self.values.iter().filter_map(|value| match &value.inst {
+            Inst::Call { callee, args } => match self.inst(*callee) {
+                Inst::Function { function, .. } => Some((*function, args.as_slice())),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+}
+
+impl Program {
+    // Types of the functions, which depend on each other through calls and globals. Parameters get the types
+    // of the arguments passed to them, calls the return types of the functions and globals the types of the
+    // values stored in them, until none of them changes.
+    // Functions still not known to return after that never do, and their calls are then given None like
+    // the LLVM functions, as their results are never used. The same goes for globals never assigned, whose
+    // loads raise a NameError.
+    pub fn infer_types(&mut self) {
+        self.infer_types_with(None);
+        self.infer_types_with(Some(InferredType::None));
+    }
+
+    fn infer_types_with(&mut self, never_returns: Option<InferredType>) {
+        let mut changed = true;
+        while changed {
+            let return_types: Vec<Option<InferredType>> = self
+                .functions
+                .iter()
+                .map(|function| function.as_ref().and_then(|function| function.return_type))
+                .collect();
+            let call_types: Vec<Option<InferredType>> =
+                return_types.iter().map(|return_type| return_type.or(never_returns)).collect();
+            let global_types = std::mem::take(&mut self.global_types);
+            let mut load_types = global_types.clone();
+            if let Some(never_assigned) = never_returns {
+                for name in self.functions.iter().flatten().flat_map(Function::global_loads) {
+                    load_types.entry(name.clone()).or_insert(never_assigned);
+                }
+            }
+            for function in self.functions.iter_mut().flatten() {
+                function.infer_types(&call_types, &load_types);
+            }
+            for function in self.functions.iter().flatten() {
+                for (name, stored) in function.global_stores() {
+                    if let Some(stored_type) = function.values[stored].value_type {
+                        self.global_types
+                            .entry(name.clone())
+                            .and_modify(|global_type| *global_type = global_type.join(stored_type))
+                            .or_insert(stored_type);
+                    }
+                }
+            }
+
+            let mut param_types: Vec<Vec<Option<InferredType>>> = self
+                .functions
+                .iter()
+                .map(|function| function.as_ref().map_or(Vec::new(), |function| function.param_types.clone()))
+                .collect();
+            for caller in self.functions.iter().flatten() {
+                for (callee, args) in caller.calls() {
+                    for (param_type, arg) in param_types[callee].iter_mut().zip(args) {
+                        if let Some(arg_type) = caller.values[*arg].value_type {
+                            *param_type = Some(param_type.map_or(arg_type, |param_type| param_type.join(arg_type)));
+                        }
+                    }
+                }
+            }
+
+            changed = self.global_types != global_types;
+            for (idx, function) in self.functions.iter_mut().enumerate() {
+                if let Some(function) = function {
+                    changed |= function.return_type != return_types[idx] || function.param_types != param_types[idx];
+                    function.param_types = std::mem::take(&mut param_types[idx]);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "function {:?} {:?} -> {:?}", self.name, self.param_types, self.return_type)?;
+        for (block_id, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "  block{}:", block_id)?;
+            for value in &block.values {
+                writeln!(f, "    %{} = {:?} : {:?}", value, self.inst(*value), self.value_type(*value))?;
+            }
+            writeln!(f, "    {:?}", block.terminator)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1238 @@
+use super::{
+    builtins,
+    cfg::{BlockIndex, ControlFlowGraph, EdgeKind},
+    code::CodeBlock,
+    compileerror::{CompileError, CompileErrorKind, CompileResult},
+    ir::{BlockId, Function, FunctionId, Inst, Terminator, ValueId},
+    liveness::{variable_access, Access, Definition, Liveness, Variable},
+    opcodes::Opcode,
+    operations::{
+        InstructionIndex, Name, Operation, FUNCTION_ANNOTATIONS, FUNCTION_CLOSURE, FUNCTION_DEFAULTS,
+        FUNCTION_KWDEFAULTS,
+    },
+    typeinference::{InferredType, TypeInference},
+    var::Var,
+    version::PythonVersion,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Flags of code objects taking extra positional or keyword arguments
+const CO_VARARGS: i32 = 0x4;
+const CO_VARKEYWORDS: i32 = 0x8;
+
+// Name of the module run as the program, which its code reads as __name__
+const MAIN_MODULE: &str = "__main__";
+
+// What the code of the module refers to outside the code object being built
+pub struct Scope<'a> {
+    pub functions: Vec<&'a CodeBlock>, // code objects of the module, by FunctionId
+    // Names assigned in the module, with the function they are bound to if it's always the same one
+    pub globals: BTreeMap<Name, Option<Inst>>,
+    // Names of the module kept in LLVM globals, as the functions read or assign them
+    pub variables: BTreeSet<Name>,
+}
+
+impl<'a> Scope<'a> {
+    // Names assigned through global declarations are known from the bytecode, before any function is built
+    pub fn new(module: &'a CodeBlock) -> Self {
+        let variables = all_operations(module)
+            .into_iter()
+            .filter_map(|op| match op {
+                Operation::StoreGlobal(name) | Operation::DeleteGlobal(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        Scope {
+            functions: module.get_code_blocks(),
+            globals: BTreeMap::new(),
+            variables,
+        }
+    }
+
+    // Finds the names of the module bound to functions, from the values it stores, which have to come from the
+    // same MAKE_FUNCTION so that the default values are the same too. Names also assigned by
+    // the functions through global declarations may be bound to anything. Functions are called directly
+    // through their names, even from code running before the name is assigned.
+    pub fn bind_globals(&mut self, module: &Function) {
+        let mut bound: BTreeMap<Name, Option<ValueId>> = BTreeMap::new();
+        for (variable, value) in &module.stores {
+            if let Variable::Name(name) = variable {
+                let function = matches!(module.inst(*value), Inst::Function { .. }).then_some(*value);
+                bound
+                    .entry(name.clone())
+                    .and_modify(|bound| {
+                        if *bound != function {
+                            *bound = None;
+                        }
+                    })
+                    .or_insert(function);
+            }
+        }
+        self.globals = bound
+            .into_iter()
+            .map(|(name, function)| (name, function.map(|function| module.inst(function).clone())))
+            .collect();
+        for name in &self.variables {
+            self.globals.insert(name.clone(), None);
+        }
+        // Other names the functions read have to be shared with them, unless they are bound to functions
+        for op in all_operations(self.functions[0]) {
+            if let Operation::LoadGlobal { name, .. } = op {
+                if let Some(None) = self.globals.get(name) {
+                    self.variables.insert(name.clone());
+                }
+            }
+        }
+    }
+
+    // Whether the variable of the module is kept in an LLVM global
+    fn is_shared(&self, variable: &Variable) -> bool {
+        matches!(variable, Variable::Name(name) if self.variables.contains(name))
+    }
+
+    // Function of the code object among the constants of the module
+    fn function_of(&self, code: &CodeBlock) -> Option<FunctionId> {
+        self.functions.iter().position(|function| std::ptr::eq(*function, code))
+    }
+}
+
+// Values of the stack slots and variables at some point of the bytecode
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    stack: Vec<ValueId>,
+    variables: BTreeMap<Variable, ValueId>,
+}
+
+impl Frame {
+    fn pop(&mut self, offset: u32) -> ValueId {
+        self.stack
+            .pop()
+            .unwrap_or_else(|| panic!("expected stack to contain a value at offset {}", offset))
+    }
+
+    fn peek(&self, n: usize) -> ValueId {
+        self.stack[self.stack.len() - n]
+    }
+
+    // Slots of the frame, so that phis can be created and filled in the same order for all predecessors
+    fn slots(&self) -> Vec<ValueId> {
+        self.stack.iter().chain(self.variables.values()).copied().collect()
+    }
+
+    fn slots_mut(&mut self) -> Vec<&mut ValueId> {
+        self.stack.iter_mut().chain(self.variables.values_mut()).collect()
+    }
+}
+
+// How a block is left, with the frame each successor is entered with
+type Exit = (Terminator, Vec<(BlockIndex, Frame)>);
+
+// Builds the SSA form of a code object from its control-flow graph. Blocks are walked in reverse postorder,
+// so that all predecessors of a block except those of loop back edges are walked before it. A block entered
+// with different values, or from a back edge, gets phis for its slots, those that turn out to always have
+// the same value being removed in the end.
+// Exception handlers are left out, as exceptions end the program for now. Types are inferred once the functions
+// the code calls are built, see Program::infer_types, and checked against those of the inference over the bytecode.
+pub struct IrBuilder<'a> {
+    version: PythonVersion,
+    code: &'a CodeBlock,
+    cfg: &'a ControlFlowGraph,
+    liveness: &'a Liveness,
+    types: &'a TypeInference,
+    scope: &'a Scope<'a>,
+    function: Function,
+    blocks: Vec<Option<BlockId>>,                     // IR block of every reachable bytecode block
+    exits: BTreeMap<(BlockIndex, BlockIndex), Frame>, // frames of the edges walked so far
+    loop_phis: Vec<(BlockIndex, Vec<ValueId>)>,       // phis waiting for the values of back edges
+    undefined: BTreeMap<Variable, ValueId>,           // values of the variables before they are assigned
+}
+
+impl<'a> IrBuilder<'a> {
+    pub fn new(
+        version: PythonVersion,
+        code: &'a CodeBlock,
+        cfg: &'a ControlFlowGraph,
+        liveness: &'a Liveness,
+        types: &'a TypeInference,
+        scope: &'a Scope<'a>,
+    ) -> Self {
+        IrBuilder {
+            version,
+            code,
+            cfg,
+            liveness,
+            types,
+            scope,
+            function: Function::new(code.get_name(), param_count(code)),
+            blocks: vec![None; cfg.blocks.len()],
+            exits: BTreeMap::new(),
+            loop_phis: Vec::new(),
+            undefined: BTreeMap::new(),
+        }
+    }
+
+    pub fn build(mut self) -> CompileResult<Function> {
+        // The entry block holds the values of the variables before they are assigned
+        let entry = self.function.add_block();
+        let variables: BTreeSet<Variable> = self
+            .code
+            .get_instructions()
+            .iter()
+            .filter_map(|instruction| variable_access(&instruction.op))
+            .map(|(variable, _)| variable)
+            .collect();
+        let mut start = Frame {
+            stack: Vec::new(),
+            variables: BTreeMap::new(),
+        };
+        for variable in variables {
+            let undefined = self.function.add_value(entry, Inst::Undefined(variable.clone()));
+            start.variables.insert(variable.clone(), undefined);
+            self.undefined.insert(variable, undefined);
+        }
+        let varnames = self.code.get_varnames();
+        // The tuple and dict of the variable arguments are built by the calls, but have no native values yet
+        for name in &varnames[named_param_count(self.code)..param_count(self.code)] {
+            let read = self.code.get_instructions().iter().any(|instruction| match variable_access(&instruction.op) {
+                Some((Variable::Fast(variable), Access::Load | Access::LoadAndClear)) => variable == *name,
+                _ => false,
+            });
+            if read {
+                return Err(self.error(CompileErrorKind::VariableArguments(name.clone())));
+            }
+        }
+        for (idx, name) in varnames.iter().take(param_count(self.code)).enumerate() {
+            let param = self.function.add_value(entry, Inst::Param(idx));
+            start.variables.insert(Variable::Fast(name.clone()), param);
+        }
+        // The module is run as the main program
+        let module_name = Variable::Name(String::from("__name__"));
+        if self.code.is_module() && start.variables.contains_key(&module_name) {
+            if self.scope.is_shared(&module_name) {
+                return Err(self.error(CompileErrorKind::ModuleAttribute(String::from("__name__"))));
+            }
+            let value = self.function.add_value(entry, Inst::Const(Var::Unicode(String::from(MAIN_MODULE))));
+            start.variables.insert(module_name, value);
+        }
+        if self.cfg.blocks.is_empty() {
+            return Ok(self.function);
+        }
+
+        let order = self.normal_order();
+        for block in &order {
+            self.blocks[*block] = Some(self.function.add_block());
+        }
+        self.function.blocks[entry].terminator = Terminator::Jump(self.ir_block(self.cfg.entry()));
+
+        for block in order {
+            // The first bytecode block may also be the header of a loop
+            let incoming = if block == self.cfg.entry() {
+                vec![(entry, start.clone())]
+            } else {
+                Vec::new()
+            };
+            let Some(frame) = self.enter_from_predecessors(block, incoming) else {
+                continue;
+            };
+            let (terminator, edges) = self.walk(block, frame)?;
+            let ir_block = self.ir_block(block);
+            self.function.blocks[ir_block].terminator = terminator;
+            for (successor, frame) in edges {
+                self.exits.insert((block, successor), frame);
+            }
+        }
+
+        self.complete_loop_phis();
+        self.remove_trivial_phis();
+        self.function.bytecode_return_type = self.types.return_type;
+        Ok(self.function)
+    }
+
+    fn ir_block(&self, block: BlockIndex) -> BlockId {
+        self.blocks[block].expect("block to be reachable")
+    }
+
+    // Reverse postorder of the blocks reachable without exceptions
+    fn normal_order(&self) -> Vec<BlockIndex> {
+        let mut reachable = vec![false; self.cfg.blocks.len()];
+        let mut worklist = vec![self.cfg.entry()];
+        reachable[self.cfg.entry()] = true;
+        while let Some(block) = worklist.pop() {
+            for (successor, kind) in &self.cfg.blocks[block].successors {
+                if *kind != EdgeKind::Exception && !reachable[*successor] {
+                    reachable[*successor] = true;
+                    worklist.push(*successor);
+                }
+            }
+        }
+        self.cfg
+            .reverse_postorder()
+            .into_iter()
+            .filter(|block| reachable[*block])
+            .collect()
+    }
+
+    // Frame the block starts with, None if none of the edges into it is taken
+    fn enter_from_predecessors(&mut self, block: BlockIndex, mut incoming: Vec<(BlockId, Frame)>) -> Option<Frame> {
+        let mut has_back_edge = false;
+        for (predecessor, kind) in &self.cfg.blocks[block].predecessors {
+            if *kind == EdgeKind::Exception || self.blocks[*predecessor].is_none() {
+                continue;
+            }
+            match self.exits.get(&(*predecessor, block)) {
+                Some(frame) => incoming.push((self.ir_block(*predecessor), frame.clone())),
+                // Edges of walked blocks are known, so a missing one is a back edge or isn't taken
+                None => has_back_edge |= self.cfg.is_back_edge(*predecessor, block),
+            }
+        }
+        self.enter(block, incoming, has_back_edge)
+    }
+
+    fn enter(&mut self, block: BlockIndex, incoming: Vec<(BlockId, Frame)>, has_back_edge: bool) -> Option<Frame> {
+        let (_, first) = incoming.first()?;
+        if !has_back_edge && incoming.iter().all(|(_, frame)| frame == first) {
+            return Some(first.clone());
+        }
+        if incoming.iter().any(|(_, frame)| frame.stack.len() != first.stack.len()) {
+            panic!("expected block {} to be entered with the same stack depth on all paths", block);
+        }
+
+        let ir_block = self.ir_block(block);
+        let mut frame = first.clone();
+        let mut phis: Vec<ValueId> = Vec::new();
+        for (i, slot) in frame.slots_mut().into_iter().enumerate() {
+            let values: Vec<(BlockId, ValueId)> = incoming
+                .iter()
+                .map(|(predecessor, frame)| (*predecessor, frame.slots()[i]))
+                .collect();
+            let phi = self.function.add_value(ir_block, Inst::Phi(values));
+            *slot = phi;
+            phis.push(phi);
+        }
+        if has_back_edge {
+            self.loop_phis.push((block, phis));
+        }
+        Some(frame)
+    }
+
+    // Adds the values of the back edges to the phis of loop headers, once all blocks are walked
+    fn complete_loop_phis(&mut self) {
+        for (block, phis) in std::mem::take(&mut self.loop_phis) {
+            for (predecessor, kind) in &self.cfg.blocks[block].predecessors {
+                if *kind == EdgeKind::Exception || !self.cfg.is_back_edge(*predecessor, block) {
+                    continue;
+                }
+                let Some(frame) = self.exits.get(&(*predecessor, block)) else {
+                    continue;
+                };
+                let ir_predecessor = self.ir_block(*predecessor);
+                for (phi, value) in phis.iter().zip(frame.slots()) {
+                    if let Inst::Phi(incoming) = &mut self.function.values[*phi].inst {
+                        incoming.push((ir_predecessor, value));
+                    }
+                }
+            }
+        }
+    }
+
+    // Replaces phis whose incoming values are all the same value, or the phi itself, with that value.
+    // Replacing one may make others trivial, so this is repeated until none is left.
+    fn remove_trivial_phis(&mut self) {
+        let mut replacements: Vec<ValueId> = (0..self.function.values.len()).collect();
+        let resolve = |replacements: &[ValueId], mut value: ValueId| {
+            while replacements[value] != value {
+                value = replacements[value];
+            }
+            value
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in 0..self.function.blocks.len() {
+                for phi in self.function.blocks[block].values.clone() {
+                    let Inst::Phi(incoming) = self.function.inst(phi) else {
+                        continue;
+                    };
+                    if replacements[phi] != phi {
+                        continue;
+                    }
+                    let sources: BTreeSet<ValueId> = incoming
+                        .iter()
+                        .map(|(_, value)| resolve(&replacements, *value))
+                        .filter(|value| *value != phi)
+                        .collect();
+                    if sources.len() == 1 {
+                        replacements[phi] = *sources.first().expect("phi to have a source");
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        for block in &mut self.function.blocks {
+            block.values.retain(|value| replacements[*value] == *value);
+        }
+        self.function.replace_values(&replacements);
+    }
+
+    // Computes the values of the operations of the block, from the frame it's entered with
+    fn walk(&mut self, block: BlockIndex, mut frame: Frame) -> CompileResult<Exit> {
+        let instructions = self.code.get_instructions();
+        let range = self.cfg.blocks[block].instructions.clone();
+        let ir_block = self.ir_block(block);
+        let mut kw_names: Vec<Name> = Vec::new(); // set by KwNames for the next Call
+
+        for idx in range.clone() {
+            let instruction = &instructions[idx];
+            let offset = instruction.offset;
+            self.record_bytecode_types(idx, &frame);
+            match &instruction.op {
+                Operation::LoadConst(constant) => {
+                    let inst = match self.code.get_consts()[constant.index as usize] {
+                        Var::Code(code) => match self.scope.function_of(code) {
+                            Some(function) => Inst::Code(function),
+                            None => return Err(self.error(CompileErrorKind::NestedFunction(code.get_name()))),
+                        },
+                        _ => Inst::Const(constant.value.clone()),
+                    };
+                    let value = self.function.add_value(ir_block, inst);
+                    frame.stack.push(value);
+                }
+                Operation::LoadName(name) | Operation::LoadFast(name) => {
+                    let (variable, _) = variable_access(&instruction.op).expect("load to access a variable");
+                    if self.scope.is_shared(&variable) {
+                        let value = self.load_global(ir_block, name)?;
+                        frame.stack.push(value);
+                        continue;
+                    }
+                    // Names not assigned by the code fall back to the builtins
+                    let builtin = matches!(variable, Variable::Name(_)) && builtins::is_python_builtin(name);
+                    if self.is_unassigned(idx) {
+                        if builtin {
+                            let value = self.function.add_value(ir_block, Inst::Builtin(name.clone()));
+                            frame.stack.push(value);
+                            continue;
+                        }
+                        return Ok((Terminator::Error(self.unassigned_error(&variable)), Vec::new()));
+                    }
+                    let value = frame.variables[&variable];
+                    if self.may_be_unassigned(idx) {
+                        if builtin {
+                            return Err(self.error(CompileErrorKind::ShadowedBuiltin(name.clone())));
+                        }
+                        let check = Inst::CheckAssigned(value, self.unassigned_error(&variable));
+                        self.function.add_value(ir_block, check);
+                    }
+                    if let Inst::Undefined(_) = self.function.inst(value) {
+                        return Err(self.error(CompileErrorKind::ModuleAttribute(name.clone())));
+                    }
+                    frame.stack.push(value);
+                }
+                Operation::StoreName(name) | Operation::StoreFast(name) => {
+                    let (variable, _) = variable_access(&instruction.op).expect("store to access a variable");
+                    let value = frame.pop(offset);
+                    // Values never read are dropped, leaving the variable unassigned. Functions may read the
+                    // shared ones at any call.
+                    if self.liveness.is_dead_store(idx) && !self.scope.is_shared(&variable) {
+                        let undefined = self.undefined[&variable];
+                        frame.variables.insert(variable, undefined);
+                        continue;
+                    }
+                    self.function.stores.push((variable.clone(), value));
+                    if self.scope.is_shared(&variable) {
+                        self.function.add_value(ir_block, Inst::StoreGlobal(name.clone(), value));
+                    } else {
+                        frame.variables.insert(variable, value);
+                    }
+                }
+                Operation::DeleteName(name) | Operation::DeleteFast(name) => {
+                    let (variable, _) = variable_access(&instruction.op).expect("delete to access a variable");
+                    if self.may_be_unassigned(idx) && !self.scope.is_shared(&variable) {
+                        let check = Inst::CheckAssigned(frame.variables[&variable], self.unassigned_error(&variable));
+                        self.function.add_value(ir_block, check);
+                    }
+                    let undefined = self.undefined[&variable];
+                    self.function.stores.push((variable.clone(), undefined));
+                    if self.scope.is_shared(&variable) {
+                        self.function.add_value(ir_block, Inst::DeleteGlobal(name.clone()));
+                    } else {
+                        frame.variables.insert(variable, undefined);
+                    }
+                }
+                // Names the module doesn't assign are builtins, except __name__
+                Operation::LoadGlobal { name, push_null } => {
+                    let value = match self.scope.globals.get(name) {
+                        Some(Some(function)) => self.function.add_value(ir_block, function.clone()),
+                        _ if self.scope.variables.contains(name) => self.load_global(ir_block, name)?,
+                        _ if name == "__name__" => {
+                            let inst = Inst::Const(Var::Unicode(String::from(MAIN_MODULE)));
+                            self.function.add_value(ir_block, inst)
+                        }
+                        _ if builtins::is_python_builtin(name) => {
+                            self.function.add_value(ir_block, Inst::Builtin(name.clone()))
+                        }
+                        _ => {
+                            let message = format!("NameError: name '{}' is not defined", name);
+                            return Ok((Terminator::Error(message), Vec::new()));
+                        }
+                    };
+                    // The NULL goes below the global, and above it since 3.13
+                    if *push_null && !self.version.at_least(3, 13) {
+                        frame.stack.push(self.function.add_value(ir_block, Inst::Null));
+                    }
+                    frame.stack.push(value);
+                    if *push_null && self.version.at_least(3, 13) {
+                        frame.stack.push(self.function.add_value(ir_block, Inst::Null));
+                    }
+                }
+
+                Operation::StoreGlobal(name) => {
+                    let value = frame.pop(offset);
+                    self.function.add_value(ir_block, Inst::StoreGlobal(name.clone(), value));
+                }
+                Operation::DeleteGlobal(name) => {
+                    self.function.add_value(ir_block, Inst::DeleteGlobal(name.clone()));
+                }
+
+                Operation::UnaryOp(op) => {
+                    let operand = frame.pop(offset);
+                    let value = self.function.add_value(ir_block, Inst::Unary(*op, operand));
+                    frame.stack.push(value);
+                }
+                Operation::BinaryOp(op) | Operation::InplaceOp(op) => {
+                    let rhs = frame.pop(offset);
+                    let lhs = frame.pop(offset);
+                    let inst = Inst::Binary {
+                        op: *op,
+                        inplace: matches!(instruction.op, Operation::InplaceOp(_)),
+                        lhs,
+                        rhs,
+                    };
+                    let value = self.function.add_value(ir_block, inst);
+                    frame.stack.push(value);
+                }
+
+                Operation::CompareOp(kind) => {
+                    let rhs = frame.pop(offset);
+                    let lhs = frame.pop(offset);
+                    let value = self.function.add_value(ir_block, Inst::Compare(*kind, lhs, rhs));
+                    frame.stack.push(value);
+                }
+                Operation::ToBool => {
+                    let operand = frame.pop(offset);
+                    let value = self.function.add_value(ir_block, Inst::ToBool(operand));
+                    frame.stack.push(value);
+                }
+
+                Operation::PopTop => {
+                    frame.pop(offset);
+                }
+                Operation::Copy(n) => frame.stack.push(frame.peek(*n as usize)),
+                Operation::DupTopTwo => {
+                    frame.stack.push(frame.peek(2));
+                    frame.stack.push(frame.peek(2));
+                }
+                Operation::Swap(n) => {
+                    let len = frame.stack.len();
+                    frame.stack.swap(len - 1, len - *n as usize);
+                }
+                Operation::Rotate(n) => {
+                    let top = frame.pop(offset);
+                    let idx = frame.stack.len() + 1 - *n as usize;
+                    frame.stack.insert(idx, top);
+                }
+                Operation::PushNull => {
+                    let value = self.function.add_value(ir_block, Inst::Null);
+                    frame.stack.push(value);
+                }
+                Operation::CallFunction(arg_count) | Operation::Call(arg_count) => {
+                    let args = frame.stack.split_off(frame.stack.len() - *arg_count as usize);
+                    let callee = self.pop_callee(&mut frame, offset);
+                    let (args, keywords) = split_keywords(args, std::mem::take(&mut kw_names));
+                    match self.call(ir_block, callee, args, keywords)? {
+                        Ok(value) => frame.stack.push(value),
+                        Err(message) => return Ok((Terminator::Error(message), Vec::new())),
+                    }
+                }
+                // The keyword names are a constant tuple, passed above the arguments or before the call in 3.11
+                // and 3.12. The values of the keyword arguments are the last arguments.
+                Operation::KwNames(names) => kw_names = keyword_names(&names.value),
+                Operation::CallFunctionKw(arg_count) | Operation::CallKw(arg_count) => {
+                    let names = frame.pop(offset);
+                    let names = match self.function.inst(names) {
+                        Inst::Const(names) => keyword_names(names),
+                        inst => panic!("expected a tuple of keyword names, found {:?}", inst),
+                    };
+                    let args = frame.stack.split_off(frame.stack.len() - *arg_count as usize);
+                    let callee = self.pop_callee(&mut frame, offset);
+                    let (args, keywords) = split_keywords(args, names);
+                    match self.call(ir_block, callee, args, keywords)? {
+                        Ok(value) => frame.stack.push(value),
+                        Err(message) => return Ok((Terminator::Error(message), Vec::new())),
+                    }
+                }
+                // The arguments are unpacked from a tuple and a dict, which have to be known here
+                Operation::CallFunctionEx { has_kwargs } => {
+                    let kwargs = if *has_kwargs { Some(frame.pop(offset)) } else { None };
+                    let args = frame.pop(offset);
+                    let callee = self.pop_callee(&mut frame, offset);
+                    let Some(args) = self.elements(ir_block, args) else {
+                        return Err(self.error(CompileErrorKind::RuntimeArguments));
+                    };
+                    let keywords = match kwargs.map(|kwargs| self.function.inst(kwargs)) {
+                        Some(Inst::Dict(items)) => items.clone(),
+                        Some(_) => return Err(self.error(CompileErrorKind::RuntimeArguments)),
+                        None => Vec::new(),
+                    };
+                    match self.call(ir_block, callee, args, keywords)? {
+                        Ok(value) => frame.stack.push(value),
+                        Err(message) => return Ok((Terminator::Error(message), Vec::new())),
+                    }
+                }
+                // Up to 3.10 the qualified name is pushed above the code object
+                Operation::MakeFunction(flags) => {
+                    if !self.version.at_least(3, 11) {
+                        frame.pop(offset);
+                    }
+                    let code = frame.pop(offset);
+                    let mut function = match self.function.inst(code) {
+                        Inst::Code(function) => Inst::Function {
+                            function: *function,
+                            defaults: Vec::new(),
+                            kwdefaults: Vec::new(),
+                        },
+                        inst => panic!("expected a code object to make a function from, found {:?}", inst),
+                    };
+                    for flag in [FUNCTION_CLOSURE, FUNCTION_ANNOTATIONS, FUNCTION_KWDEFAULTS, FUNCTION_DEFAULTS] {
+                        if flags & flag != 0 {
+                            let attribute = frame.pop(offset);
+                            self.set_function_attribute(&mut function, flag, attribute)?;
+                        }
+                    }
+                    let value = self.function.add_value(ir_block, function);
+                    frame.stack.push(value);
+                }
+                Operation::SetFunctionAttribute(flag) => {
+                    let function = frame.pop(offset);
+                    let attribute = frame.pop(offset);
+                    let mut function = self.function.inst(function).clone();
+                    self.set_function_attribute(&mut function, *flag, attribute)?;
+                    let value = self.function.add_value(ir_block, function);
+                    frame.stack.push(value);
+                }
+
+                Operation::BuildTuple(count) | Operation::BuildList(count) => {
+                    let items = frame.stack.split_off(frame.stack.len() - *count as usize);
+                    let inst = match instruction.op {
+                        Operation::BuildTuple(_) => Inst::Tuple(items),
+                        _ => Inst::List(items),
+                    };
+                    let value = self.function.add_value(ir_block, inst);
+                    frame.stack.push(value);
+                }
+                Operation::ListExtend(n) => {
+                    let iterable = frame.pop(offset);
+                    let slot = frame.stack.len() - *n as usize;
+                    let mut items = match self.function.inst(frame.stack[slot]) {
+                        Inst::List(items) => items.clone(),
+                        inst => panic!("expected a list to extend, found {:?}", inst),
+                    };
+                    let Some(extension) = self.elements(ir_block, iterable) else {
+                        return Err(self.error(CompileErrorKind::RuntimeArguments));
+                    };
+                    items.extend(extension);
+                    frame.stack[slot] = self.function.add_value(ir_block, Inst::List(items));
+                }
+                Operation::ListToTuple => {
+                    let list = frame.pop(offset);
+                    let items = match self.function.inst(list) {
+                        Inst::List(items) => items.clone(),
+                        inst => panic!("expected a list to convert to a tuple, found {:?}", inst),
+                    };
+                    let value = self.function.add_value(ir_block, Inst::Tuple(items));
+                    frame.stack.push(value);
+                }
+                // Up to 3.8 the arguments of CALL_FUNCTION_EX are concatenated by dedicated operations
+                Operation::Other {
+                    opcode: Opcode::BuildTupleUnpackWithCall,
+                    arg,
+                } => {
+                    let iterables = frame.stack.split_off(frame.stack.len() - *arg as usize);
+                    let mut items = Vec::new();
+                    for iterable in iterables {
+                        let Some(elements) = self.elements(ir_block, iterable) else {
+                            return Err(self.error(CompileErrorKind::RuntimeArguments));
+                        };
+                        items.extend(elements);
+                    }
+                    let value = self.function.add_value(ir_block, Inst::Tuple(items));
+                    frame.stack.push(value);
+                }
+                Operation::Other {
+                    opcode: Opcode::BuildMapUnpackWithCall,
+                    arg,
+                } => {
+                    let callee = self.callee_below(&frame, *arg as usize + 2);
+                    let dicts = frame.stack.split_off(frame.stack.len() - *arg as usize);
+                    let mut items = Vec::new();
+                    for dict in dicts {
+                        if let Err(message) = self.merge(&mut items, dict, Some(callee))? {
+                            return Ok((Terminator::Error(message), Vec::new()));
+                        }
+                    }
+                    let value = self.function.add_value(ir_block, Inst::Dict(items));
+                    frame.stack.push(value);
+                }
+                // Keys have to be constant strings, as only keyword arguments are built from dicts
+                Operation::BuildMap(count) => {
+                    let pairs = frame.stack.split_off(frame.stack.len() - 2 * *count as usize);
+                    let mut items = Vec::new();
+                    for pair in pairs.chunks(2) {
+                        let key = match self.function.inst(pair[0]) {
+                            Inst::Const(key) => key.as_string(),
+                            _ => None,
+                        };
+                        let Some(key) = key else {
+                            return Err(self.error(CompileErrorKind::DictKeys));
+                        };
+                        insert(&mut items, key, pair[1]);
+                    }
+                    let value = self.function.add_value(ir_block, Inst::Dict(items));
+                    frame.stack.push(value);
+                }
+                Operation::BuildConstKeyMap(count) => {
+                    let keys = frame.pop(offset);
+                    let keys = match self.function.inst(keys) {
+                        Inst::Const(keys) => keys.as_tuple().cloned().unwrap_or_default(),
+                        inst => panic!("expected a tuple of keys, found {:?}", inst),
+                    };
+                    let values = frame.stack.split_off(frame.stack.len() - *count as usize);
+                    let mut items = Vec::new();
+                    for (key, value) in keys.iter().zip(values) {
+                        let Some(key) = key.as_string() else {
+                            return Err(self.error(CompileErrorKind::DictKeys));
+                        };
+                        insert(&mut items, key, value);
+                    }
+                    let value = self.function.add_value(ir_block, Inst::Dict(items));
+                    frame.stack.push(value);
+                }
+                // DICT_MERGE builds the keyword arguments of CALL_FUNCTION_EX, which can't be given twice
+                Operation::DictMerge(n) | Operation::DictUpdate(n) => {
+                    let update = frame.pop(offset);
+                    let slot = frame.stack.len() - *n as usize;
+                    let mut items = match self.function.inst(frame.stack[slot]) {
+                        Inst::Dict(items) => items.clone(),
+                        inst => panic!("expected a dict to update, found {:?}", inst),
+                    };
+                    let callee = match instruction.op {
+                        Operation::DictMerge(_) => Some(self.callee_below(&frame, *n as usize + 2)),
+                        _ => None,
+                    };
+                    if let Err(message) = self.merge(&mut items, update, callee)? {
+                        return Ok((Terminator::Error(message), Vec::new()));
+                    }
+                    frame.stack[slot] = self.function.add_value(ir_block, Inst::Dict(items));
+                }
+                Operation::GetIter => {
+                    let iterable = frame.pop(offset);
+                    let value = self.function.add_value(ir_block, Inst::GetIter(iterable));
+                    frame.stack.push(value);
+                }
+
+                Operation::ReturnValue => {
+                    let value = frame.pop(offset);
+                    return Ok((Terminator::Return(value), Vec::new()));
+                }
+                Operation::ReturnConst(constant) => {
+                    let value = self.function.add_value(ir_block, Inst::Const(constant.value.clone()));
+                    return Ok((Terminator::Return(value), Vec::new()));
+                }
+                Operation::Jump(target) => {
+                    let successor = self.cfg.block_of(*target);
+                    return Ok((Terminator::Jump(self.ir_block(successor)), vec![(successor, frame)]));
+                }
+                Operation::PopJumpIfFalse(target) | Operation::PopJumpIfTrue(target) => {
+                    let condition = frame.pop(offset);
+                    let (jump, next) = (self.cfg.block_of(*target), block + 1);
+                    let jump_if = matches!(instruction.op, Operation::PopJumpIfTrue(_));
+                    let terminator = self.branch(condition, jump_if, jump, next);
+                    return Ok((terminator, vec![(jump, frame.clone()), (next, frame)]));
+                }
+                // The iterator stays on the stack below the value while looping. Once exhausted it's popped,
+                // or since 3.12 left for END_FOR along with a placeholder for the value.
+                Operation::ForIter(target) => {
+                    let iterator = frame.pop(offset);
+                    let has_next = self.function.add_value(ir_block, Inst::IterHasNext(iterator));
+                    let value = self.function.add_value(ir_block, Inst::IterValue(iterator));
+                    let advanced = self.function.add_value(ir_block, Inst::IterAdvance(iterator));
+                    let (exhausted, next) = (self.cfg.block_of(*target), block + 1);
+                    let mut exhausted_frame = frame.clone();
+                    if instruction.op.stack_effect(&self.version, true) == Some(1) {
+                        exhausted_frame.stack.push(iterator);
+                        exhausted_frame.stack.push(self.function.add_value(ir_block, Inst::Null));
+                    }
+                    frame.stack.push(advanced);
+                    frame.stack.push(value);
+                    let terminator = Terminator::Branch {
+                        condition: has_next,
+                        then: self.ir_block(next),
+                        otherwise: self.ir_block(exhausted),
+                    };
+                    return Ok((terminator, vec![(exhausted, exhausted_frame), (next, frame)]));
+                }
+                Operation::EndFor => {
+                    let count = -instruction.op.stack_effect(&self.version, false).unwrap_or(0);
+                    for _ in 0..count {
+                        frame.pop(offset);
+                    }
+                }
+                // The value is kept as the result of the and/or expression when jumping, popped otherwise
+                Operation::JumpIfFalseOrPop(target) | Operation::JumpIfTrueOrPop(target) => {
+                    let condition = frame.peek(1);
+                    let (jump, next) = (self.cfg.block_of(*target), block + 1);
+                    let jump_if = matches!(instruction.op, Operation::JumpIfTrueOrPop(_));
+                    let terminator = self.branch(condition, jump_if, jump, next);
+                    let jump_frame = frame.clone();
+                    frame.pop(offset);
+                    return Ok((terminator, vec![(jump, jump_frame), (next, frame)]));
+                }
+
+                // Operations with no effect on the values
+                Operation::Nop | Operation::Precall(_) => {}
+                op => return Err(self.error(CompileErrorKind::Operation(format!("{:?}", op)))),
+            }
+        }
+
+        let successor = block + 1;
+        Ok((Terminator::Jump(self.ir_block(successor)), vec![(successor, frame)]))
+    }
+
+    // Compile error pointing at the function being built
+    fn error(&self, kind: CompileErrorKind) -> CompileError {
+        CompileError::new(&self.function.name, kind)
+    }
+
+    // Types the inference over the bytecode gives the values of the frame before the instruction. Variables
+    // not assigned yet, or kept in globals, have no value there.
+    fn record_bytecode_types(&mut self, instruction: InstructionIndex, frame: &Frame) {
+        let Some(state) = self.types.before(instruction) else {
+            return;
+        };
+        let mut typed: Vec<(ValueId, InferredType)> = Vec::new();
+        if state.stack.len() == frame.stack.len() {
+            typed.extend(frame.stack.iter().copied().zip(state.stack.iter().copied()));
+        }
+        for (variable, value) in &frame.variables {
+            let (Variable::Name(name) | Variable::Fast(name)) = variable;
+            let assigned = !matches!(self.function.inst(*value), Inst::Undefined(_));
+            if let Some(value_type) = state.variables.get(name).filter(|_| assigned) {
+                typed.push((*value, *value_type));
+            }
+        }
+        for (value, value_type) in typed {
+            self.function
+                .bytecode_types
+                .entry(value)
+                .and_modify(|own| *own = own.join(value_type))
+                .or_insert(value_type);
+        }
+    }
+
+    // Value of a module variable shared with the functions. While it's unassigned, a builtin of the same name
+    // would be read instead, which isn't supported.
+    fn load_global(&mut self, block: BlockId, name: &Name) -> CompileResult<ValueId> {
+        if builtins::is_python_builtin(name) {
+            return Err(self.error(CompileErrorKind::ShadowedBuiltin(name.clone())));
+        }
+        Ok(self.function.add_value(block, Inst::LoadGlobal(name.clone())))
+    }
+
+    // Pops the callable of a call, along with the NULL placed below it since 3.11, or above it since 3.13
+    fn pop_callee(&self, frame: &mut Frame, offset: u32) -> ValueId {
+        let callee = frame.pop(offset);
+        if let Inst::Null = self.function.inst(callee) {
+            return frame.pop(offset);
+        }
+        if frame
+            .stack
+            .last()
+            .is_some_and(|value| matches!(self.function.inst(*value), Inst::Null))
+        {
+            frame.pop(offset);
+        }
+        callee
+    }
+
+    // Callable of a call being prepared, n slots from the top of the stack, or below the NULL there since 3.13
+    fn callee_below(&self, frame: &Frame, n: usize) -> ValueId {
+        let callee = frame.peek(n);
+        match self.function.inst(callee) {
+            Inst::Null => frame.peek(n + 1),
+            _ => callee,
+        }
+    }
+
+    // Value of the call, or the TypeError it raises. Arguments of calls to the functions of the program are
+    // bound to their parameters here, so that the calls pass one argument for each parameter.
+    fn call(
+        &mut self,
+        block: BlockId,
+        callee: ValueId,
+        args: Vec<ValueId>,
+        keywords: Vec<(Name, ValueId)>,
+    ) -> CompileResult<Result<ValueId, String>> {
+        let inst = match self.function.inst(callee).clone() {
+            Inst::Function {
+                function,
+                defaults,
+                kwdefaults,
+            } => match self.bind_arguments(block, function, (&defaults, &kwdefaults), args, keywords) {
+                Ok(args) => Inst::Call { callee, args },
+                Err(message) => return Ok(Err(message)),
+            },
+            Inst::Builtin(name) if !keywords.is_empty() => {
+                return Err(self.error(CompileErrorKind::BuiltinKeywords(name)))
+            }
+            _ if !keywords.is_empty() => return Err(self.error(CompileErrorKind::RuntimeCallee)),
+            Inst::Builtin(name) if name == builtins::RANGE => match self.range(block, &args) {
+                Ok(range) => range,
+                Err(message) => return Ok(Err(message)),
+            },
+            _ => Inst::Call { callee, args },
+        };
+        Ok(Ok(self.function.add_value(block, inst)))
+    }
+
+    // Sets the default values of the function, which have to be constants. Annotations have no effect on the
+    // code of the function.
+    fn set_function_attribute(&self, function: &mut Inst, flag: u32, attribute: ValueId) -> CompileResult<()> {
+        let Inst::Function {
+            function,
+            defaults,
+            kwdefaults,
+        } = function
+        else {
+            panic!("expected a function to set the attribute of, found {:?}", function);
+        };
+        let name = self.scope.functions[*function].get_name();
+        let runtime_defaults = || self.error(CompileErrorKind::RuntimeDefaults(name.clone()));
+        match flag {
+            FUNCTION_DEFAULTS => {
+                let values = match self.function.inst(attribute) {
+                    Inst::Const(constant) => constant.as_tuple().cloned(),
+                    Inst::Tuple(items) => items.iter().map(|item| self.constant(*item)).collect(),
+                    _ => None,
+                };
+                *defaults = values.ok_or_else(runtime_defaults)?;
+            }
+            FUNCTION_KWDEFAULTS => {
+                let values = match self.function.inst(attribute) {
+                    Inst::Dict(items) => items
+                        .iter()
+                        .map(|(name, value)| Some((name.clone(), self.constant(*value)?)))
+                        .collect(),
+                    _ => None,
+                };
+                *kwdefaults = values.ok_or_else(runtime_defaults)?;
+            }
+            FUNCTION_ANNOTATIONS => {}
+            FUNCTION_CLOSURE => return Err(self.error(CompileErrorKind::NestedFunction(name.clone()))),
+            _ => panic!("unknown function attribute {}", flag),
+        }
+        Ok(())
+    }
+
+    fn constant(&self, value: ValueId) -> Option<Var> {
+        match self.function.inst(value) {
+            Inst::Const(constant) => Some(constant.clone()),
+            _ => None,
+        }
+    }
+
+    // Values of the items of a tuple or list known at compile time
+    fn elements(&mut self, block: BlockId, value: ValueId) -> Option<Vec<ValueId>> {
+        match self.function.inst(value) {
+            Inst::Tuple(items) | Inst::List(items) => Some(items.clone()),
+            Inst::Const(constant) => {
+                let items = constant.as_tuple()?.clone();
+                Some(
+                    items
+                        .into_iter()
+                        .map(|item| self.function.add_value(block, Inst::Const(item)))
+                        .collect(),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    // Adds the items of the dict to the items. When merging the keyword arguments of a call to the callee,
+    // keys already given raise a TypeError.
+    fn merge(
+        &self,
+        items: &mut Vec<(Name, ValueId)>,
+        update: ValueId,
+        callee: Option<ValueId>,
+    ) -> CompileResult<Result<(), String>> {
+        let Inst::Dict(update) = self.function.inst(update) else {
+            return Err(self.error(CompileErrorKind::RuntimeArguments));
+        };
+        for (key, value) in update {
+            if let Some(callee) = callee.filter(|_| items.iter().any(|(existing, _)| existing == key)) {
+                return Ok(Err(format!(
+                    "TypeError: {} got multiple values for keyword argument '{}'",
+                    self.describe(callee)?,
+                    key
+                )));
+            }
+            insert(items, key.clone(), *value);
+        }
+        Ok(Ok(()))
+    }
+
+    // Callable as named in errors of calls, functions being qualified by their module since 3.10
+    fn describe(&self, callee: ValueId) -> CompileResult<String> {
+        match self.function.inst(callee) {
+            Inst::Function { function, .. } if self.version.at_least(3, 10) => {
+                Ok(format!("__main__.{}()", self.scope.functions[*function].get_name()))
+            }
+            Inst::Function { function, .. } => Ok(format!("{}()", self.scope.functions[*function].get_name())),
+            Inst::Builtin(name) => Ok(format!("{}()", name)),
+            _ => Err(self.error(CompileErrorKind::RuntimeCallee)),
+        }
+    }
+
+    // Arguments of a call to the function, one for each of its parameters, or the TypeError the call raises.
+    // As in CPython, keywords are matched to the parameters after the positional arguments, and the parameters
+    // left get their default values. Extra positional arguments go into the tuple of *args and unmatched
+    // keywords into the dict of **kwargs, when the function takes them.
+    fn bind_arguments(
+        &mut self,
+        block: BlockId,
+        function: FunctionId,
+        (defaults, kwdefaults): (&[Var], &[(Name, Var)]),
+        args: Vec<ValueId>,
+        keywords: Vec<(Name, ValueId)>,
+    ) -> Result<Vec<ValueId>, String> {
+        let code = self.scope.functions[function];
+        let name = code.get_name();
+        let varnames = code.get_varnames();
+        let params = &varnames[..named_param_count(code)];
+        let (posonly, positional) = (code.co_posonlyargcount as usize, code.co_argcount as usize);
+        let (varargs, varkeywords) = (code.co_flags & CO_VARARGS != 0, code.co_flags & CO_VARKEYWORDS != 0);
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        let missing_error = |kind: &str, missing: &[String]| {
+            format!(
+                "TypeError: {}() missing {} required {} argument{}: {}",
+                name,
+                missing.len(),
+                kind,
+                plural(missing.len()),
+                quoted_list(missing)
+            )
+        };
+
+        let mut bound: Vec<Option<ValueId>> = vec![None; params.len()];
+        for (slot, arg) in bound.iter_mut().zip(args.iter().take(positional)) {
+            *slot = Some(*arg);
+        }
+        let mut extra_keywords = Vec::new();
+        for (keyword, value) in &keywords {
+            let Some(idx) = params.iter().skip(posonly).position(|param| param == keyword) else {
+                if varkeywords {
+                    extra_keywords.push((keyword.clone(), *value));
+                    continue;
+                }
+                let posonly_keywords: Vec<&str> = keywords
+                    .iter()
+                    .map(|(keyword, _)| keyword.as_str())
+                    .filter(|keyword| params[..posonly].iter().any(|param| param == keyword))
+                    .collect();
+                if !posonly_keywords.is_empty() {
+                    return Err(format!(
+                        "TypeError: {}() got some positional-only arguments passed as keyword arguments: '{}'",
+                        name,
+                        posonly_keywords.join(", ")
+                    ));
+                }
+                return Err(format!("TypeError: {}() got an unexpected keyword argument '{}'", name, keyword));
+            };
+            let slot = &mut bound[posonly + idx];
+            if slot.is_some() {
+                return Err(format!("TypeError: {}() got multiple values for argument '{}'", name, keyword));
+            }
+            *slot = Some(*value);
+        }
+
+        if args.len() > positional && !varargs {
+            let kwonly_given = bound[positional..].iter().filter(|slot| slot.is_some()).count();
+            let takes = match defaults.len() {
+                0 => format!("{} positional argument{}", positional, plural(positional)),
+                count => format!("from {} to {} positional arguments", positional - count, positional),
+            };
+            let given = match kwonly_given {
+                0 => format!("{} {}", args.len(), if args.len() == 1 { "was" } else { "were" }),
+                _ => format!(
+                    "{} positional argument{} (and {} keyword-only argument{}) were",
+                    args.len(),
+                    plural(args.len()),
+                    kwonly_given,
+                    plural(kwonly_given)
+                ),
+            };
+            return Err(format!("TypeError: {}() takes {} but {} given", name, takes, given));
+        }
+
+        let required = positional - defaults.len();
+        let missing: Vec<String> = (args.len()..required)
+            .filter(|idx| bound[*idx].is_none())
+            .map(|idx| params[idx].clone())
+            .collect();
+        if !missing.is_empty() {
+            return Err(missing_error("positional", &missing));
+        }
+        for (slot, default) in bound[required..positional].iter_mut().zip(defaults) {
+            if slot.is_none() {
+                *slot = Some(self.function.add_value(block, Inst::Const(default.clone())));
+            }
+        }
+
+        let mut missing = Vec::new();
+        for (slot, param) in bound.iter_mut().zip(params).skip(positional) {
+            if slot.is_some() {
+                continue;
+            }
+            match kwdefaults.iter().find(|(name, _)| name == param) {
+                Some((_, default)) => *slot = Some(self.function.add_value(block, Inst::Const(default.clone()))),
+                None => missing.push(param.clone()),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(missing_error("keyword-only", &missing));
+        }
+        let mut bound: Vec<ValueId> = bound.into_iter().map(|slot| slot.expect("parameter to be bound")).collect();
+        if varargs {
+            let extra_args = args.get(positional..).unwrap_or_default().to_vec();
+            bound.push(self.function.add_value(block, Inst::Tuple(extra_args)));
+        }
+        if varkeywords {
+            bound.push(self.function.add_value(block, Inst::Dict(extra_keywords)));
+        }
+        Ok(bound)
+    }
+
+    // range() with one to three arguments, start defaulting to 0 and step to 1, or the TypeError of the call
+    fn range(&mut self, block: BlockId, args: &[ValueId]) -> Result<Inst, String> {
+        let mut constant = |value: i32| self.function.add_value(block, Inst::Const(Var::Int(value)));
+        match *args {
+            [stop] => Ok(Inst::Range {
+                start: constant(0),
+                stop,
+                step: constant(1),
+            }),
+            [start, stop] => Ok(Inst::Range {
+                start,
+                stop,
+                step: constant(1),
+            }),
+            [start, stop, step] => Ok(Inst::Range { start, stop, step }),
+            [] if self.version.at_least(3, 9) => {
+                Err(String::from("TypeError: range expected at least 1 argument, got 0"))
+            }
+            [] => Err(String::from("TypeError: range expected 1 argument, got 0")),
+            _ => Err(format!("TypeError: range expected at most 3 arguments, got {}", args.len())),
+        }
+    }
+
+    // Branch to the jump target when the condition has the given truth value, to the next block otherwise
+    fn branch(&self, condition: ValueId, jump_if: bool, jump: BlockIndex, next: BlockIndex) -> Terminator {
+        let (jump, next) = (self.ir_block(jump), self.ir_block(next));
+        let (then, otherwise) = if jump_if { (jump, next) } else { (next, jump) };
+        Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        }
+    }
+
+    // Whether the variable read by the instruction is never assigned before it
+    fn is_unassigned(&self, instruction: InstructionIndex) -> bool {
+        self.liveness
+            .definitions_of(instruction)
+            .is_some_and(|definitions| definitions.iter().all(|definition| *definition == Definition::Unassigned))
+    }
+
+    // Whether the variable read or deleted by the instruction is unassigned on some path to it
+    fn may_be_unassigned(&self, instruction: InstructionIndex) -> bool {
+        self.liveness
+            .definitions_of(instruction)
+            .is_some_and(|definitions| definitions.contains(&Definition::Unassigned))
+    }
+
+    // Error raised by reading or deleting the variable while it's unassigned
+    fn unassigned_error(&self, variable: &Variable) -> String {
+        match variable {
+            Variable::Name(name) => format!("NameError: name '{}' is not defined", name),
+            Variable::Fast(name) if self.version.at_least(3, 11) => format!(
+                "UnboundLocalError: cannot access local variable '{}' where it is not associated with a value",
+                name
+            ),
+            Variable::Fast(name) => format!("UnboundLocalError: local variable '{}' referenced before assignment", name),
+        }
+    }
+}
+
+// Operations of the module and of the code objects nested in it
+fn all_operations(module: &CodeBlock) -> Vec<&Operation> {
+    let mut operations = module.get_operations();
+    operations.extend(module.get_nested_operations());
+    operations
+}
+
+// Positional arguments and keyword arguments of a call, the values of the keywords being the last arguments
+fn split_keywords(mut args: Vec<ValueId>, names: Vec<Name>) -> (Vec<ValueId>, Vec<(Name, ValueId)>) {
+    let values = args.split_off(args.len() - names.len());
+    (args, names.into_iter().zip(values).collect())
+}
+
+// Names of the keyword arguments of a call, from the constant tuple of CALL_FUNCTION_KW, KW_NAMES or CALL_KW
+fn keyword_names(names: &Var) -> Vec<Name> {
+    let names = names
+        .as_tuple()
+        .unwrap_or_else(|| panic!("expected a tuple of keyword names, found {:?}", names));
+    names
+        .iter()
+        .map(|name| name.as_string().expect("keyword name to be a string"))
+        .collect()
+}
+
+// Sets the value of the key, which keeps its position if it's already in the dict
+fn insert(items: &mut Vec<(Name, ValueId)>, key: Name, value: ValueId) {
+    match items.iter_mut().find(|(existing, _)| *existing == key) {
+        Some((_, existing)) => *existing = value,
+        None => items.push((key, value)),
+    }
+}
+
+// Parameters of the code object, those of *args and **kwargs following the positional and keyword-only ones
+fn param_count(code: &CodeBlock) -> usize {
+    named_param_count(code) + (code.co_flags & CO_VARARGS != 0) as usize + (code.co_flags & CO_VARKEYWORDS != 0) as usize
+}
+
+// Parameters before those of *args and **kwargs
+pub fn named_param_count(code: &CodeBlock) -> usize {
+    (code.co_argcount + code.co_kwonlyargcount) as usize
+}
+
+// Names as listed in the messages of errors, e.g. 'a', 'b', and 'c'
+fn quoted_list(names: &[String]) -> String {
+    let quoted: Vec<String> = names.iter().map(|name| format!("'{}'", name)).collect();
+    match quoted.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, [first])) => format!("{} and {}", first, last),
+        Some((last, rest)) => format!("{}, and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,338 @@
+use super::version::PythonVersion;
+use std::ops::Range;
+
+// Source location of a range of bytecode. Lines are 1-based, columns 0-based in UTF-8 bytes.
+// Only the location table of 3.11+ has the end line and columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEntry {
+    pub offsets: Range<u32>, // byte offsets in co_code
+    pub line: Option<i32>,   // None for code without a line, e.g. artificial returns
+    pub end_line: Option<i32>,
+    pub column: Option<u32>,
+    pub end_column: Option<u32>,
+}
+
+impl LineEntry {
+    fn new(offsets: Range<u32>, line: Option<i32>) -> Self {
+        LineEntry {
+            offsets,
+            line,
+            end_line: line,
+            column: None,
+            end_column: None,
+        }
+    }
+}
+
+// Decodes co_lnotab (3.8, 3.9), co_linetable (3.10) or the location table (3.11+)
+// into consecutive, non-empty ranges of co_code of the given length
+pub fn decode(
+    version: &PythonVersion,
+    table: &[u8],
+    firstlineno: i32,
+    code_len: u32,
+) -> Result<Vec<LineEntry>, &'static str> {
+    if version.at_least(3, 11) {
+        decode_locations(table, firstlineno)
+    } else if version.at_least(3, 10) {
+        Ok(decode_linetable(table, firstlineno))
+    } else {
+        Ok(decode_lnotab(table, firstlineno, code_len))
+    }
+}
+
+// Pairs of (offset increment, signed line increment), where a line is known at the start of the code
+// and large increments are split into several pairs
+fn decode_lnotab(table: &[u8], firstlineno: i32, code_len: u32) -> Vec<LineEntry> {
+    let mut line_starts: Vec<(u32, i32)> = Vec::new();
+    let mut offset: u32 = 0;
+    let mut line = firstlineno;
+    let mut last_line: Option<i32> = None;
+    for pair in table.chunks_exact(2) {
+        if pair[0] != 0 {
+            if last_line != Some(line) {
+                line_starts.push((offset, line));
+                last_line = Some(line);
+            }
+            offset += pair[0] as u32;
+        }
+        line += pair[1] as i8 as i32;
+    }
+    if last_line != Some(line) {
+        line_starts.push((offset, line));
+    }
+
+    let mut entries: Vec<LineEntry> = Vec::new();
+    for (i, (start, line)) in line_starts.iter().enumerate() {
+        let end = line_starts.get(i + 1).map_or(code_len, |(next_start, _)| *next_start);
+        if *start < end {
+            entries.push(LineEntry::new(*start..end, Some(*line)));
+        }
+    }
+    entries
+}
+
+// Pairs of (range length, signed line increment), where an increment of -128 means no line
+fn decode_linetable(table: &[u8], firstlineno: i32) -> Vec<LineEntry> {
+    let mut entries: Vec<LineEntry> = Vec::new();
+    let mut start: u32 = 0;
+    let mut line = firstlineno;
+    for pair in table.chunks_exact(2) {
+        let end = start + pair[0] as u32;
+        let line_delta = pair[1] as i8;
+        let entry_line = if line_delta == -128 {
+            None
+        } else {
+            line += line_delta as i32;
+            Some(line)
+        };
+        if start < end {
+            entries.push(LineEntry::new(start..end, entry_line));
+        }
+        start = end;
+    }
+    entries
+}
+
+struct LocationReader<'a> {
+    table: &'a [u8],
+    idx: usize,
+}
+
+impl LocationReader<'_> {
+    fn read_byte(&mut self) -> Result<u8, &'static str> {
+        let byte = *self.table.get(self.idx).ok_or("truncated entry")?;
+        self.idx += 1;
+        Ok(byte)
+    }
+
+    // Little endian chunks of 6 bits, where bit 6 marks that another chunk follows
+    fn read_varint(&mut self) -> Result<u32, &'static str> {
+        let mut byte = self.read_byte()?;
+        let mut value = (byte & 0x3F) as u32;
+        let mut shift = 0;
+        while byte & 0x40 != 0 {
+            byte = self.read_byte()?;
+            shift += 6;
+            if shift >= 32 {
+                return Err("varint too long");
+            }
+            value |= ((byte & 0x3F) as u32) << shift;
+        }
+        Ok(value)
+    }
+
+    // Varint with the sign in the lowest bit
+    fn read_signed_varint(&mut self) -> Result<i32, &'static str> {
+        let value = self.read_varint()?;
+        let magnitude = (value >> 1) as i32;
+        Ok(if value & 1 != 0 { -magnitude } else { magnitude })
+    }
+
+    // Column stored as a varint increased by 1, 0 meaning no column
+    fn read_column(&mut self) -> Result<Option<u32>, &'static str> {
+        Ok(self.read_varint()?.checked_sub(1))
+    }
+}
+
+// Entries start with a byte with the highest bit set, holding the kind of the entry (bits 3-6)
+// and the number of code units it covers minus one (bits 0-2), followed by the kind's data
+fn decode_locations(table: &[u8], firstlineno: i32) -> Result<Vec<LineEntry>, &'static str> {
+    const NO_LOCATION: u8 = 15;
+    const LONG: u8 = 14;
+    const NO_COLUMNS: u8 = 13;
+    const ONE_LINE_0: u8 = 10;
+    const ONE_LINE_2: u8 = 12;
+
+    let mut reader = LocationReader { table, idx: 0 };
+    let mut entries: Vec<LineEntry> = Vec::new();
+    let mut start: u32 = 0;
+    let mut line = firstlineno;
+    while reader.idx < table.len() {
+        let first = reader.read_byte()?;
+        if first & 0x80 == 0 {
+            return Err("entry doesn't start with the highest bit set");
+        }
+        let kind = (first >> 3) & 0x0F;
+        let end = start + ((first & 0x07) as u32 + 1) * 2;
+
+        let mut entry = LineEntry::new(start..end, None);
+        match kind {
+            NO_LOCATION => {}
+            LONG => {
+                line += reader.read_signed_varint()?;
+                entry.line = Some(line);
+                entry.end_line = Some(line + reader.read_varint()? as i32);
+                entry.column = reader.read_column()?;
+                entry.end_column = reader.read_column()?;
+            }
+            NO_COLUMNS => {
+                line += reader.read_signed_varint()?;
+                entry.line = Some(line);
+                entry.end_line = Some(line);
+            }
+            ONE_LINE_0..=ONE_LINE_2 => {
+                line += (kind - ONE_LINE_0) as i32;
+                entry.line = Some(line);
+                entry.end_line = Some(line);
+                entry.column = Some(reader.read_byte()? as u32);
+                entry.end_column = Some(reader.read_byte()? as u32);
+            }
+            // Short form, on the previous line with the column split between the kind and the next byte
+            _ => {
+                let second = reader.read_byte()?;
+                let column = kind as u32 * 8 + ((second >> 4) & 0x07) as u32;
+                entry.line = Some(line);
+                entry.end_line = Some(line);
+                entry.column = Some(column);
+                entry.end_column = Some(column + (second & 0x0F) as u32);
+            }
+        }
+        entries.push(entry);
+        start = end;
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PY38: PythonVersion = PythonVersion { major: 3, minor: 8 };
+    const PY310: PythonVersion = PythonVersion { major: 3, minor: 10 };
+    const PY312: PythonVersion = PythonVersion { major: 3, minor: 12 };
+
+    fn located(offsets: Range<u32>, lines: Range<i32>, columns: Range<u32>) -> LineEntry {
+        LineEntry {
+            offsets,
+            line: Some(lines.start),
+            end_line: Some(lines.end),
+            column: Some(columns.start),
+            end_column: Some(columns.end),
+        }
+    }
+
+    // def f(n):
+    //     x = g(
+    //         n,
+    //         1)
+    //     <200 blank lines>
+    //     for i in n:
+    //         try:
+    //             x = (n +
+    //                  i)
+    //         finally:
+    //             pass
+    //     return x
+    #[test]
+    fn lnotab_with_negative_and_split_line_increments() {
+        let lnotab = [
+            0, 1, 2, 1, 2, 1, 2, 254, 4, 127, 0, 77, 8, 1, 2, 1, 2, 1, 2, 255, 8, 3, 4, 1,
+        ];
+        let lines = [
+            (0..2, 3),
+            (2..4, 4),
+            (4..6, 5),
+            (6..10, 3),
+            (10..18, 207),
+            (18..20, 208),
+            (20..22, 209),
+            (22..24, 210),
+            (24..32, 209),
+            (32..36, 212),
+            (36..40, 213),
+        ];
+        let expected: Vec<LineEntry> = lines
+            .into_iter()
+            .map(|(offsets, line)| LineEntry::new(offsets, Some(line)))
+            .collect();
+        assert_eq!(decode(&PY38, &lnotab, 2, 40), Ok(expected));
+    }
+
+    // Same code as above in 3.10, whose ranges are given by their length
+    #[test]
+    fn linetable_with_negative_and_split_line_increments() {
+        let linetable = [
+            2, 1, 2, 1, 2, 1, 4, 254, 0, 127, 8, 77, 2, 1, 2, 1, 2, 1, 6, 255, 4, 3, 4, 1,
+        ];
+        let lines = [
+            (0..2, 3),
+            (2..4, 4),
+            (4..6, 5),
+            (6..10, 3),
+            (10..18, 207),
+            (18..20, 208),
+            (20..22, 209),
+            (22..24, 210),
+            (24..30, 209),
+            (30..34, 212),
+            (34..38, 213),
+        ];
+        let expected: Vec<LineEntry> = lines
+            .into_iter()
+            .map(|(offsets, line)| LineEntry::new(offsets, Some(line)))
+            .collect();
+        assert_eq!(decode(&PY310, &linetable, 2, 38), Ok(expected));
+    }
+
+    // def g(n):
+    //     for i in n:
+    //         yield i
+    #[test]
+    fn linetable_without_line() {
+        let linetable = [2, 128, 8, 1, 8, 1, 4, 255];
+        let expected = vec![
+            LineEntry::new(0..2, None),
+            LineEntry::new(2..10, Some(3)),
+            LineEntry::new(10..18, Some(4)),
+            LineEntry::new(18..22, Some(3)),
+        ];
+        assert_eq!(decode(&PY310, &linetable, 2, 22), Ok(expected));
+    }
+
+    // def f(a):
+    //     try:
+    //         return (a +
+    //                 1)
+    //     finally:
+    //         pass
+    #[test]
+    fn locations_of_all_forms() {
+        let table = [
+            128, 0, 240, 2, 4, 5, 13, 216, 16, 17, 216, 16, 17, 241, 3, 1, 17, 18, 240, 6, 0, 9, 13, 248, 136, 4, 250,
+        ];
+        let expected = vec![
+            located(0..2, 1..1, 0..0),    // short
+            located(2..4, 2..6, 4..12),   // long
+            located(4..6, 3..3, 16..17),  // one line
+            located(6..8, 4..4, 16..17),  // one line
+            located(8..12, 3..4, 16..17), // long, back a line
+            located(12..14, 6..6, 8..12), // long
+            LineEntry::new(14..16, None), // no location
+            located(16..18, 6..6, 8..12), // short
+            LineEntry::new(18..24, None), // no location
+        ];
+        assert_eq!(decode(&PY312, &table, 1, 24), Ok(expected));
+    }
+
+    // Not emitted by the compiler for source code, checked against code.co_positions() instead
+    #[test]
+    fn locations_without_columns() {
+        let table = [128, 0, 233, 4, 232, 3];
+        let expected = vec![
+            located(0..2, 1..1, 0..0),
+            LineEntry::new(2..6, Some(3)),
+            LineEntry::new(6..8, Some(2)),
+        ];
+        assert_eq!(decode(&PY312, &table, 1, 8), Ok(expected));
+    }
+
+    #[test]
+    fn locations_malformed() {
+        assert_eq!(decode(&PY312, &[128, 0, 240, 2, 4], 1, 4), Err("truncated entry"));
+        assert_eq!(
+            decode(&PY312, &[128, 0, 16], 1, 4),
+            Err("entry doesn't start with the highest bit set")
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use super::{
+    builtins,
+    cfg::{BlockIndex, ControlFlowGraph, EdgeKind},
+    code::CodeBlock,
+    operations::{InstructionIndex, Name, Operation},
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// Flags of code objects taking *args and **kwargs
+const CO_VARARGS: i32 = 0x4;
+const CO_VARKEYWORDS: i32 = 0x8;
+
+// Globals set by the import system before the code of a module runs
+const MODULE_ATTRIBUTES: &[&str] = &[
+    "__name__",
+    "__doc__",
+    "__file__",
+    "__cached__",
+    "__loader__",
+    "__package__",
+    "__spec__",
+    "__builtins__",
+    "__annotations__",
+];
+
+// Variable a code object accesses by name: one of its fast locals, or a name of its namespace,
+// i.e. a global of a module or an attribute of a class body
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Variable {
+    Name(Name),
+    Fast(Name),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Load,
+    Store,
+    Delete,
+    LoadAndClear, // reads the value if there is one and unbinds the variable, around inlined comprehensions
+}
+
+// Where the value of a variable at some point may come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Definition {
+    Unassigned,              // not bound yet or deleted
+    Entry,                   // argument, or global set before the module runs
+    Store(InstructionIndex), // assigned by the operation
+}
+
+// Variable read or deleted where it may not be bound, which raises NameError or UnboundLocalError
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnassignedUse {
+    pub variable: Variable,
+    pub instruction: InstructionIndex,
+    pub offset: u32,
+    pub line: Option<i32>,
+    pub definitely: bool, // unassigned on all paths rather than on some of them
+}
+
+impl fmt::Display for UnassignedUse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (kind, name) = match &self.variable {
+            Variable::Name(name) => ("name", name),
+            Variable::Fast(name) => ("local variable", name),
+        };
+        let line = self.line.map_or(String::from("-"), |line| line.to_string());
+        write!(
+            f,
+            "{} {:?} {} used before assignment at line {} (offset {})",
+            kind,
+            name,
+            if self.definitely { "is" } else { "may be" },
+            line,
+            self.offset
+        )
+    }
+}
+
+// Variable read or written by the operation, if any
+pub fn variable_access(op: &Operation) -> Option<(Variable, Access)> {
+    match op {
+        Operation::LoadName(name) => Some((Variable::Name(name.clone()), Access::Load)),
+        Operation::StoreName(name) => Some((Variable::Name(name.clone()), Access::Store)),
+        Operation::DeleteName(name) => Some((Variable::Name(name.clone()), Access::Delete)),
+        Operation::LoadFast(name) => Some((Variable::Fast(name.clone()), Access::Load)),
+        Operation::LoadFastAndClear(name) => Some((Variable::Fast(name.clone()), Access::LoadAndClear)),
+        Operation::StoreFast(name) => Some((Variable::Fast(name.clone()), Access::Store)),
+        Operation::DeleteFast(name) => Some((Variable::Fast(name.clone()), Access::Delete)),
+        _ => None,
+    }
+}
+
+type Definitions = BTreeMap<Variable, BTreeSet<Definition>>;
+
+// Use-def chains and dead stores of the variables of a code object, found by a forward data-flow analysis
+// of the definitions reaching every instruction and a backward one of the variables live after it.
+// Cells and globals of functions are left out, as other code objects may access them at any call.
+#[derive(Debug, Clone)]
+pub struct Liveness {
+    reaching: BTreeMap<InstructionIndex, BTreeSet<Definition>>, // definitions reaching every read or delete
+    dead_stores: BTreeSet<InstructionIndex>,                    // stores of values never read
+    pub unassigned_uses: Vec<UnassignedUse>,
+}
+
+// Joins the definitions into the start of the block, queueing it to be walked again if they changed
+fn join_into(
+    block_definitions: &mut [Option<Definitions>],
+    worklist: &mut Vec<BlockIndex>,
+    block: BlockIndex,
+    definitions: &Definitions,
+) {
+    let changed = match &mut block_definitions[block] {
+        Some(own) => {
+            let mut changed = false;
+            for (variable, other) in definitions {
+                let own_set = own.entry(variable.clone()).or_default();
+                let len = own_set.len();
+                own_set.extend(other.iter().copied());
+                changed |= own_set.len() != len;
+            }
+            changed
+        }
+        None => {
+            block_definitions[block] = Some(definitions.clone());
+            true
+        }
+    };
+    if changed && !worklist.contains(&block) {
+        worklist.push(block);
+    }
+}
+
+// Applies the operation to the definitions reaching it
+fn define(definitions: &mut Definitions, instruction: InstructionIndex, op: &Operation) {
+    match variable_access(op) {
+        Some((variable, Access::Store)) => {
+            definitions.insert(variable, BTreeSet::from([Definition::Store(instruction)]));
+        }
+        Some((variable, Access::Delete | Access::LoadAndClear)) => {
+            definitions.insert(variable, BTreeSet::from([Definition::Unassigned]));
+        }
+        // `from module import *` may assign any name
+        _ if matches!(op, Operation::ImportStar) => {
+            for (variable, variable_definitions) in definitions.iter_mut() {
+                if let Variable::Name(_) = variable {
+                    variable_definitions.insert(Definition::Store(instruction));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Liveness {
+    pub fn analyze(code: &CodeBlock, cfg: &ControlFlowGraph) -> Self {
+        let instructions = code.get_instructions();
+        let is_module = code.is_module();
+
+        // Module globals read by nested code objects, or assigned by them with a global statement
+        let mut escaping: BTreeSet<Variable> = BTreeSet::new();
+        let mut assigned_elsewhere: BTreeSet<Name> = BTreeSet::new();
+        if is_module {
+            for op in code.get_nested_operations() {
+                match op {
+                    Operation::LoadGlobal { name, .. } | Operation::LoadName(name) => {
+                        escaping.insert(Variable::Name(name.clone()));
+                    }
+                    Operation::StoreGlobal(name) | Operation::DeleteGlobal(name) => {
+                        assigned_elsewhere.insert(name.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // All variables are in the map, so that a missing one isn't mistaken for an unassigned one
+        let mut entry: Definitions = instructions
+            .iter()
+            .filter_map(|instruction| variable_access(&instruction.op))
+            .map(|(variable, _)| (variable, BTreeSet::from([Definition::Unassigned])))
+            .collect();
+        let arguments = code.co_argcount
+            + code.co_kwonlyargcount
+            + (code.co_flags & CO_VARARGS != 0) as i32
+            + (code.co_flags & CO_VARKEYWORDS != 0) as i32;
+        let varnames = code.co_varnames.as_tuple().map_or(&[][..], |names| names.as_slice());
+        for name in varnames.iter().take(arguments.max(0) as usize).filter_map(|name| name.as_string()) {
+            entry.insert(Variable::Fast(name), BTreeSet::from([Definition::Entry]));
+        }
+        if is_module {
+            for name in MODULE_ATTRIBUTES {
+                entry.insert(Variable::Name(name.to_string()), BTreeSet::from([Definition::Entry]));
+            }
+        }
+
+        // Reaching definitions at the start of every block, handlers getting those before any instruction
+        // of the blocks they protect
+        let mut block_definitions: Vec<Option<Definitions>> = vec![None; cfg.blocks.len()];
+        let mut worklist: Vec<BlockIndex> = Vec::new();
+        if !cfg.blocks.is_empty() {
+            join_into(&mut block_definitions, &mut worklist, cfg.entry(), &entry);
+        }
+        while let Some(block) = worklist.pop() {
+            let mut definitions = block_definitions[block].clone().expect("blocks to be walked once reached");
+            let successors = cfg.blocks[block].successors.clone();
+            for idx in cfg.blocks[block].instructions.clone() {
+                for (handler, _) in successors.iter().filter(|(_, kind)| *kind == EdgeKind::Exception) {
+                    join_into(&mut block_definitions, &mut worklist, *handler, &definitions);
+                }
+                define(&mut definitions, idx, &instructions[idx].op);
+            }
+            for (successor, _) in successors.iter().filter(|(_, kind)| *kind != EdgeKind::Exception) {
+                join_into(&mut block_definitions, &mut worklist, *successor, &definitions);
+            }
+        }
+
+        // Use-def chains from the definitions reaching every read and delete
+        let mut reaching: BTreeMap<InstructionIndex, BTreeSet<Definition>> = BTreeMap::new();
+        let mut unassigned_uses: Vec<UnassignedUse> = Vec::new();
+        for (block, start_definitions) in block_definitions.iter().enumerate() {
+            let Some(mut definitions) = start_definitions.clone() else {
+                continue;
+            };
+            for idx in cfg.blocks[block].instructions.clone() {
+                let instruction = &instructions[idx];
+                if let Some((variable, access)) = variable_access(&instruction.op) {
+                    if access != Access::Store {
+                        let variable_definitions = definitions[&variable].clone();
+
+                        // Names a module doesn't assign may be builtins, and those of class bodies fall back
+                        // to the globals. Inlined comprehensions clear their variable whether it's bound or not.
+                        let checked = match &variable {
+                            Variable::Name(name) => {
+                                is_module && !builtins::is_python_builtin(name) && !assigned_elsewhere.contains(name)
+                            }
+                            Variable::Fast(_) => access != Access::LoadAndClear,
+                        };
+                        if checked && variable_definitions.contains(&Definition::Unassigned) {
+                            unassigned_uses.push(UnassignedUse {
+                                variable: variable.clone(),
+                                instruction: idx,
+                                offset: instruction.offset,
+                                line: instruction.line,
+                                definitely: variable_definitions.len() == 1,
+                            });
+                        }
+                        reaching.insert(idx, variable_definitions);
+                    }
+                }
+                define(&mut definitions, idx, &instruction.op);
+            }
+        }
+
+        // Variables live at the start of every reachable block, until they stop changing
+        let reachable: Vec<bool> = block_definitions.iter().map(Option::is_some).collect();
+        let mut live_in: Vec<BTreeSet<Variable>> = vec![BTreeSet::new(); cfg.blocks.len()];
+        let mut live_after: Vec<Option<BTreeSet<Variable>>> = vec![None; instructions.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in (0..cfg.blocks.len()).rev().filter(|block| reachable[*block]) {
+                let successors = &cfg.blocks[block].successors;
+                let mut live: BTreeSet<Variable> = BTreeSet::new();
+                for (successor, kind) in successors {
+                    if *kind != EdgeKind::Exception {
+                        live.extend(live_in[*successor].iter().cloned());
+                    }
+                }
+                for idx in cfg.blocks[block].instructions.clone().rev() {
+                    live_after[idx] = Some(live.clone());
+                    // Deletes need the variable to be bound, so they keep the stores before them
+                    match variable_access(&instructions[idx].op) {
+                        Some((variable, Access::Store)) => {
+                            live.remove(&variable);
+                        }
+                        Some((variable, _)) => {
+                            live.insert(variable);
+                        }
+                        None => {}
+                    }
+                    for (handler, kind) in successors {
+                        if *kind == EdgeKind::Exception {
+                            live.extend(live_in[*handler].iter().cloned());
+                        }
+                    }
+                }
+                if live != live_in[block] {
+                    live_in[block] = live;
+                    changed = true;
+                }
+            }
+        }
+
+        // Stores to the namespace of a class body are kept, as it becomes the dict of the class
+        let dead_stores: BTreeSet<InstructionIndex> = (0..instructions.len())
+            .filter(|idx| match (variable_access(&instructions[*idx].op), &live_after[*idx]) {
+                (Some((variable, Access::Store)), Some(live)) => {
+                    let observable = escaping.contains(&variable) || matches!(variable, Variable::Name(_)) && !is_module;
+                    !observable && !live.contains(&variable)
+                }
+                _ => false,
+            })
+            .collect();
+
+        Liveness {
+            reaching,
+            dead_stores,
+            unassigned_uses,
+        }
+    }
+
+    // Definitions that may reach the read or delete of a variable
+    pub fn definitions_of(&self, instruction: InstructionIndex) -> Option<&BTreeSet<Definition>> {
+        self.reaching.get(&instruction)
+    }
+
+    // Whether the operation stores a value that's never used, so that it can be dropped
+    pub fn is_dead_store(&self, instruction: InstructionIndex) -> bool {
+        self.dead_stores.contains(&instruction)
+    }
+}
@@ -1,8 +1,27 @@
-use super::{builtins, code::CodeBlock, operations::Operation, var::Var};
+use super::{
+    builtins,
+    cfg::ControlFlowGraph,
+    code::CodeBlock,
+    compileerror::{CompileError, CompileErrorKind, CompileResult},
+    ir::{Function, FunctionId, Inst, Program, Terminator, ValueId},
+    irbuilder::{named_param_count, IrBuilder, Scope},
+    liveness::Liveness,
+    operations::{BinOp, CmpKind, Name, UnaryOp},
+    stackdepth::StackDepths,
+    typeinference::{InferredType, TypeInference},
+    var::Var,
+    version::PythonVersion,
+};
+use inkwell::basic_block::BasicBlock;
+use inkwell::builder::Builder;
 use inkwell::context::Context;
-use inkwell::types::{BasicType, BasicTypeEnum};
-use inkwell::values::BasicValueEnum;
-use std::collections::HashMap;
+use inkwell::module::{Linkage, Module};
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum};
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, GlobalValue, IntValue, PhiValue, StructValue,
+};
+use inkwell::IntPredicate;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::io::Write;
 use std::path::Path;
@@ -11,213 +30,733 @@ use std::process::Command;
 #[derive(Debug)]
 pub struct LlvmCompiler {
     code: CodeBlock,
-    refs: Vec<Var>,
+    version: PythonVersion,
 }
 
 // Owned type that doesn't depend on lifetimes
 #[derive(Debug, Clone)]
 pub enum VarType {
     Int32,
+    Str,   // pointer to a global string constant
+    Range, // fields of a range or of its iterator
     // Add more types as needed
 }
 
-// Owned variable representation without lifetime dependencies
+// IR value as emitted to LLVM
 #[derive(Debug, Clone)]
-pub struct OwnedVariable {
-    pub var_type: VarType,
-    pub name: String,
-    pub value: i64, // Store the actual value, not LLVM representation
-    pub is_temp: bool,
+pub enum LoweredValue<'a> {
+    Value(BasicValueEnum<'a>), // SSA value computed at run time
+    Builtin(String),           // builtin function loaded by name, e.g. print
+    Function(FunctionId),      // function of the program, called directly
+    Null,                      // NULL pushed along with the callable since 3.11
 }
 
-// Runtime LLVM variable that gets created during IR generation
-#[derive(Debug, Clone)]
-pub struct LlvmVariable<'a> {
-    pub v_type: BasicTypeEnum<'a>,
-    pub ptr: BasicValueEnum<'a>,
-    pub value: BasicValueEnum<'a>,
+// Emits the LLVM instructions for the IR of a single function
+pub struct LlvmHandlers<'a, 'ctx> {
+    context: &'ctx Context,
+    builder: &'a Builder<'ctx>,
+    ir: &'a Function,
+    function: FunctionValue<'ctx>,
+    program: ProgramDecls<'a, 'ctx>,
+    values: Vec<Option<LoweredValue<'ctx>>>,     // None for values not emitted, like those of unassigned variables
+    maybe_unassigned: BTreeSet<ValueId>,         // phis that may take the value of an unassigned variable
+    assigned: BTreeMap<ValueId, PhiValue<'ctx>>, // flags of those phis, set while they hold a value
 }
 
-// Handler struct that can be extracted without lifetime issues
-pub struct LlvmHandlers {
-    temp_counter: usize,
+// What the functions of the program share: the version of Python whose errors they raise, and the LLVM
+// functions and globals they refer to
+#[derive(Clone, Copy)]
+pub struct ProgramDecls<'a, 'ctx> {
+    version: PythonVersion,
+    functions: &'a [Option<FunctionValue<'ctx>>], // functions of the program, by FunctionId
+    globals: &'a BTreeMap<Name, SharedGlobal<'ctx>>,
+    libc: LibcFunctions<'ctx>,
 }
 
-impl LlvmHandlers {
-    pub fn new() -> Self {
-        Self { temp_counter: 0 }
-    }
-
-    fn get_next_temp_name(&mut self) -> String {
-        let name = format!("temp_{}", self.temp_counter);
-        self.temp_counter += 1;
-        name
-    }
-
-    pub fn handle_load_const(
-        &mut self,
-        consts: &[&Var],
-        i: u8,
-        variables: &mut HashMap<String, OwnedVariable>,
-        stack: &mut Vec<OwnedVariable>,
-    ) {
-        let temp_name = self.get_next_temp_name();
-        let var = consts[i as usize];
-        let owned_var = OwnedVariable::from_var(var, temp_name.clone(), true);
-        
-        variables.insert(temp_name, owned_var.clone());
-        stack.push(owned_var);
-    }
-
-    pub fn handle_store_name(
-        &mut self,
-        names: &[String],
-        i: u8,
-        variables: &mut HashMap<String, OwnedVariable>,
-        stack: &mut Vec<OwnedVariable>,
-    ) {
-        let name = &names[i as usize];
-        let mut temp_var = stack.pop().expect(&format!("expected stack to contain at least one element - {:?}", name));
-        
-        // Update the variable to be non-temporary and use the proper name
-        temp_var.name = name.clone();
-        temp_var.is_temp = false;
-        
-        // Remove any temporary variables
-        let temp_keys: Vec<String> = variables.keys()
-            .filter(|k| k.starts_with("temp_"))
-            .cloned()
-            .collect();
-        for temp_key in temp_keys {
-            variables.remove(&temp_key);
+// C library functions the emitted code calls. They're declared before the functions of the program, which
+// LLVM then renames should one of them have the same name.
+#[derive(Debug, Clone, Copy)]
+pub struct LibcFunctions<'ctx> {
+    printf: FunctionValue<'ctx>,
+    strcmp: FunctionValue<'ctx>,
+    dprintf: FunctionValue<'ctx>,
+    exit: FunctionValue<'ctx>,
+}
+
+impl<'ctx> LibcFunctions<'ctx> {
+    fn declare(context: &'ctx Context, module: &Module<'ctx>) -> Self {
+        let i32_type = context.i32_type();
+        let ptr_type = context.i8_type().ptr_type(inkwell::AddressSpace::default());
+        let printf_type = i32_type.fn_type(&[ptr_type.into()], true);
+        let strcmp_type = i32_type.fn_type(&[ptr_type.into(), ptr_type.into()], false);
+        let dprintf_type = i32_type.fn_type(&[i32_type.into(), ptr_type.into()], true);
+        let exit_type = context.void_type().fn_type(&[i32_type.into()], false);
+        LibcFunctions {
+            printf: module.add_function("printf", printf_type, None),
+            strcmp: module.add_function("strcmp", strcmp_type, None),
+            dprintf: module.add_function("dprintf", dprintf_type, None),
+            exit: module.add_function("exit", exit_type, None),
         }
-        
-        variables.insert(name.clone(), temp_var);
     }
+}
 
-    pub fn handle_load_name(
-        &self,
-        names: &[String],
-        i: u8,
-        variables: &HashMap<String, OwnedVariable>,
-        stack: &mut Vec<OwnedVariable>,
-    ) {
-        let name = &names[i as usize];
-        
-        if let Some(var) = variables.get(name) {
-            stack.push(var.clone());
-        } else if builtins::is_builtin(name) {
-            // Create a placeholder for builtin functions
-            let builtin_var = OwnedVariable {
-                var_type: VarType::Int32,
-                name: name.clone(),
-                value: 0,
-                is_temp: false,
+// Module variable shared with the functions, along with the flag telling whether it's assigned
+#[derive(Debug, Clone, Copy)]
+pub struct SharedGlobal<'ctx> {
+    value: GlobalValue<'ctx>,
+    value_type: BasicTypeEnum<'ctx>,
+    assigned: GlobalValue<'ctx>,
+}
+
+impl<'a, 'ctx> LlvmHandlers<'a, 'ctx> {
+    pub fn new(
+        context: &'ctx Context,
+        builder: &'a Builder<'ctx>,
+        ir: &'a Function,
+        function: FunctionValue<'ctx>,
+        program: ProgramDecls<'a, 'ctx>,
+    ) -> Self {
+        Self {
+            context,
+            builder,
+            ir,
+            function,
+            program,
+            values: vec![None; ir.values.len()],
+            maybe_unassigned: ir.maybe_unassigned(),
+            assigned: BTreeMap::new(),
+        }
+    }
+
+    fn value(&self, value: ValueId) -> BasicValueEnum<'ctx> {
+        match &self.values[value] {
+            Some(LoweredValue::Value(lowered)) => *lowered,
+            other => panic!("expected %{} to be a value, found {:?}", value, other),
+        }
+    }
+
+    // Values whose type is only known at run time can't be lowered yet
+    fn llvm_type(&self, value: ValueId) -> CompileResult<BasicTypeEnum<'ctx>> {
+        let var_type = VarType::from_inferred(self.ir.value_type(value))
+            .ok_or_else(|| self.error(CompileErrorKind::MixedTypes))?;
+        Ok(var_type.to_llvm_type(self.context))
+    }
+
+    // Compile error pointing at the function being emitted
+    fn error(&self, kind: CompileErrorKind) -> CompileError {
+        CompileError::new(&self.ir.name, kind)
+    }
+
+    // Phis are created empty, as values of back edges are only emitted after the block
+    pub fn handle_phi(&mut self, value: ValueId) -> CompileResult<()> {
+        let phi = self
+            .builder
+            .build_phi(self.llvm_type(value)?, "phi")
+            .expect("llvm to build a phi instruction");
+        self.values[value] = Some(LoweredValue::Value(phi.as_basic_value()));
+        if self.maybe_unassigned.contains(&value) {
+            let assigned = self
+                .builder
+                .build_phi(self.context.bool_type(), "assigned")
+                .expect("llvm to build a phi instruction");
+            self.assigned.insert(value, assigned);
+        }
+        Ok(())
+    }
+
+    // Values of unassigned variables come in as undef along with a false flag, as reading them raises an error
+    // before they are used
+    pub fn complete_phis(&self, exits: &[BasicBlock<'ctx>]) {
+        for (value, ir_value) in self.ir.values.iter().enumerate() {
+            let (Inst::Phi(incoming), Some(LoweredValue::Value(phi))) = (&ir_value.inst, &self.values[value]) else {
+                continue;
             };
-            stack.push(builtin_var);
-        } else {
-            panic!("expected loaded variable to be already declared - {:?}", name);
-        }
-    }
-
-    pub fn handle_binary_add(
-        &mut self,
-        stack: &mut Vec<OwnedVariable>,
-    ) {
-        let b = stack.pop().expect("expected stack to have the first of two elements");
-        let a = stack.pop().expect("expected stack to have the second of two elements");
-
-        let result_value = a.value + b.value;
-        let temp_name = self.get_next_temp_name();
-        
-        let result_var = OwnedVariable {
-            var_type: VarType::Int32,
-            name: temp_name,
-            value: result_value,
-            is_temp: true,
+            let phi = PhiValue::try_from(phi.as_instruction_value().expect("phi to be an instruction"))
+                .expect("phi to be a phi instruction");
+            for (block, incoming) in incoming {
+                let incoming_value = match self.ir.inst(*incoming) {
+                    Inst::Undefined(_) => undef(phi.as_basic_value().get_type()),
+                    _ => self.value(*incoming),
+                };
+                phi.add_incoming(&[(&incoming_value, exits[*block])]);
+                if let Some(assigned) = self.assigned.get(&value) {
+                    assigned.add_incoming(&[(&self.assigned_flag(*incoming), exits[*block])]);
+                }
+            }
+        }
+    }
+
+    // Whether the value isn't that of an unassigned variable, as an i1
+    fn assigned_flag(&self, value: ValueId) -> IntValue<'ctx> {
+        let bool_type = self.context.bool_type();
+        match (self.ir.inst(value), self.assigned.get(&value)) {
+            (Inst::Undefined(_), _) => bool_type.const_zero(),
+            (_, Some(assigned)) => assigned.as_basic_value().into_int_value(),
+            _ => bool_type.const_int(1, false),
+        }
+    }
+
+    pub fn handle_check_assigned(&self, checked: ValueId, message: &str) {
+        self.build_flag_check(self.assigned_flag(checked), message);
+    }
+
+    pub fn handle_const(&mut self, value: ValueId, var: &Var) -> CompileResult<()> {
+        let lowered = match var.as_string() {
+            Some(s) => self
+                .builder
+                .build_global_string_ptr(&s, "str")
+                .expect("llvm to create a global string constant")
+                .as_pointer_value()
+                .into(),
+            None => VarType::from_var(var)
+                .ok_or_else(|| self.error(CompileErrorKind::Constant(format!("{:?}", var))))?
+                .const_value(self.context, var),
         };
-        
-        stack.push(result_var);
-    }
-
-    pub fn handle_binary_subtract(
-        &mut self,
-        stack: &mut Vec<OwnedVariable>,
-    ) {
-        let b = stack.pop().expect("expected stack to have the first of two elements");
-        let a = stack.pop().expect("expected stack to have the second of two elements");
-
-        let result_value = a.value - b.value;
-        let temp_name = self.get_next_temp_name();
-        
-        let result_var = OwnedVariable {
-            var_type: VarType::Int32,
-            name: temp_name,
-            value: result_value,
-            is_temp: true,
+        self.values[value] = Some(LoweredValue::Value(lowered));
+        Ok(())
+    }
+
+    // Operations on ints and bools, the only ones with native values so far
+    pub fn handle_binary(&mut self, value: ValueId, op: BinOp, lhs: ValueId, rhs: ValueId) -> CompileResult<()> {
+        let (lhs_type, rhs_type) = (self.ir.value_type(lhs), self.ir.value_type(rhs));
+        if !lhs_type.is_integral() || !rhs_type.is_integral() {
+            return Err(self.error(CompileErrorKind::BinaryOperation(op, lhs_type, rhs_type)));
+        }
+        let a = self.value(lhs).into_int_value();
+        let b = self.value(rhs).into_int_value();
+        let result = match op {
+            BinOp::Add => self.builder.build_int_add(a, b, "add"),
+            BinOp::Subtract => self.builder.build_int_sub(a, b, "sub"),
+            BinOp::Multiply => self.builder.build_int_mul(a, b, "mul"),
+            BinOp::And => self.builder.build_and(a, b, "and"),
+            BinOp::Or => self.builder.build_or(a, b, "or"),
+            BinOp::Xor => self.builder.build_xor(a, b, "xor"),
+            BinOp::FloorDivide | BinOp::Modulo => Ok(self.build_floor_division(op, a, b)),
+            BinOp::Lshift | BinOp::Rshift => Ok(self.build_shift(op, a, b)),
+            // Powers with negative exponents and true division give floats
+            BinOp::Power | BinOp::TrueDivide | BinOp::MatrixMultiply => {
+                return Err(self.error(CompileErrorKind::BinaryOperation(op, lhs_type, rhs_type)))
+            }
+        }
+        .unwrap_or_else(|_| panic!("llvm to build the {:?} instruction", op));
+        self.values[value] = Some(LoweredValue::Value(result.into()));
+        Ok(())
+    }
+
+    // Quotient or remainder of the division, rounded towards negative infinity as in Python rather than towards
+    // zero as by LLVM. Dividing by zero raises a ZeroDivisionError.
+    fn build_floor_division(&self, op: BinOp, a: IntValue<'ctx>, b: IntValue<'ctx>) -> IntValue<'ctx> {
+        let message = match op {
+            BinOp::Modulo if self.program.version.at_least(3, 11) => "ZeroDivisionError: integer modulo by zero",
+            _ => "ZeroDivisionError: integer division or modulo by zero",
         };
-        
-        stack.push(result_var);
+        let zero = b.get_type().const_zero();
+        let compare = |predicate, lhs, rhs, name| {
+            self.builder
+                .build_int_compare(predicate, lhs, rhs, name)
+                .expect("llvm to build a comparison")
+        };
+        self.build_flag_check(compare(IntPredicate::NE, b, zero, "nonzero"), message);
+
+        let quotient = self.builder.build_int_signed_div(a, b, "div").expect("llvm to build a division");
+        let remainder = self.builder.build_int_signed_rem(a, b, "rem").expect("llvm to build a remainder");
+        // The results are off by one when the remainder isn't zero and its sign differs from that of the divisor
+        let signs = self.builder.build_xor(remainder, b, "signs").expect("llvm to build a xor instruction");
+        let inexact = compare(IntPredicate::NE, remainder, zero, "inexact");
+        let opposite = compare(IntPredicate::SLT, signs, zero, "opposite");
+        let adjust = self.builder.build_and(inexact, opposite, "adjust").expect("llvm to build an and instruction");
+        match op {
+            BinOp::FloorDivide => {
+                let adjust = self
+                    .builder
+                    .build_int_z_extend(adjust, a.get_type(), "adjust")
+                    .expect("llvm to extend the flag to an int");
+                self.builder.build_int_sub(quotient, adjust, "floordiv").expect("llvm to build a sub instruction")
+            }
+            _ => {
+                let adjusted = self
+                    .builder
+                    .build_int_add(remainder, b, "adjusted")
+                    .expect("llvm to build an add instruction");
+                self.builder
+                    .build_select(adjust, adjusted, remainder, "mod")
+                    .expect("llvm to build a select instruction")
+                    .into_int_value()
+            }
+        }
     }
 
-    pub fn handle_return_value(
-        &self,
-        stack: &mut Vec<OwnedVariable>,
-    ) -> Option<OwnedVariable> {
-        stack.pop()
+    // Shifts by negative counts raise a ValueError. Those by the width of the int or more, which LLVM leaves
+    // undefined, shift all the bits out as in Python, leaving the sign for right shifts.
+    fn build_shift(&self, op: BinOp, a: IntValue<'ctx>, b: IntValue<'ctx>) -> IntValue<'ctx> {
+        let int_type = a.get_type();
+        let last_bit = int_type.const_int(int_type.get_bit_width() as u64 - 1, false);
+        let compare = |predicate, lhs, rhs, name| {
+            self.builder
+                .build_int_compare(predicate, lhs, rhs, name)
+                .expect("llvm to build a comparison")
+        };
+        let nonnegative = compare(IntPredicate::SGE, b, int_type.const_zero(), "nonnegative");
+        self.build_flag_check(nonnegative, "ValueError: negative shift count");
+
+        let too_far = compare(IntPredicate::SGT, b, last_bit, "too_far");
+        let select = |then: IntValue<'ctx>, otherwise: IntValue<'ctx>, name| {
+            self.builder
+                .build_select(too_far, then, otherwise, name)
+                .expect("llvm to build a select instruction")
+                .into_int_value()
+        };
+        match op {
+            BinOp::Lshift => {
+                let shifted = self.builder.build_left_shift(a, b, "shl").expect("llvm to build a shift");
+                select(int_type.const_zero(), shifted, "lshift")
+            }
+            _ => {
+                let count = select(last_bit, b, "count");
+                self.builder.build_right_shift(a, count, true, "rshift").expect("llvm to build a shift")
+            }
+        }
     }
 
-    pub fn handle_pop_top(
-        &self,
-        stack: &mut Vec<OwnedVariable>,
-    ) {
-        stack.pop().expect("expected stack to contain at least one element");
-    }
-
-    pub fn handle_call_function(
-        &mut self,
-        names: &[String],
-        arg_count: u8,
-        variables: &HashMap<String, OwnedVariable>,
-        stack: &mut Vec<OwnedVariable>,
-    ) -> Result<(String, Vec<OwnedVariable>), String> {
-        if stack.len() < (arg_count + 1) as usize {
-            return Err(format!("expected stack to have at least {} arguments plus function name", arg_count));
-        }
-        
-        // Get the arguments (they're on top of the stack)
-        let mut args = Vec::new();
-        for _ in 0..arg_count {
-            args.push(stack.pop().expect("expected argument on stack"));
-        }
-        args.reverse(); // Arguments were pushed in reverse order
-        
-        // Get the function name (it's now the top of the stack)
-        let func_name_var = stack.pop().expect("expected function name on stack");
-        
-        // Find the function name
-        let func_name = names.iter()
-            .find(|name| {
-                if let Some(var) = variables.get(*name) {
-                    var.name == func_name_var.name
-                } else {
-                    builtins::is_builtin(name)
+    // Strings are compared by the result of strcmp, as the order of their UTF-8 bytes is that of their code points.
+    // None only equals itself, and ints, strings and None never equal each other.
+    pub fn handle_compare(&mut self, value: ValueId, kind: CmpKind, lhs: ValueId, rhs: ValueId) -> CompileResult<()> {
+        let (lhs_type, rhs_type) = (self.ir.value_type(lhs), self.ir.value_type(rhs));
+        let equality = matches!(kind, CmpKind::Eq | CmpKind::Ne);
+        let scalar = |value_type: InferredType| value_type.is_integral() || value_type == InferredType::Str;
+        let (lhs, rhs) = match (lhs_type, rhs_type) {
+            (InferredType::Str, InferredType::Str) => {
+                let order = self.build_strcmp(self.value(lhs), self.value(rhs));
+                (order, order.get_type().const_zero())
+            }
+            _ if lhs_type.is_integral() && rhs_type.is_integral() => {
+                (self.value(lhs).into_int_value(), self.value(rhs).into_int_value())
+            }
+            (InferredType::None, InferredType::None) if equality => {
+                (self.value(lhs).into_int_value(), self.value(rhs).into_int_value())
+            }
+            _ if equality
+                && (scalar(lhs_type) || lhs_type == InferredType::None)
+                && (scalar(rhs_type) || rhs_type == InferredType::None) =>
+            {
+                let differ = self.context.bool_type().const_int((kind == CmpKind::Ne) as u64, false);
+                self.values[value] = Some(LoweredValue::Value(self.bool_value(differ)));
+                return Ok(());
+            }
+            _ => return Err(self.error(CompileErrorKind::Comparison(kind, lhs_type, rhs_type))),
+        };
+        let predicate = match kind {
+            CmpKind::Lt => IntPredicate::SLT,
+            CmpKind::Le => IntPredicate::SLE,
+            CmpKind::Eq => IntPredicate::EQ,
+            CmpKind::Ne => IntPredicate::NE,
+            CmpKind::Gt => IntPredicate::SGT,
+            CmpKind::Ge => IntPredicate::SGE,
+            CmpKind::ExceptionMatch => return Err(self.error(CompileErrorKind::Comparison(kind, lhs_type, rhs_type))),
+        };
+        let result = self
+            .builder
+            .build_int_compare(predicate, lhs, rhs, "cmp")
+            .expect("llvm to build a comparison");
+        self.values[value] = Some(LoweredValue::Value(self.bool_value(result)));
+        Ok(())
+    }
+
+    fn build_strcmp(&self, lhs: BasicValueEnum<'ctx>, rhs: BasicValueEnum<'ctx>) -> IntValue<'ctx> {
+        self.builder
+            .build_call(self.program.libc.strcmp, &[lhs.into(), rhs.into()], "order")
+            .expect("llvm to build a call to strcmp")
+            .try_as_basic_value()
+            .left()
+            .expect("strcmp to return a value")
+            .into_int_value()
+    }
+
+    pub fn handle_unary(&mut self, value: ValueId, op: UnaryOp, operand: ValueId) -> CompileResult<()> {
+        let operand_type = self.ir.value_type(operand);
+        if op != UnaryOp::Not && !operand_type.is_integral() {
+            return Err(self.error(CompileErrorKind::UnaryOperation(op, operand_type)));
+        }
+        let result = match op {
+            UnaryOp::Not => {
+                let truth = self.truth(operand)?;
+                let negated = self.builder.build_not(truth, "not").expect("llvm to build a not instruction");
+                self.bool_value(negated)
+            }
+            // Bools are already stored as the ints 0 and 1
+            UnaryOp::Positive => self.value(operand),
+            UnaryOp::Negative => self
+                .builder
+                .build_int_neg(self.value(operand).into_int_value(), "neg")
+                .expect("llvm to build a neg instruction")
+                .into(),
+            UnaryOp::Invert => self
+                .builder
+                .build_not(self.value(operand).into_int_value(), "invert")
+                .expect("llvm to build a not instruction")
+                .into(),
+        };
+        self.values[value] = Some(LoweredValue::Value(result));
+        Ok(())
+    }
+
+    pub fn handle_to_bool(&mut self, value: ValueId, operand: ValueId) -> CompileResult<()> {
+        let truth = self.truth(operand)?;
+        self.values[value] = Some(LoweredValue::Value(self.bool_value(truth)));
+        Ok(())
+    }
+
+    // Truth value of the value as an i1, following Python's rules for its type
+    fn truth(&self, value: ValueId) -> CompileResult<IntValue<'ctx>> {
+        let lowered = self.value(value);
+        let zero = |int_value: IntValue<'ctx>| int_value.get_type().const_zero();
+        let truth = match self.ir.value_type(value) {
+            // None is lowered as 0, so it's false like the int
+            InferredType::Int | InferredType::Bool | InferredType::None => {
+                let int_value = lowered.into_int_value();
+                self.builder
+                    .build_int_compare(IntPredicate::NE, int_value, zero(int_value), "truth")
+            }
+            // Strings are true unless empty
+            InferredType::Str => {
+                let first = self
+                    .builder
+                    .build_load(self.context.i8_type(), lowered.into_pointer_value(), "first_char")
+                    .expect("llvm to load the first character of the string")
+                    .into_int_value();
+                self.builder.build_int_compare(IntPredicate::NE, first, zero(first), "truth")
+            }
+            value_type => return Err(self.error(CompileErrorKind::TruthValue(value_type))),
+        };
+        Ok(truth.expect("llvm to build a comparison"))
+    }
+
+    // Bools are lowered as the ints 0 and 1, so that they join ints in arithmetic
+    fn bool_value(&self, truth: IntValue<'ctx>) -> BasicValueEnum<'ctx> {
+        self.builder
+            .build_int_z_extend(truth, self.context.i32_type(), "bool")
+            .expect("llvm to extend the bool to an int")
+            .into()
+    }
+
+    pub fn handle_call(&mut self, value: ValueId, callee: ValueId, args: &[ValueId]) -> CompileResult<()> {
+        let func_name = match &self.values[callee] {
+            Some(LoweredValue::Builtin(name)) => name.clone(),
+            Some(LoweredValue::Function(function)) => {
+                self.handle_direct_call(value, *function, args);
+                return Ok(());
+            }
+            _ => return Err(self.error(CompileErrorKind::RuntimeCallee)),
+        };
+
+        match func_name.as_str() {
+            builtins::PRINT => {
+                if let Some(arg) = args.first() {
+                    let printed = match self.ir.value_type(*arg) {
+                        InferredType::Bool => self.bool_name(*arg)?,
+                        InferredType::None => self.none_name(),
+                        InferredType::Int | InferredType::Str => self.value(*arg),
+                        arg_type => return Err(self.error(CompileErrorKind::Print(arg_type))),
+                    };
+                    builtins::handle_print_builtin(self.builder, self.program.libc.printf, &printed);
                 }
-            })
-            .cloned()
-            .unwrap_or_else(|| func_name_var.name.clone());
-        
-        // Push a dummy result back onto the stack to maintain stack balance
-        let temp_name = self.get_next_temp_name();
-        let dummy_result = OwnedVariable {
-            var_type: VarType::Int32,
-            name: temp_name,
-            value: 0,
-            is_temp: true,
+            }
+            _ => return Err(self.error(CompileErrorKind::Builtin(func_name))),
+        }
+
+        // Builtins handled so far return None
+        self.values[value] = Some(LoweredValue::Value(self.context.i32_type().const_zero().into()));
+        Ok(())
+    }
+
+    pub fn handle_range(&mut self, value: ValueId, start: ValueId, stop: ValueId, step: ValueId) -> CompileResult<()> {
+        for arg in [start, stop, step] {
+            let arg_type = self.ir.value_type(arg);
+            if !arg_type.is_integral() {
+                return Err(self.error(CompileErrorKind::RangeArgument(arg_type)));
+            }
+        }
+        // Constant steps are checked at compile time
+        match self.ir.inst(step) {
+            Inst::Const(var) if var.as_int().is_some_and(|step| step != 0) => {}
+            _ => self.build_step_check(self.value(step).into_int_value()),
+        }
+
+        let mut range = builtins::range_type(self.context).get_undef();
+        for (idx, field) in [start, stop, step].into_iter().enumerate() {
+            range = self
+                .builder
+                .build_insert_value(range, self.value(field), idx as u32, "range")
+                .expect("llvm to build the range")
+                .into_struct_value();
+        }
+        self.values[value] = Some(LoweredValue::Value(range.into()));
+        Ok(())
+    }
+
+    // Raises the ValueError of range() for a step of 0, continuing in a new block otherwise
+    fn build_step_check(&self, step: IntValue<'ctx>) {
+        let current = self.builder.get_insert_block().expect("builder to be positioned in a block");
+        let error = self.context.insert_basic_block_after(current, "range_error");
+        let checked = self.context.insert_basic_block_after(error, "range");
+        let is_zero = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, step, step.get_type().const_zero(), "step_is_zero")
+            .expect("llvm to build a comparison");
+        self.builder
+            .build_conditional_branch(is_zero, error, checked)
+            .expect("llvm to build a conditional branch");
+        self.builder.position_at_end(error);
+        self.build_runtime_error("ValueError: range() arg 3 must not be zero");
+        self.builder.position_at_end(checked);
+    }
+
+    // The iterator over a range starts out with the same fields
+    pub fn handle_get_iter(&mut self, value: ValueId, iterable: ValueId) -> CompileResult<()> {
+        match self.ir.value_type(iterable) {
+            InferredType::Range => self.values[value] = Some(LoweredValue::Value(self.value(iterable))),
+            iterable_type => return Err(self.error(CompileErrorKind::Iteration(iterable_type))),
+        }
+        Ok(())
+    }
+
+    // Iterators are only made from ranges, see handle_get_iter
+    fn range_iterator(&self, iterator: ValueId) -> StructValue<'ctx> {
+        match self.ir.value_type(iterator) {
+            InferredType::RangeIterator => self.value(iterator).into_struct_value(),
+            iterator_type => panic!("expected a range iterator, found {:?}", iterator_type),
+        }
+    }
+
+    pub fn handle_iter_has_next(&mut self, value: ValueId, iterator: ValueId) {
+        let has_next = builtins::build_range_has_next(self.builder, self.range_iterator(iterator));
+        self.values[value] = Some(LoweredValue::Value(self.bool_value(has_next)));
+    }
+
+    pub fn handle_iter_value(&mut self, value: ValueId, iterator: ValueId) {
+        let next = self
+            .builder
+            .build_extract_value(self.range_iterator(iterator), 0, "next")
+            .expect("llvm to extract the next value of the range iterator");
+        self.values[value] = Some(LoweredValue::Value(next));
+    }
+
+    pub fn handle_iter_advance(&mut self, value: ValueId, iterator: ValueId) {
+        let iterator = self.range_iterator(iterator);
+        let field = |idx: u32, name: &str| {
+            self.builder
+                .build_extract_value(iterator, idx, name)
+                .expect("llvm to extract a field of the range iterator")
+                .into_int_value()
+        };
+        let advanced = self
+            .builder
+            .build_int_add(field(0, "next"), field(2, "step"), "advanced")
+            .expect("llvm to build an add instruction");
+        let iterator = self
+            .builder
+            .build_insert_value(iterator, advanced, 0, "iterator")
+            .expect("llvm to update the range iterator")
+            .into_struct_value();
+        self.values[value] = Some(LoweredValue::Value(iterator.into()));
+    }
+
+    pub fn handle_load_global(&mut self, value: ValueId, name: &Name) {
+        let global = self.program.globals[name];
+        self.build_assigned_check(name, global);
+        let loaded = self
+            .builder
+            .build_load(global.value_type, global.value.as_pointer_value(), name)
+            .expect("llvm to load the global");
+        self.values[value] = Some(LoweredValue::Value(loaded));
+    }
+
+    pub fn handle_store_global(&mut self, name: &Name, stored: ValueId) {
+        let global = self.program.globals[name];
+        self.builder
+            .build_store(global.value.as_pointer_value(), self.value(stored))
+            .expect("llvm to store the global");
+        self.set_assigned(global, true);
+    }
+
+    pub fn handle_delete_global(&mut self, name: &Name) {
+        let global = self.program.globals[name];
+        self.build_assigned_check(name, global);
+        self.set_assigned(global, false);
+    }
+
+    fn set_assigned(&self, global: SharedGlobal<'ctx>, assigned: bool) {
+        let flag = self.context.bool_type().const_int(assigned as u64, false);
+        self.builder
+            .build_store(global.assigned.as_pointer_value(), flag)
+            .expect("llvm to store the flag of the global");
+    }
+
+    // Raises the NameError of an unassigned global
+    fn build_assigned_check(&self, name: &Name, global: SharedGlobal<'ctx>) {
+        let assigned = self
+            .builder
+            .build_load(self.context.bool_type(), global.assigned.as_pointer_value(), "assigned")
+            .expect("llvm to load the flag of the global")
+            .into_int_value();
+        self.build_flag_check(assigned, &format!("NameError: name '{}' is not defined", name));
+    }
+
+    // Raises the error unless the flag is set, continuing in a new block otherwise. Flags known to be set need
+    // no check.
+    fn build_flag_check(&self, flag: IntValue<'ctx>, message: &str) {
+        if flag.get_zero_extended_constant() == Some(1) {
+            return;
+        }
+        let current = self.builder.get_insert_block().expect("builder to be positioned in a block");
+        let error = self.context.insert_basic_block_after(current, "unassigned");
+        let checked = self.context.insert_basic_block_after(error, "assigned");
+        self.builder
+            .build_conditional_branch(flag, checked, error)
+            .expect("llvm to build a conditional branch");
+        self.builder.position_at_end(error);
+        self.build_runtime_error(message);
+        self.builder.position_at_end(checked);
+    }
+
+    // Arguments are already bound to the parameters, those of *args and **kwargs coming last and left out
+    fn handle_direct_call(&mut self, value: ValueId, function: FunctionId, args: &[ValueId]) {
+        let callee = self.program.functions[function].expect("called function to be compiled");
+        let args: Vec<BasicMetadataValueEnum> = args
+            .iter()
+            .take(callee.count_params() as usize)
+            .map(|arg| self.value(*arg).into())
+            .collect();
+        let result = self
+            .builder
+            .build_call(callee, &args, "call")
+            .expect("llvm to build a call")
+            .try_as_basic_value()
+            .left()
+            .expect("function to return a value");
+        self.values[value] = Some(LoweredValue::Value(result));
+    }
+
+    // "True" or "False", as bools are printed
+    fn bool_name(&self, value: ValueId) -> CompileResult<BasicValueEnum<'ctx>> {
+        let name = |text: &str| {
+            self.builder
+                .build_global_string_ptr(text, "bool_name")
+                .expect("llvm to create a global string constant")
+                .as_pointer_value()
         };
-        stack.push(dummy_result);
-        
-        Ok((func_name, args))
+        let (true_name, false_name) = (name("True"), name("False"));
+        Ok(self
+            .builder
+            .build_select(self.truth(value)?, true_name, false_name, "bool_name")
+            .expect("llvm to build a select instruction"))
+    }
+
+    // "None", as None is printed. It has a single value, so the lowered one isn't needed.
+    fn none_name(&self) -> BasicValueEnum<'ctx> {
+        self.builder
+            .build_global_string_ptr("None", "none_name")
+            .expect("llvm to create a global string constant")
+            .as_pointer_value()
+            .into()
+    }
+
+    pub fn handle_value(&mut self, value: ValueId) -> CompileResult<()> {
+        match &self.ir.inst(value) {
+            Inst::Phi(_) => self.handle_phi(value)?,
+            // Never read, loading an unassigned variable raises an error instead
+            Inst::Undefined(_) => {}
+            // The tuple and dict of *args and **kwargs are never read, see IrBuilder::build, nor passed
+            Inst::Param(idx) => {
+                if let Some(param) = self.function.get_nth_param(*idx as u32) {
+                    self.values[value] = Some(LoweredValue::Value(param));
+                }
+            }
+            // Constant tuples are only unpacked like the containers below, or hold the names of keyword arguments
+            Inst::Const(var) if var.as_tuple().is_some() => {}
+            Inst::Const(var) => self.handle_const(value, var)?,
+            // Only made into a function
+            Inst::Code(_) => {}
+            Inst::Function { function, .. } => self.values[value] = Some(LoweredValue::Function(*function)),
+            Inst::Builtin(name) => self.values[value] = Some(LoweredValue::Builtin(name.clone())),
+            Inst::Null => self.values[value] = Some(LoweredValue::Null),
+            Inst::LoadGlobal(name) => self.handle_load_global(value, name),
+            Inst::StoreGlobal(name, stored) => self.handle_store_global(name, *stored),
+            Inst::DeleteGlobal(name) => self.handle_delete_global(name),
+            Inst::CheckAssigned(checked, message) => self.handle_check_assigned(*checked, message),
+            Inst::Unary(op, operand) => self.handle_unary(value, *op, *operand)?,
+            Inst::ToBool(operand) => self.handle_to_bool(value, *operand)?,
+            Inst::Binary { op, lhs, rhs, .. } => self.handle_binary(value, *op, *lhs, *rhs)?,
+            Inst::Compare(kind, lhs, rhs) => self.handle_compare(value, *kind, *lhs, *rhs)?,
+            Inst::Call { callee, args } => self.handle_call(value, *callee, args)?,
+            // Containers are only unpacked into the arguments of calls or default values
+            Inst::Tuple(_) | Inst::List(_) | Inst::Dict(_) => {}
+            Inst::Range { start, stop, step } => self.handle_range(value, *start, *stop, *step)?,
+            Inst::GetIter(iterable) => self.handle_get_iter(value, *iterable)?,
+            Inst::IterHasNext(iterator) => self.handle_iter_has_next(value, *iterator),
+            Inst::IterValue(iterator) => self.handle_iter_value(value, *iterator),
+            Inst::IterAdvance(iterator) => self.handle_iter_advance(value, *iterator),
+        }
+        Ok(())
+    }
+
+    pub fn handle_terminator(&self, terminator: &Terminator, blocks: &[BasicBlock<'ctx>]) -> CompileResult<()> {
+        match terminator {
+            Terminator::Jump(target) => {
+                self.builder
+                    .build_unconditional_branch(blocks[*target])
+                    .expect("llvm to build a branch instruction");
+            }
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.builder
+                    .build_conditional_branch(self.truth(*condition)?, blocks[*then], blocks[*otherwise])
+                    .expect("llvm to build a conditional branch");
+            }
+            Terminator::Return(value) => {
+                self.builder
+                    .build_return(Some(&self.value(*value)))
+                    .expect("llvm to build a return instruction");
+            }
+            Terminator::Error(message) => self.build_runtime_error(message),
+            Terminator::Unreachable => {
+                self.builder
+                    .build_unreachable()
+                    .expect("llvm to build an unreachable instruction");
+            }
+        }
+        Ok(())
+    }
+
+    // Prints the error to stderr and exits, like an uncaught exception
+    fn build_runtime_error(&self, message: &str) {
+        let i32_type = self.context.i32_type();
+        let text = self
+            .builder
+            .build_global_string_ptr(&format!("{}\n", message), "error_message")
+            .expect("llvm to create a global string constant");
+        let stderr = i32_type.const_int(2, false);
+        self.builder
+            .build_call(self.program.libc.dprintf, &[stderr.into(), text.as_pointer_value().into()], "error")
+            .expect("llvm to build a call to dprintf");
+        self.builder
+            .build_call(self.program.libc.exit, &[i32_type.const_int(1, false).into()], "")
+            .expect("llvm to build a call to exit");
+        self.builder
+            .build_unreachable()
+            .expect("llvm to build an unreachable instruction");
+    }
+}
+
+fn undef(llvm_type: BasicTypeEnum) -> BasicValueEnum {
+    match llvm_type {
+        BasicTypeEnum::IntType(int_type) => int_type.get_undef().into(),
+        BasicTypeEnum::PointerType(pointer_type) => pointer_type.get_undef().into(),
+        BasicTypeEnum::StructType(struct_type) => struct_type.get_undef().into(),
+        _ => panic!("expected the type of a lowered value, found {:?}", llvm_type),
     }
 }
 
@@ -225,202 +764,204 @@ impl VarType {
     fn to_llvm_type<'a>(&self, context: &'a Context) -> BasicTypeEnum<'a> {
         match self {
             VarType::Int32 => context.i32_type().as_basic_type_enum(),
+            VarType::Str => context.i8_type().ptr_type(inkwell::AddressSpace::default()).as_basic_type_enum(),
+            VarType::Range => builtins::range_type(context).as_basic_type_enum(),
         }
     }
-}
 
-impl OwnedVariable {
-    fn to_llvm_value<'a>(&self, context: &'a Context) -> BasicValueEnum<'a> {
-        match self.var_type {
-            VarType::Int32 => {
-                let int_type = context.i32_type();
-                BasicValueEnum::IntValue(int_type.const_int(self.value as u64, false))
-            }
+    // None is returned as 0 until there are boxed values for it. Other types, like Dynamic for values whose
+    // type is only known at run time, have no native type yet.
+    fn from_inferred(inferred: InferredType) -> Option<Self> {
+        match inferred {
+            InferredType::Int | InferredType::Bool | InferredType::None => Some(VarType::Int32),
+            InferredType::Str => Some(VarType::Str),
+            InferredType::Range | InferredType::RangeIterator => Some(VarType::Range),
+            _ => None,
         }
     }
 
-    fn from_var(var: &Var, name: String, is_temp: bool) -> Self {
+    // Constants other than strings, those with no native type yet being None
+    fn from_var(var: &Var) -> Option<Self> {
         match var {
-            Var::None => OwnedVariable {
-                var_type: VarType::Int32,
-                name,
-                value: 0,
-                is_temp,
-            },
-            Var::Int(val) => OwnedVariable {
-                var_type: VarType::Int32,
-                name,
-                value: *val as i64,
-                is_temp,
-            },
-            _ => todo!("Support for var type {:?} not implemented", var),
+            Var::None | Var::True | Var::False | Var::Int(_) => Some(VarType::Int32),
+            _ => None,
+        }
+    }
+
+    fn const_value<'a>(&self, context: &'a Context, var: &Var) -> BasicValueEnum<'a> {
+        match self {
+            VarType::Int32 => {
+                let value = var.as_int().expect("expected an integer constant");
+                let int_type = self.to_llvm_type(context).into_int_type();
+                BasicValueEnum::IntValue(int_type.const_int(value as u64, true))
+            }
+            VarType::Str => unreachable!("string constants are emitted as global strings"),
+            VarType::Range => unreachable!("ranges are never constants"),
         }
     }
 }
 
-// TODO: Make the IR generator use the instructions and refs it was given
 impl LlvmCompiler {
-    pub fn new(code: CodeBlock, refs: Vec<Var>) -> LlvmCompiler {
-        LlvmCompiler { code, refs }
+    pub fn new(code: CodeBlock, version: PythonVersion) -> LlvmCompiler {
+        LlvmCompiler { code, version }
     }
 
-
-
-    pub fn generate_ir(&self) -> String {
+    pub fn generate_ir(&self) -> CompileResult<String> {
         let context = Context::create();
-        let module = context.create_module(&self.code.get_name(&self.refs));
+        let module = context.create_module(&self.code.get_name());
         let builder = context.create_builder();
 
-        // Declare printf function at the beginning
-        let printf_type = context.i32_type().fn_type(
-            &[context.i8_type().ptr_type(inkwell::AddressSpace::default()).into()],
-            true, // varargs
-        );
-        let _printf_func = module.add_function("printf", printf_type, None);
-
-        let code_blocks = self.code.get_code_blocks(&self.refs);
-        let mut fn_idx = 0;
-
-        for code_block in code_blocks {
-            let fn_ret_type = code_block.get_return_type(&self.refs, &context);
-            let fn_type = fn_ret_type.fn_type(&[], false);
-            let fn_name = if fn_idx > 0 {
-                code_block.get_name(&self.refs)
-            } else {
-                String::from("main")
-            };
-            let function = module.add_function(&fn_name, fn_type, None);
-            let entry = context.append_basic_block(function, "entry");
-            builder.position_at_end(entry);
-
-            // Use owned variables and handlers
-            let mut handlers = LlvmHandlers::new();
-            let mut variables: HashMap<String, OwnedVariable> = HashMap::new();
-            let mut stack: Vec<OwnedVariable> = Vec::new();
-            let mut llvm_variables: HashMap<String, LlvmVariable> = HashMap::new();
-            
-            let names = code_block.get_names(&self.refs);
-            let consts = code_block.get_consts(&self.refs);
-            let operations = code_block.get_operations();
-
-            // Debug output of the read operations
-            for op in operations {
-                println!("{:?}: {:?}", code_block.get_name(&self.refs), op);
+        let libc = LibcFunctions::declare(&context, &module);
+
+        // Functions are built as they are found to be called, starting from the module
+        let mut scope = Scope::new(&self.code);
+        let mut program = Program {
+            functions: vec![None; scope.functions.len()],
+            global_types: BTreeMap::new(),
+        };
+        let mut worklist: Vec<FunctionId> = vec![0];
+        while let Some(function) = worklist.pop() {
+            if program.functions[function].is_some() {
+                continue;
             }
+            let ir = self.build_function(scope.functions[function], &mut scope)?;
+            worklist.extend(ir.calls().map(|(callee, _)| callee));
+            program.functions[function] = Some(ir);
+        }
+        program.infer_types();
+        for ir in program.functions.iter().flatten() {
+            ir.check_types()
+                .map_err(|err| CompileError::new(&ir.name, CompileErrorKind::InconsistentTypes(err)))?;
+        }
 
-            // First pass: Process operations using owned types, but defer return
-            let mut return_var: Option<OwnedVariable> = None;
-            let mut print_calls: Vec<(String, Vec<OwnedVariable>)> = Vec::new();
-            
-            for op in operations {
-                match op {
-                    Operation::LoadConstArg(i) => {
-                        handlers.handle_load_const(&consts, *i, &mut variables, &mut stack);
-                    }
-                    Operation::StoreNameArg(i) => {
-                        handlers.handle_store_name(&names, *i, &mut variables, &mut stack);
-                    }
-                    Operation::LoadNameArg(i) => {
-                        handlers.handle_load_name(&names, *i, &variables, &mut stack);
-                    }
-                    Operation::BinaryAdd => {
-                        handlers.handle_binary_add(&mut stack);
-                    }
-                    Operation::BinarySubtract => {
-                        handlers.handle_binary_subtract(&mut stack);
-                    }
-                    Operation::ReturnValue => {
-                        return_var = handlers.handle_return_value(&mut stack);
+        // Each global of the module gets a flag, which is set while it's assigned
+        let globals: BTreeMap<Name, SharedGlobal> = scope
+            .variables
+            .iter()
+            .map(|name| {
+                let global_type = program.global_types.get(name).copied().unwrap_or(InferredType::None);
+                let value_type = match VarType::from_inferred(global_type) {
+                    Some(var_type) => var_type.to_llvm_type(&context),
+                    None => {
+                        let kind = CompileErrorKind::MixedGlobalTypes(name.clone());
+                        return Err(CompileError::new(&self.code.get_name(), kind));
                     }
-                    Operation::StopCode => {
-                        // StopCode marks the end of bytecode - ignore it
-                        continue;
-                    }
-                    Operation::PopTop => {
-                        handlers.handle_pop_top(&mut stack);
-                    }
-                    Operation::CallFunctionArg(i) => {
-                        // Handle function calls
-                        let arg_count = *i;
-                        
-                        match handlers.handle_call_function(&names, arg_count, &variables, &mut stack) {
-                            Ok((func_name, args)) => {
-                                if builtins::is_builtin(&func_name) {
-                                    match func_name.as_str() {
-                                        builtins::PRINT => {
-                                            // Store for later processing
-                                            print_calls.push((func_name, args));
-                                        }
-                                        _ => {
-                                            todo!("Builtin function '{}' not yet implemented", func_name);
-                                        }
-                                    }
-                                } else {
-                                    todo!("Function call to {} (not yet fully implemented)", func_name);
-                                }
-                            }
-                            Err(e) => {
-                                panic!("Function call error: {}", e);
-                            }
-                        }
-                    }
-                    _ => todo!("operation {:?}", op),
-                }
-            }
+                };
+                let value = module.add_global(value_type, None, &format!("global.{}", name));
+                value.set_initializer(&value_type.const_zero());
+                let bool_type = context.bool_type();
+                let assigned = module.add_global(bool_type, None, &format!("global.{}.assigned", name));
+                assigned.set_initializer(&bool_type.const_zero());
+                let global = SharedGlobal {
+                    value,
+                    value_type,
+                    assigned,
+                };
+                Ok((name.clone(), global))
+            })
+            .collect::<CompileResult<_>>()?;
 
-            // Second pass: Create LLVM allocations for stored variables
-            for (name, owned_var) in &variables {
-                if !owned_var.is_temp {
-                    let llvm_type = owned_var.var_type.to_llvm_type(&context);
-                    let llvm_value = owned_var.to_llvm_value(&context);
-                    
-                    let llvm_ptr = builder
-                        .build_alloca(llvm_type, name)
-                        .expect(&format!("expected llvm to create a local pointer for variable - {:?}", name));
-                    
-                    builder
-                        .build_store(llvm_ptr, llvm_value)
-                        .expect(&format!("llvm to store variable of type {:?}", llvm_type));
-                    
-                    let llvm_var = LlvmVariable {
-                        ptr: BasicValueEnum::PointerValue(llvm_ptr),
-                        v_type: llvm_type,
-                        value: llvm_value,
-                    };
-                    
-                    llvm_variables.insert(name.clone(), llvm_var);
-                }
-            }
+        // All functions are declared first, so that calls can refer to those emitted later
+        let functions: Vec<Option<FunctionValue>> = program
+            .functions
+            .iter()
+            .enumerate()
+            .map(|(function, ir)| {
+                ir.as_ref()
+                    .map(|ir| self.declare_function(&context, &module, function, scope.functions[function], ir))
+                    .transpose()
+            })
+            .collect::<CompileResult<_>>()?;
 
-            // Third pass: Handle deferred operations (print calls)
-            for (func_name, args) in print_calls {
-                match func_name.as_str() {
-                    builtins::PRINT => {
-                        if let Some(arg) = args.first() {
-                            let llvm_arg = LlvmVariable {
-                                v_type: arg.var_type.to_llvm_type(&context),
-                                ptr: context.i32_type().const_zero().into(), // Placeholder
-                                value: arg.to_llvm_value(&context),
-                            };
-                            builtins::handle_print_builtin(&builder, &module, &llvm_arg);
-                        }
-                    }
-                    _ => {
-                        todo!("Builtin function '{}' not yet implemented", func_name);
-                    }
+        let program_decls = ProgramDecls {
+            version: self.version,
+            functions: &functions,
+            globals: &globals,
+            libc,
+        };
+        for (ir, function) in program.functions.iter().zip(&functions) {
+            let (Some(ir), Some(function)) = (ir, function) else {
+                continue;
+            };
+            let blocks: Vec<BasicBlock> = (0..ir.blocks.len())
+                .map(|block| match block {
+                    0 => context.append_basic_block(*function, "entry"),
+                    _ => context.append_basic_block(*function, &format!("block{}", block)),
+                })
+                .collect();
+            let mut handlers = LlvmHandlers::new(&context, &builder, ir, *function, program_decls);
+            let mut exits: Vec<BasicBlock> = Vec::with_capacity(blocks.len());
+            for (block, ir_block) in ir.blocks.iter().enumerate() {
+                builder.position_at_end(blocks[block]);
+                for value in &ir_block.values {
+                    handlers.handle_value(*value)?;
                 }
+                // Checks for errors may split the block, so its edges leave from the last part
+                exits.push(builder.get_insert_block().expect("builder to be positioned in a block"));
+                handlers.handle_terminator(&ir_block.terminator, &blocks)?;
             }
+            handlers.complete_phis(&exits);
+        }
 
-            // Finally: Handle return instruction
-            if let Some(ret_var) = return_var {
-                let llvm_value = ret_var.to_llvm_value(&context);
-                let _ = builder.build_return(Some(&llvm_value));
-            }
+        Ok(module.print_to_string().to_string())
+    }
+
+    // LLVM function of the code object, whose parameters and return value need a native type
+    fn declare_function<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        function: FunctionId,
+        code_block: &CodeBlock,
+        ir: &Function,
+    ) -> CompileResult<FunctionValue<'ctx>> {
+        let native_type = |inferred: InferredType, kind: CompileErrorKind| {
+            VarType::from_inferred(inferred)
+                .map(|var_type| var_type.to_llvm_type(context))
+                .ok_or_else(|| CompileError::new(&ir.name, kind))
+        };
+        // Code that never returns gets the type of None
+        let return_type = ir.return_type.unwrap_or(InferredType::None);
+        let fn_ret_type = native_type(return_type, CompileErrorKind::MixedReturnTypes)?;
+        // The tuple and dict of *args and **kwargs have no native values yet
+        let mut param_types: Vec<BasicMetadataTypeEnum> = Vec::new();
+        let params = ir.param_types.iter().take(named_param_count(code_block));
+        for (param_type, name) in params.zip(code_block.get_varnames()) {
+            let param_type = param_type.unwrap_or(InferredType::Dynamic);
+            param_types.push(native_type(param_type, CompileErrorKind::MixedParamTypes(name))?.into());
+        }
+        let fn_type = fn_ret_type.fn_type(&param_types, false);
+        // Functions of the program are private to it, so that they can't take the place of C library ones
+        Ok(match function {
+            0 => module.add_function("main", fn_type, None),
+            _ => module.add_function(&ir.name, fn_type, Some(Linkage::Internal)),
+        })
+    }
 
-            fn_idx += 1;
+    // Analyses the bytecode of the code object and builds its IR. The IR of the module tells which of its names
+    // are bound to functions, the others the functions read being then shared with them.
+    fn build_function(&self, code_block: &CodeBlock, scope: &mut Scope) -> CompileResult<Function> {
+        // The bytecode has to keep the stack consistent on all paths for the stack model below
+        let cfg = ControlFlowGraph::new(code_block);
+        let depths = StackDepths::analyze(self.version, code_block, &cfg)
+            .map_err(|err| CompileError::new(&code_block.get_name(), CompileErrorKind::StackUsage(err)))?;
+        let types = TypeInference::analyze(self.version, code_block, &cfg, &depths);
+        let liveness = Liveness::analyze(code_block, &cfg);
+        for unassigned_use in &liveness.unassigned_uses {
+            eprintln!("Warning: in {:?}, {}", code_block.get_name(), unassigned_use);
         }
 
-        module.print_to_string().to_string()
+        let ir = IrBuilder::new(self.version, code_block, &cfg, &liveness, &types, scope).build()?;
+        if !code_block.is_module() {
+            return Ok(ir);
+        }
+        let shared = scope.variables.len();
+        scope.bind_globals(&ir);
+        if scope.variables.len() == shared {
+            return Ok(ir);
+        }
+        // Built again to access the newly shared names through their globals
+        IrBuilder::new(self.version, code_block, &cfg, &liveness, &types, scope).build()
     }
 
     // TODO: Refactor this or export file reading/writing to a separate struct
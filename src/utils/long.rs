@@ -0,0 +1,144 @@
+use std::fmt;
+
+// Marshal stores integers outside of the 32-bit range as 15-bit digits, regardless
+// of the digit size CPython uses internally
+pub const DIGIT_SHIFT: u32 = 15;
+pub const DIGIT_MASK: u16 = (1 << DIGIT_SHIFT) - 1;
+
+// Arbitrary-precision integer, kept in the marshal representation so that it can be
+// written back unchanged: sign and magnitude as base 2^15 digits, least significant first
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PyLong {
+    pub negative: bool,
+    pub digits: Vec<u16>,
+}
+
+impl PyLong {
+    // Lossless conversion, None if the value doesn't fit
+    pub fn to_i64(&self) -> Option<i64> {
+        let mut magnitude: u64 = 0;
+        for digit in self.digits.iter().rev() {
+            if magnitude.leading_zeros() < DIGIT_SHIFT {
+                return None;
+            }
+            magnitude = (magnitude << DIGIT_SHIFT) | *digit as u64;
+        }
+
+        if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+}
+
+impl fmt::Display for PyLong {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(value) = self.to_i64() {
+            return write!(f, "{}", value);
+        }
+
+        // Repeatedly divide the magnitude by 10^4, collecting the remainders
+        const CHUNK: u32 = 10_000;
+        let mut magnitude: Vec<u32> = self.digits.iter().map(|digit| *digit as u32).collect();
+        let mut chunks: Vec<u32> = Vec::new();
+        while magnitude.iter().any(|digit| *digit != 0) {
+            let mut remainder: u32 = 0;
+            for digit in magnitude.iter_mut().rev() {
+                let current = (remainder << DIGIT_SHIFT) | *digit;
+                *digit = current / CHUNK;
+                remainder = current % CHUNK;
+            }
+            chunks.push(remainder);
+        }
+
+        let mut text = String::new();
+        if self.negative {
+            text.push('-');
+        }
+        match chunks.split_last() {
+            Some((most_significant, rest)) => {
+                text.push_str(&most_significant.to_string());
+                for chunk in rest.iter().rev() {
+                    text.push_str(&format!("{:04}", chunk));
+                }
+            }
+            None => text.push('0'),
+        }
+        f.write_str(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{marshalerror::MarshalErrorKind, pycachereader::PyCacheReader, var::Var};
+    use super::*;
+
+    // Reads the output of marshal.dumps(value), which flags the long as referenceable
+    fn read(marshalled: &[u8]) -> PyLong {
+        let mut reader = PyCacheReader::new(marshalled.to_vec());
+        let var = reader.read_var().expect("a valid long");
+        match var.resolve(reader.get_refs()) {
+            Var::Long(long) => long,
+            other => panic!("expected a long, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn negative_over_i64() {
+        // -2**100
+        let long = read(&[236, 249, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4]);
+        assert!(long.negative);
+        assert_eq!(long.digits, [0, 0, 0, 0, 0, 0, 1024]);
+        assert_eq!(long.to_i64(), None);
+        assert_eq!(long.to_string(), "-1267650600228229401496703205376");
+    }
+
+    #[test]
+    fn just_outside_i32() {
+        // 2**31 and -2**31 - 1
+        let long = read(&[236, 3, 0, 0, 0, 0, 0, 0, 0, 2, 0]);
+        assert_eq!(long.to_i64(), Some(2147483648));
+        let long = read(&[236, 253, 255, 255, 255, 1, 0, 0, 0, 2, 0]);
+        assert_eq!(long.to_i64(), Some(-2147483649));
+        assert_eq!(long.to_string(), "-2147483649");
+    }
+
+    #[test]
+    fn bounds_of_i64() {
+        // 2**63 - 1, -2**63 and 2**63
+        let long = read(&[236, 5, 0, 0, 0, 255, 127, 255, 127, 255, 127, 255, 127, 7, 0]);
+        assert_eq!(long.to_i64(), Some(i64::MAX));
+        let long = read(&[236, 251, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 8, 0]);
+        assert_eq!(long.to_i64(), Some(i64::MIN));
+        let long = read(&[236, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 0]);
+        assert_eq!(long.to_i64(), None);
+        assert_eq!(long.to_string(), "9223372036854775808");
+    }
+
+    #[test]
+    fn decimal_chunks_with_zeros() {
+        // 12345678901234567890123, whose chunks of 4 decimal digits other than the first have leading zeros
+        let long = read(&[236, 5, 0, 0, 0, 203, 68, 132, 98, 217, 57, 178, 21, 212, 41]);
+        assert_eq!(long.to_string(), "12345678901234567890123");
+        assert_eq!(PyLong::default().to_string(), "0");
+    }
+
+    #[test]
+    fn invalid_digits() {
+        let error = |marshalled: Vec<u8>| {
+            PyCacheReader::new(marshalled)
+                .read_var()
+                .expect_err("an invalid long")
+                .kind
+        };
+        assert_eq!(
+            error(vec![108, 1, 0, 0, 0, 0, 128]),
+            MarshalErrorKind::InvalidLongDigit(32768)
+        );
+        assert_eq!(
+            error(vec![108, 2, 0, 0, 0, 1, 0, 0, 0]),
+            MarshalErrorKind::InvalidLongDigit(0)
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use super::version::PythonVersion;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarshalErrorKind {
+    UnexpectedEof { needed: usize, available: usize },
+    InvalidHeader(String),
+    UnknownType,                                         // marshal type code not known to the reader
+    UnknownOpcode { opcode: u8, version: PythonVersion }, // opcode missing from the version's table
+    InvalidCodeSize(usize),                              // co_code isn't made of whole code units
+    InvalidOperand(String),                              // argument of an operation not matching the code object
+    InvalidSize(i64),                                    // negative size of a sized object
+    InvalidFloat(String),                                // float stored as text which can't be parsed
+    InvalidString(&'static str),                         // text not valid in the encoding of its type
+    InvalidLongDigit(u16),                               // digit of TYPE_LONG out of range, or leading zero
+    UnexpectedVar { expected: &'static str, found: String },
+    InvalidRef(u32),                                     // TYPE_REF to an object which hasn't been read yet
+    InvalidLineTable(&'static str),                      // malformed co_lnotab or location table
+    InvalidExceptionTable(String),                       // malformed or not matching the operations
+}
+
+// Error raised while reading a .pyc file, pointing at where and in what it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarshalError {
+    pub kind: MarshalErrorKind,
+    pub offset: usize,          // byte offset in the file
+    pub type_code: Option<u8>,  // marshal type code of the innermost object being read
+    pub context: Vec<String>,   // what was being read, outermost first
+}
+
+impl fmt::Display for MarshalErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarshalErrorKind::UnexpectedEof { needed, available } => write!(
+                f,
+                "unexpected end of file (needed {} more bytes, {} available)",
+                needed, available
+            ),
+            MarshalErrorKind::InvalidHeader(msg) => write!(f, "invalid header - {}", msg),
+            MarshalErrorKind::UnknownType => write!(f, "unknown marshal type"),
+            MarshalErrorKind::UnknownOpcode { opcode, version } => {
+                write!(f, "unknown opcode {} for Python {}", opcode, version)
+            }
+            MarshalErrorKind::InvalidCodeSize(size) => {
+                write!(f, "co_code size {} is not a multiple of the 2-byte code unit", size)
+            }
+            MarshalErrorKind::InvalidOperand(msg) => write!(f, "invalid operand - {}", msg),
+            MarshalErrorKind::InvalidSize(size) => write!(f, "invalid object size {}", size),
+            MarshalErrorKind::InvalidFloat(text) => write!(f, "invalid float {:?}", text),
+            MarshalErrorKind::InvalidString(encoding) => write!(f, "invalid {} string", encoding),
+            MarshalErrorKind::InvalidLongDigit(digit) => write!(f, "invalid long digit {}", digit),
+            MarshalErrorKind::UnexpectedVar { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            MarshalErrorKind::InvalidRef(idx) => write!(f, "reference to unknown object {}", idx),
+            MarshalErrorKind::InvalidLineTable(msg) => write!(f, "invalid line table - {}", msg),
+            MarshalErrorKind::InvalidExceptionTable(msg) => write!(f, "invalid exception table - {}", msg),
+        }
+    }
+}
+
+impl fmt::Display for MarshalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)?;
+        if let Some(type_code) = self.type_code {
+            write!(f, " (type code {:?}, hex: {:#x})", (type_code & 0x7F) as char, type_code)?;
+        }
+        if !self.context.is_empty() {
+            write!(f, " while reading {}", self.context.join(" > "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MarshalError {}
+
+pub type MarshalResult<T> = Result<T, MarshalError>;
@@ -1,7 +1,23 @@
-pub mod builtins;
-pub mod code;
-pub mod llvm;
-pub mod operations;
-pub mod pycachereader;
-pub mod pycachegenerator;
-pub mod var;
\ No newline at end of file
+pub mod builtins;
+pub mod cfg;
+pub mod code;
+pub mod compileerror;
+pub mod exceptiontable;
+pub mod instruction;
+pub mod ir;
+pub mod irbuilder;
+pub mod linetable;
+pub mod liveness;
+pub mod llvm;
+pub mod long;
+pub mod marshalerror;
+pub mod opcodes;
+pub mod operations;
+pub mod pycachereader;
+pub mod pycheader;
+pub mod pycachegenerator;
+pub mod pycachewriter;
+pub mod stackdepth;
+pub mod typeinference;
+pub mod var;
+pub mod version;
@@ -0,0 +1,1032 @@
+// Generated by src/python/opcodes.py from the opcode maps of CPython - do not edit by hand
+use super::version::PythonVersion;
+
+// Opcodes of all the supported versions, except EXTENDED_ARG which is folded into arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    AsyncGenWrap,
+    BeforeAsyncWith,
+    BeforeWith,
+    BeginFinally,
+    BinaryAdd,
+    BinaryAnd,
+    BinaryFloorDivide,
+    BinaryLshift,
+    BinaryMatrixMultiply,
+    BinaryModulo,
+    BinaryMultiply,
+    BinaryOp,
+    BinaryOr,
+    BinaryPower,
+    BinaryRshift,
+    BinarySlice,
+    BinarySubscr,
+    BinarySubtract,
+    BinaryTrueDivide,
+    BinaryXor,
+    BuildConstKeyMap,
+    BuildList,
+    BuildListUnpack,
+    BuildMap,
+    BuildMapUnpack,
+    BuildMapUnpackWithCall,
+    BuildSet,
+    BuildSetUnpack,
+    BuildSlice,
+    BuildString,
+    BuildTuple,
+    BuildTupleUnpack,
+    BuildTupleUnpackWithCall,
+    Cache,
+    Call,
+    CallFinally,
+    CallFunction,
+    CallFunctionEx,
+    CallFunctionKw,
+    CallIntrinsic1,
+    CallIntrinsic2,
+    CallKw,
+    CallMethod,
+    CheckEgMatch,
+    CheckExcMatch,
+    CleanupThrow,
+    CompareOp,
+    ContainsOp,
+    ConvertValue,
+    Copy,
+    CopyDictWithoutKeys,
+    CopyFreeVars,
+    DeleteAttr,
+    DeleteDeref,
+    DeleteFast,
+    DeleteGlobal,
+    DeleteName,
+    DeleteSubscr,
+    DictMerge,
+    DictUpdate,
+    DupTop,
+    DupTopTwo,
+    EndAsyncFor,
+    EndFinally,
+    EndFor,
+    EndSend,
+    EnterExecutor,
+    ExitInitCheck,
+    FormatSimple,
+    FormatValue,
+    FormatWithSpec,
+    ForIter,
+    GenStart,
+    GetAiter,
+    GetAnext,
+    GetAwaitable,
+    GetIter,
+    GetLen,
+    GetYieldFromIter,
+    ImportFrom,
+    ImportName,
+    ImportStar,
+    InplaceAdd,
+    InplaceAnd,
+    InplaceFloorDivide,
+    InplaceLshift,
+    InplaceMatrixMultiply,
+    InplaceModulo,
+    InplaceMultiply,
+    InplaceOr,
+    InplacePower,
+    InplaceRshift,
+    InplaceSubtract,
+    InplaceTrueDivide,
+    InplaceXor,
+    InterpreterExit,
+    IsOp,
+    JumpAbsolute,
+    JumpBackward,
+    JumpBackwardNoInterrupt,
+    JumpForward,
+    JumpIfFalseOrPop,
+    JumpIfNotExcMatch,
+    JumpIfTrueOrPop,
+    KwNames,
+    ListAppend,
+    ListExtend,
+    ListToTuple,
+    LoadAssertionError,
+    LoadAttr,
+    LoadBuildClass,
+    LoadClassderef,
+    LoadClosure,
+    LoadConst,
+    LoadDeref,
+    LoadFast,
+    LoadFastAndClear,
+    LoadFastCheck,
+    LoadFastLoadFast,
+    LoadFromDictOrDeref,
+    LoadFromDictOrGlobals,
+    LoadGlobal,
+    LoadLocals,
+    LoadMethod,
+    LoadName,
+    LoadSuperAttr,
+    MakeCell,
+    MakeFunction,
+    MapAdd,
+    MatchClass,
+    MatchKeys,
+    MatchMapping,
+    MatchSequence,
+    Nop,
+    PopBlock,
+    PopExcept,
+    PopFinally,
+    PopJumpBackwardIfFalse,
+    PopJumpBackwardIfNone,
+    PopJumpBackwardIfNotNone,
+    PopJumpBackwardIfTrue,
+    PopJumpForwardIfFalse,
+    PopJumpForwardIfNone,
+    PopJumpForwardIfNotNone,
+    PopJumpForwardIfTrue,
+    PopJumpIfFalse,
+    PopJumpIfNone,
+    PopJumpIfNotNone,
+    PopJumpIfTrue,
+    PopTop,
+    Precall,
+    PrepReraiseStar,
+    PrintExpr,
+    PushExcInfo,
+    PushNull,
+    RaiseVarargs,
+    Reraise,
+    Reserved,
+    Resume,
+    ReturnConst,
+    ReturnGenerator,
+    ReturnValue,
+    RotFour,
+    RotN,
+    RotThree,
+    RotTwo,
+    Send,
+    SetupAnnotations,
+    SetupAsyncWith,
+    SetupFinally,
+    SetupWith,
+    SetAdd,
+    SetFunctionAttribute,
+    SetUpdate,
+    StoreAttr,
+    StoreDeref,
+    StoreFast,
+    StoreFastLoadFast,
+    StoreFastStoreFast,
+    StoreGlobal,
+    StoreName,
+    StoreSlice,
+    StoreSubscr,
+    Swap,
+    ToBool,
+    UnaryInvert,
+    UnaryNegative,
+    UnaryNot,
+    UnaryPositive,
+    UnpackEx,
+    UnpackSequence,
+    WithCleanupFinish,
+    WithCleanupStart,
+    WithExceptStart,
+    YieldFrom,
+    YieldValue,
+}
+
+// Magic numbers (first 2 bytes of a .pyc file) of the supported CPython versions
+pub const MAGIC_NUMBERS: [(u16, PythonVersion); 6] = [
+    (3413, PythonVersion { major: 3, minor: 8 }),
+    (3425, PythonVersion { major: 3, minor: 9 }),
+    (3439, PythonVersion { major: 3, minor: 10 }),
+    (3495, PythonVersion { major: 3, minor: 11 }),
+    (3531, PythonVersion { major: 3, minor: 12 }),
+    (3571, PythonVersion { major: 3, minor: 13 }),
+];
+
+// Opcode of EXTENDED_ARG, which is folded into the argument of the following operation
+pub fn extended_arg(version: &PythonVersion) -> Option<u8> {
+    match (version.major, version.minor) {
+        (3, 8) => Some(144),
+        (3, 9) => Some(144),
+        (3, 10) => Some(144),
+        (3, 11) => Some(144),
+        (3, 12) => Some(144),
+        (3, 13) => Some(71),
+        _ => None,
+    }
+}
+
+// Number of inline CACHE code units following an operation (3.11+)
+pub fn inline_cache_entries(version: &PythonVersion, opcode: u8) -> usize {
+    match (version.major, version.minor) {
+        (3, 11) => match opcode {
+            25 => 4, // BINARY_SUBSCR
+            60 => 1, // STORE_SUBSCR
+            92 => 1, // UNPACK_SEQUENCE
+            95 => 4, // STORE_ATTR
+            106 => 4, // LOAD_ATTR
+            107 => 2, // COMPARE_OP
+            116 => 5, // LOAD_GLOBAL
+            122 => 1, // BINARY_OP
+            160 => 10, // LOAD_METHOD
+            166 => 1, // PRECALL
+            171 => 4, // CALL
+            _ => 0,
+        },
+        (3, 12) => match opcode {
+            25 => 1, // BINARY_SUBSCR
+            60 => 1, // STORE_SUBSCR
+            92 => 1, // UNPACK_SEQUENCE
+            93 => 1, // FOR_ITER
+            95 => 4, // STORE_ATTR
+            106 => 9, // LOAD_ATTR
+            107 => 1, // COMPARE_OP
+            116 => 4, // LOAD_GLOBAL
+            122 => 1, // BINARY_OP
+            123 => 1, // SEND
+            141 => 1, // LOAD_SUPER_ATTR
+            171 => 3, // CALL
+            _ => 0,
+        },
+        (3, 13) => match opcode {
+            5 => 1, // BINARY_SUBSCR
+            39 => 1, // STORE_SUBSCR
+            40 => 3, // TO_BOOL
+            45 => 1, // BINARY_OP
+            53 => 3, // CALL
+            58 => 1, // COMPARE_OP
+            59 => 1, // CONTAINS_OP
+            72 => 1, // FOR_ITER
+            77 => 1, // JUMP_BACKWARD
+            82 => 9, // LOAD_ATTR
+            91 => 4, // LOAD_GLOBAL
+            93 => 1, // LOAD_SUPER_ATTR
+            97 => 1, // POP_JUMP_IF_FALSE
+            98 => 1, // POP_JUMP_IF_NONE
+            99 => 1, // POP_JUMP_IF_NOT_NONE
+            100 => 1, // POP_JUMP_IF_TRUE
+            104 => 1, // SEND
+            108 => 4, // STORE_ATTR
+            117 => 1, // UNPACK_SEQUENCE
+            _ => 0,
+        },
+        _ => 0,
+    }
+}
+
+pub fn decode(version: &PythonVersion, opcode: u8) -> Option<Opcode> {
+    match (version.major, version.minor) {
+        (3, 8) => decode_3_8(opcode),
+        (3, 9) => decode_3_9(opcode),
+        (3, 10) => decode_3_10(opcode),
+        (3, 11) => decode_3_11(opcode),
+        (3, 12) => decode_3_12(opcode),
+        (3, 13) => decode_3_13(opcode),
+        _ => None,
+    }
+}
+
+fn decode_3_8(opcode: u8) -> Option<Opcode> {
+    match opcode {
+        1 => Some(Opcode::PopTop),
+        2 => Some(Opcode::RotTwo),
+        3 => Some(Opcode::RotThree),
+        4 => Some(Opcode::DupTop),
+        5 => Some(Opcode::DupTopTwo),
+        6 => Some(Opcode::RotFour),
+        9 => Some(Opcode::Nop),
+        10 => Some(Opcode::UnaryPositive),
+        11 => Some(Opcode::UnaryNegative),
+        12 => Some(Opcode::UnaryNot),
+        15 => Some(Opcode::UnaryInvert),
+        16 => Some(Opcode::BinaryMatrixMultiply),
+        17 => Some(Opcode::InplaceMatrixMultiply),
+        19 => Some(Opcode::BinaryPower),
+        20 => Some(Opcode::BinaryMultiply),
+        22 => Some(Opcode::BinaryModulo),
+        23 => Some(Opcode::BinaryAdd),
+        24 => Some(Opcode::BinarySubtract),
+        25 => Some(Opcode::BinarySubscr),
+        26 => Some(Opcode::BinaryFloorDivide),
+        27 => Some(Opcode::BinaryTrueDivide),
+        28 => Some(Opcode::InplaceFloorDivide),
+        29 => Some(Opcode::InplaceTrueDivide),
+        50 => Some(Opcode::GetAiter),
+        51 => Some(Opcode::GetAnext),
+        52 => Some(Opcode::BeforeAsyncWith),
+        53 => Some(Opcode::BeginFinally),
+        54 => Some(Opcode::EndAsyncFor),
+        55 => Some(Opcode::InplaceAdd),
+        56 => Some(Opcode::InplaceSubtract),
+        57 => Some(Opcode::InplaceMultiply),
+        59 => Some(Opcode::InplaceModulo),
+        60 => Some(Opcode::StoreSubscr),
+        61 => Some(Opcode::DeleteSubscr),
+        62 => Some(Opcode::BinaryLshift),
+        63 => Some(Opcode::BinaryRshift),
+        64 => Some(Opcode::BinaryAnd),
+        65 => Some(Opcode::BinaryXor),
+        66 => Some(Opcode::BinaryOr),
+        67 => Some(Opcode::InplacePower),
+        68 => Some(Opcode::GetIter),
+        69 => Some(Opcode::GetYieldFromIter),
+        70 => Some(Opcode::PrintExpr),
+        71 => Some(Opcode::LoadBuildClass),
+        72 => Some(Opcode::YieldFrom),
+        73 => Some(Opcode::GetAwaitable),
+        75 => Some(Opcode::InplaceLshift),
+        76 => Some(Opcode::InplaceRshift),
+        77 => Some(Opcode::InplaceAnd),
+        78 => Some(Opcode::InplaceXor),
+        79 => Some(Opcode::InplaceOr),
+        81 => Some(Opcode::WithCleanupStart),
+        82 => Some(Opcode::WithCleanupFinish),
+        83 => Some(Opcode::ReturnValue),
+        84 => Some(Opcode::ImportStar),
+        85 => Some(Opcode::SetupAnnotations),
+        86 => Some(Opcode::YieldValue),
+        87 => Some(Opcode::PopBlock),
+        88 => Some(Opcode::EndFinally),
+        89 => Some(Opcode::PopExcept),
+        90 => Some(Opcode::StoreName),
+        91 => Some(Opcode::DeleteName),
+        92 => Some(Opcode::UnpackSequence),
+        93 => Some(Opcode::ForIter),
+        94 => Some(Opcode::UnpackEx),
+        95 => Some(Opcode::StoreAttr),
+        96 => Some(Opcode::DeleteAttr),
+        97 => Some(Opcode::StoreGlobal),
+        98 => Some(Opcode::DeleteGlobal),
+        100 => Some(Opcode::LoadConst),
+        101 => Some(Opcode::LoadName),
+        102 => Some(Opcode::BuildTuple),
+        103 => Some(Opcode::BuildList),
+        104 => Some(Opcode::BuildSet),
+        105 => Some(Opcode::BuildMap),
+        106 => Some(Opcode::LoadAttr),
+        107 => Some(Opcode::CompareOp),
+        108 => Some(Opcode::ImportName),
+        109 => Some(Opcode::ImportFrom),
+        110 => Some(Opcode::JumpForward),
+        111 => Some(Opcode::JumpIfFalseOrPop),
+        112 => Some(Opcode::JumpIfTrueOrPop),
+        113 => Some(Opcode::JumpAbsolute),
+        114 => Some(Opcode::PopJumpIfFalse),
+        115 => Some(Opcode::PopJumpIfTrue),
+        116 => Some(Opcode::LoadGlobal),
+        122 => Some(Opcode::SetupFinally),
+        124 => Some(Opcode::LoadFast),
+        125 => Some(Opcode::StoreFast),
+        126 => Some(Opcode::DeleteFast),
+        130 => Some(Opcode::RaiseVarargs),
+        131 => Some(Opcode::CallFunction),
+        132 => Some(Opcode::MakeFunction),
+        133 => Some(Opcode::BuildSlice),
+        135 => Some(Opcode::LoadClosure),
+        136 => Some(Opcode::LoadDeref),
+        137 => Some(Opcode::StoreDeref),
+        138 => Some(Opcode::DeleteDeref),
+        141 => Some(Opcode::CallFunctionKw),
+        142 => Some(Opcode::CallFunctionEx),
+        143 => Some(Opcode::SetupWith),
+        145 => Some(Opcode::ListAppend),
+        146 => Some(Opcode::SetAdd),
+        147 => Some(Opcode::MapAdd),
+        148 => Some(Opcode::LoadClassderef),
+        149 => Some(Opcode::BuildListUnpack),
+        150 => Some(Opcode::BuildMapUnpack),
+        151 => Some(Opcode::BuildMapUnpackWithCall),
+        152 => Some(Opcode::BuildTupleUnpack),
+        153 => Some(Opcode::BuildSetUnpack),
+        154 => Some(Opcode::SetupAsyncWith),
+        155 => Some(Opcode::FormatValue),
+        156 => Some(Opcode::BuildConstKeyMap),
+        157 => Some(Opcode::BuildString),
+        158 => Some(Opcode::BuildTupleUnpackWithCall),
+        160 => Some(Opcode::LoadMethod),
+        161 => Some(Opcode::CallMethod),
+        162 => Some(Opcode::CallFinally),
+        163 => Some(Opcode::PopFinally),
+        _ => None,
+    }
+}
+
+fn decode_3_9(opcode: u8) -> Option<Opcode> {
+    match opcode {
+        1 => Some(Opcode::PopTop),
+        2 => Some(Opcode::RotTwo),
+        3 => Some(Opcode::RotThree),
+        4 => Some(Opcode::DupTop),
+        5 => Some(Opcode::DupTopTwo),
+        6 => Some(Opcode::RotFour),
+        9 => Some(Opcode::Nop),
+        10 => Some(Opcode::UnaryPositive),
+        11 => Some(Opcode::UnaryNegative),
+        12 => Some(Opcode::UnaryNot),
+        15 => Some(Opcode::UnaryInvert),
+        16 => Some(Opcode::BinaryMatrixMultiply),
+        17 => Some(Opcode::InplaceMatrixMultiply),
+        19 => Some(Opcode::BinaryPower),
+        20 => Some(Opcode::BinaryMultiply),
+        22 => Some(Opcode::BinaryModulo),
+        23 => Some(Opcode::BinaryAdd),
+        24 => Some(Opcode::BinarySubtract),
+        25 => Some(Opcode::BinarySubscr),
+        26 => Some(Opcode::BinaryFloorDivide),
+        27 => Some(Opcode::BinaryTrueDivide),
+        28 => Some(Opcode::InplaceFloorDivide),
+        29 => Some(Opcode::InplaceTrueDivide),
+        48 => Some(Opcode::Reraise),
+        49 => Some(Opcode::WithExceptStart),
+        50 => Some(Opcode::GetAiter),
+        51 => Some(Opcode::GetAnext),
+        52 => Some(Opcode::BeforeAsyncWith),
+        54 => Some(Opcode::EndAsyncFor),
+        55 => Some(Opcode::InplaceAdd),
+        56 => Some(Opcode::InplaceSubtract),
+        57 => Some(Opcode::InplaceMultiply),
+        59 => Some(Opcode::InplaceModulo),
+        60 => Some(Opcode::StoreSubscr),
+        61 => Some(Opcode::DeleteSubscr),
+        62 => Some(Opcode::BinaryLshift),
+        63 => Some(Opcode::BinaryRshift),
+        64 => Some(Opcode::BinaryAnd),
+        65 => Some(Opcode::BinaryXor),
+        66 => Some(Opcode::BinaryOr),
+        67 => Some(Opcode::InplacePower),
+        68 => Some(Opcode::GetIter),
+        69 => Some(Opcode::GetYieldFromIter),
+        70 => Some(Opcode::PrintExpr),
+        71 => Some(Opcode::LoadBuildClass),
+        72 => Some(Opcode::YieldFrom),
+        73 => Some(Opcode::GetAwaitable),
+        74 => Some(Opcode::LoadAssertionError),
+        75 => Some(Opcode::InplaceLshift),
+        76 => Some(Opcode::InplaceRshift),
+        77 => Some(Opcode::InplaceAnd),
+        78 => Some(Opcode::InplaceXor),
+        79 => Some(Opcode::InplaceOr),
+        82 => Some(Opcode::ListToTuple),
+        83 => Some(Opcode::ReturnValue),
+        84 => Some(Opcode::ImportStar),
+        85 => Some(Opcode::SetupAnnotations),
+        86 => Some(Opcode::YieldValue),
+        87 => Some(Opcode::PopBlock),
+        89 => Some(Opcode::PopExcept),
+        90 => Some(Opcode::StoreName),
+        91 => Some(Opcode::DeleteName),
+        92 => Some(Opcode::UnpackSequence),
+        93 => Some(Opcode::ForIter),
+        94 => Some(Opcode::UnpackEx),
+        95 => Some(Opcode::StoreAttr),
+        96 => Some(Opcode::DeleteAttr),
+        97 => Some(Opcode::StoreGlobal),
+        98 => Some(Opcode::DeleteGlobal),
+        100 => Some(Opcode::LoadConst),
+        101 => Some(Opcode::LoadName),
+        102 => Some(Opcode::BuildTuple),
+        103 => Some(Opcode::BuildList),
+        104 => Some(Opcode::BuildSet),
+        105 => Some(Opcode::BuildMap),
+        106 => Some(Opcode::LoadAttr),
+        107 => Some(Opcode::CompareOp),
+        108 => Some(Opcode::ImportName),
+        109 => Some(Opcode::ImportFrom),
+        110 => Some(Opcode::JumpForward),
+        111 => Some(Opcode::JumpIfFalseOrPop),
+        112 => Some(Opcode::JumpIfTrueOrPop),
+        113 => Some(Opcode::JumpAbsolute),
+        114 => Some(Opcode::PopJumpIfFalse),
+        115 => Some(Opcode::PopJumpIfTrue),
+        116 => Some(Opcode::LoadGlobal),
+        117 => Some(Opcode::IsOp),
+        118 => Some(Opcode::ContainsOp),
+        121 => Some(Opcode::JumpIfNotExcMatch),
+        122 => Some(Opcode::SetupFinally),
+        124 => Some(Opcode::LoadFast),
+        125 => Some(Opcode::StoreFast),
+        126 => Some(Opcode::DeleteFast),
+        130 => Some(Opcode::RaiseVarargs),
+        131 => Some(Opcode::CallFunction),
+        132 => Some(Opcode::MakeFunction),
+        133 => Some(Opcode::BuildSlice),
+        135 => Some(Opcode::LoadClosure),
+        136 => Some(Opcode::LoadDeref),
+        137 => Some(Opcode::StoreDeref),
+        138 => Some(Opcode::DeleteDeref),
+        141 => Some(Opcode::CallFunctionKw),
+        142 => Some(Opcode::CallFunctionEx),
+        143 => Some(Opcode::SetupWith),
+        145 => Some(Opcode::ListAppend),
+        146 => Some(Opcode::SetAdd),
+        147 => Some(Opcode::MapAdd),
+        148 => Some(Opcode::LoadClassderef),
+        154 => Some(Opcode::SetupAsyncWith),
+        155 => Some(Opcode::FormatValue),
+        156 => Some(Opcode::BuildConstKeyMap),
+        157 => Some(Opcode::BuildString),
+        160 => Some(Opcode::LoadMethod),
+        161 => Some(Opcode::CallMethod),
+        162 => Some(Opcode::ListExtend),
+        163 => Some(Opcode::SetUpdate),
+        164 => Some(Opcode::DictMerge),
+        165 => Some(Opcode::DictUpdate),
+        _ => None,
+    }
+}
+
+fn decode_3_10(opcode: u8) -> Option<Opcode> {
+    match opcode {
+        1 => Some(Opcode::PopTop),
+        2 => Some(Opcode::RotTwo),
+        3 => Some(Opcode::RotThree),
+        4 => Some(Opcode::DupTop),
+        5 => Some(Opcode::DupTopTwo),
+        6 => Some(Opcode::RotFour),
+        9 => Some(Opcode::Nop),
+        10 => Some(Opcode::UnaryPositive),
+        11 => Some(Opcode::UnaryNegative),
+        12 => Some(Opcode::UnaryNot),
+        15 => Some(Opcode::UnaryInvert),
+        16 => Some(Opcode::BinaryMatrixMultiply),
+        17 => Some(Opcode::InplaceMatrixMultiply),
+        19 => Some(Opcode::BinaryPower),
+        20 => Some(Opcode::BinaryMultiply),
+        22 => Some(Opcode::BinaryModulo),
+        23 => Some(Opcode::BinaryAdd),
+        24 => Some(Opcode::BinarySubtract),
+        25 => Some(Opcode::BinarySubscr),
+        26 => Some(Opcode::BinaryFloorDivide),
+        27 => Some(Opcode::BinaryTrueDivide),
+        28 => Some(Opcode::InplaceFloorDivide),
+        29 => Some(Opcode::InplaceTrueDivide),
+        30 => Some(Opcode::GetLen),
+        31 => Some(Opcode::MatchMapping),
+        32 => Some(Opcode::MatchSequence),
+        33 => Some(Opcode::MatchKeys),
+        34 => Some(Opcode::CopyDictWithoutKeys),
+        49 => Some(Opcode::WithExceptStart),
+        50 => Some(Opcode::GetAiter),
+        51 => Some(Opcode::GetAnext),
+        52 => Some(Opcode::BeforeAsyncWith),
+        54 => Some(Opcode::EndAsyncFor),
+        55 => Some(Opcode::InplaceAdd),
+        56 => Some(Opcode::InplaceSubtract),
+        57 => Some(Opcode::InplaceMultiply),
+        59 => Some(Opcode::InplaceModulo),
+        60 => Some(Opcode::StoreSubscr),
+        61 => Some(Opcode::DeleteSubscr),
+        62 => Some(Opcode::BinaryLshift),
+        63 => Some(Opcode::BinaryRshift),
+        64 => Some(Opcode::BinaryAnd),
+        65 => Some(Opcode::BinaryXor),
+        66 => Some(Opcode::BinaryOr),
+        67 => Some(Opcode::InplacePower),
+        68 => Some(Opcode::GetIter),
+        69 => Some(Opcode::GetYieldFromIter),
+        70 => Some(Opcode::PrintExpr),
+        71 => Some(Opcode::LoadBuildClass),
+        72 => Some(Opcode::YieldFrom),
+        73 => Some(Opcode::GetAwaitable),
+        74 => Some(Opcode::LoadAssertionError),
+        75 => Some(Opcode::InplaceLshift),
+        76 => Some(Opcode::InplaceRshift),
+        77 => Some(Opcode::InplaceAnd),
+        78 => Some(Opcode::InplaceXor),
+        79 => Some(Opcode::InplaceOr),
+        82 => Some(Opcode::ListToTuple),
+        83 => Some(Opcode::ReturnValue),
+        84 => Some(Opcode::ImportStar),
+        85 => Some(Opcode::SetupAnnotations),
+        86 => Some(Opcode::YieldValue),
+        87 => Some(Opcode::PopBlock),
+        89 => Some(Opcode::PopExcept),
+        90 => Some(Opcode::StoreName),
+        91 => Some(Opcode::DeleteName),
+        92 => Some(Opcode::UnpackSequence),
+        93 => Some(Opcode::ForIter),
+        94 => Some(Opcode::UnpackEx),
+        95 => Some(Opcode::StoreAttr),
+        96 => Some(Opcode::DeleteAttr),
+        97 => Some(Opcode::StoreGlobal),
+        98 => Some(Opcode::DeleteGlobal),
+        99 => Some(Opcode::RotN),
+        100 => Some(Opcode::LoadConst),
+        101 => Some(Opcode::LoadName),
+        102 => Some(Opcode::BuildTuple),
+        103 => Some(Opcode::BuildList),
+        104 => Some(Opcode::BuildSet),
+        105 => Some(Opcode::BuildMap),
+        106 => Some(Opcode::LoadAttr),
+        107 => Some(Opcode::CompareOp),
+        108 => Some(Opcode::ImportName),
+        109 => Some(Opcode::ImportFrom),
+        110 => Some(Opcode::JumpForward),
+        111 => Some(Opcode::JumpIfFalseOrPop),
+        112 => Some(Opcode::JumpIfTrueOrPop),
+        113 => Some(Opcode::JumpAbsolute),
+        114 => Some(Opcode::PopJumpIfFalse),
+        115 => Some(Opcode::PopJumpIfTrue),
+        116 => Some(Opcode::LoadGlobal),
+        117 => Some(Opcode::IsOp),
+        118 => Some(Opcode::ContainsOp),
+        119 => Some(Opcode::Reraise),
+        121 => Some(Opcode::JumpIfNotExcMatch),
+        122 => Some(Opcode::SetupFinally),
+        124 => Some(Opcode::LoadFast),
+        125 => Some(Opcode::StoreFast),
+        126 => Some(Opcode::DeleteFast),
+        129 => Some(Opcode::GenStart),
+        130 => Some(Opcode::RaiseVarargs),
+        131 => Some(Opcode::CallFunction),
+        132 => Some(Opcode::MakeFunction),
+        133 => Some(Opcode::BuildSlice),
+        135 => Some(Opcode::LoadClosure),
+        136 => Some(Opcode::LoadDeref),
+        137 => Some(Opcode::StoreDeref),
+        138 => Some(Opcode::DeleteDeref),
+        141 => Some(Opcode::CallFunctionKw),
+        142 => Some(Opcode::CallFunctionEx),
+        143 => Some(Opcode::SetupWith),
+        145 => Some(Opcode::ListAppend),
+        146 => Some(Opcode::SetAdd),
+        147 => Some(Opcode::MapAdd),
+        148 => Some(Opcode::LoadClassderef),
+        152 => Some(Opcode::MatchClass),
+        154 => Some(Opcode::SetupAsyncWith),
+        155 => Some(Opcode::FormatValue),
+        156 => Some(Opcode::BuildConstKeyMap),
+        157 => Some(Opcode::BuildString),
+        160 => Some(Opcode::LoadMethod),
+        161 => Some(Opcode::CallMethod),
+        162 => Some(Opcode::ListExtend),
+        163 => Some(Opcode::SetUpdate),
+        164 => Some(Opcode::DictMerge),
+        165 => Some(Opcode::DictUpdate),
+        _ => None,
+    }
+}
+
+fn decode_3_11(opcode: u8) -> Option<Opcode> {
+    match opcode {
+        0 => Some(Opcode::Cache),
+        1 => Some(Opcode::PopTop),
+        2 => Some(Opcode::PushNull),
+        9 => Some(Opcode::Nop),
+        10 => Some(Opcode::UnaryPositive),
+        11 => Some(Opcode::UnaryNegative),
+        12 => Some(Opcode::UnaryNot),
+        15 => Some(Opcode::UnaryInvert),
+        25 => Some(Opcode::BinarySubscr),
+        30 => Some(Opcode::GetLen),
+        31 => Some(Opcode::MatchMapping),
+        32 => Some(Opcode::MatchSequence),
+        33 => Some(Opcode::MatchKeys),
+        35 => Some(Opcode::PushExcInfo),
+        36 => Some(Opcode::CheckExcMatch),
+        37 => Some(Opcode::CheckEgMatch),
+        49 => Some(Opcode::WithExceptStart),
+        50 => Some(Opcode::GetAiter),
+        51 => Some(Opcode::GetAnext),
+        52 => Some(Opcode::BeforeAsyncWith),
+        53 => Some(Opcode::BeforeWith),
+        54 => Some(Opcode::EndAsyncFor),
+        60 => Some(Opcode::StoreSubscr),
+        61 => Some(Opcode::DeleteSubscr),
+        68 => Some(Opcode::GetIter),
+        69 => Some(Opcode::GetYieldFromIter),
+        70 => Some(Opcode::PrintExpr),
+        71 => Some(Opcode::LoadBuildClass),
+        74 => Some(Opcode::LoadAssertionError),
+        75 => Some(Opcode::ReturnGenerator),
+        82 => Some(Opcode::ListToTuple),
+        83 => Some(Opcode::ReturnValue),
+        84 => Some(Opcode::ImportStar),
+        85 => Some(Opcode::SetupAnnotations),
+        86 => Some(Opcode::YieldValue),
+        87 => Some(Opcode::AsyncGenWrap),
+        88 => Some(Opcode::PrepReraiseStar),
+        89 => Some(Opcode::PopExcept),
+        90 => Some(Opcode::StoreName),
+        91 => Some(Opcode::DeleteName),
+        92 => Some(Opcode::UnpackSequence),
+        93 => Some(Opcode::ForIter),
+        94 => Some(Opcode::UnpackEx),
+        95 => Some(Opcode::StoreAttr),
+        96 => Some(Opcode::DeleteAttr),
+        97 => Some(Opcode::StoreGlobal),
+        98 => Some(Opcode::DeleteGlobal),
+        99 => Some(Opcode::Swap),
+        100 => Some(Opcode::LoadConst),
+        101 => Some(Opcode::LoadName),
+        102 => Some(Opcode::BuildTuple),
+        103 => Some(Opcode::BuildList),
+        104 => Some(Opcode::BuildSet),
+        105 => Some(Opcode::BuildMap),
+        106 => Some(Opcode::LoadAttr),
+        107 => Some(Opcode::CompareOp),
+        108 => Some(Opcode::ImportName),
+        109 => Some(Opcode::ImportFrom),
+        110 => Some(Opcode::JumpForward),
+        111 => Some(Opcode::JumpIfFalseOrPop),
+        112 => Some(Opcode::JumpIfTrueOrPop),
+        114 => Some(Opcode::PopJumpForwardIfFalse),
+        115 => Some(Opcode::PopJumpForwardIfTrue),
+        116 => Some(Opcode::LoadGlobal),
+        117 => Some(Opcode::IsOp),
+        118 => Some(Opcode::ContainsOp),
+        119 => Some(Opcode::Reraise),
+        120 => Some(Opcode::Copy),
+        122 => Some(Opcode::BinaryOp),
+        123 => Some(Opcode::Send),
+        124 => Some(Opcode::LoadFast),
+        125 => Some(Opcode::StoreFast),
+        126 => Some(Opcode::DeleteFast),
+        128 => Some(Opcode::PopJumpForwardIfNotNone),
+        129 => Some(Opcode::PopJumpForwardIfNone),
+        130 => Some(Opcode::RaiseVarargs),
+        131 => Some(Opcode::GetAwaitable),
+        132 => Some(Opcode::MakeFunction),
+        133 => Some(Opcode::BuildSlice),
+        134 => Some(Opcode::JumpBackwardNoInterrupt),
+        135 => Some(Opcode::MakeCell),
+        136 => Some(Opcode::LoadClosure),
+        137 => Some(Opcode::LoadDeref),
+        138 => Some(Opcode::StoreDeref),
+        139 => Some(Opcode::DeleteDeref),
+        140 => Some(Opcode::JumpBackward),
+        142 => Some(Opcode::CallFunctionEx),
+        145 => Some(Opcode::ListAppend),
+        146 => Some(Opcode::SetAdd),
+        147 => Some(Opcode::MapAdd),
+        148 => Some(Opcode::LoadClassderef),
+        149 => Some(Opcode::CopyFreeVars),
+        151 => Some(Opcode::Resume),
+        152 => Some(Opcode::MatchClass),
+        155 => Some(Opcode::FormatValue),
+        156 => Some(Opcode::BuildConstKeyMap),
+        157 => Some(Opcode::BuildString),
+        160 => Some(Opcode::LoadMethod),
+        162 => Some(Opcode::ListExtend),
+        163 => Some(Opcode::SetUpdate),
+        164 => Some(Opcode::DictMerge),
+        165 => Some(Opcode::DictUpdate),
+        166 => Some(Opcode::Precall),
+        171 => Some(Opcode::Call),
+        172 => Some(Opcode::KwNames),
+        173 => Some(Opcode::PopJumpBackwardIfNotNone),
+        174 => Some(Opcode::PopJumpBackwardIfNone),
+        175 => Some(Opcode::PopJumpBackwardIfFalse),
+        176 => Some(Opcode::PopJumpBackwardIfTrue),
+        _ => None,
+    }
+}
+
+fn decode_3_12(opcode: u8) -> Option<Opcode> {
+    match opcode {
+        0 => Some(Opcode::Cache),
+        1 => Some(Opcode::PopTop),
+        2 => Some(Opcode::PushNull),
+        3 => Some(Opcode::InterpreterExit),
+        4 => Some(Opcode::EndFor),
+        5 => Some(Opcode::EndSend),
+        9 => Some(Opcode::Nop),
+        11 => Some(Opcode::UnaryNegative),
+        12 => Some(Opcode::UnaryNot),
+        15 => Some(Opcode::UnaryInvert),
+        17 => Some(Opcode::Reserved),
+        25 => Some(Opcode::BinarySubscr),
+        26 => Some(Opcode::BinarySlice),
+        27 => Some(Opcode::StoreSlice),
+        30 => Some(Opcode::GetLen),
+        31 => Some(Opcode::MatchMapping),
+        32 => Some(Opcode::MatchSequence),
+        33 => Some(Opcode::MatchKeys),
+        35 => Some(Opcode::PushExcInfo),
+        36 => Some(Opcode::CheckExcMatch),
+        37 => Some(Opcode::CheckEgMatch),
+        49 => Some(Opcode::WithExceptStart),
+        50 => Some(Opcode::GetAiter),
+        51 => Some(Opcode::GetAnext),
+        52 => Some(Opcode::BeforeAsyncWith),
+        53 => Some(Opcode::BeforeWith),
+        54 => Some(Opcode::EndAsyncFor),
+        55 => Some(Opcode::CleanupThrow),
+        60 => Some(Opcode::StoreSubscr),
+        61 => Some(Opcode::DeleteSubscr),
+        68 => Some(Opcode::GetIter),
+        69 => Some(Opcode::GetYieldFromIter),
+        71 => Some(Opcode::LoadBuildClass),
+        74 => Some(Opcode::LoadAssertionError),
+        75 => Some(Opcode::ReturnGenerator),
+        83 => Some(Opcode::ReturnValue),
+        85 => Some(Opcode::SetupAnnotations),
+        87 => Some(Opcode::LoadLocals),
+        89 => Some(Opcode::PopExcept),
+        90 => Some(Opcode::StoreName),
+        91 => Some(Opcode::DeleteName),
+        92 => Some(Opcode::UnpackSequence),
+        93 => Some(Opcode::ForIter),
+        94 => Some(Opcode::UnpackEx),
+        95 => Some(Opcode::StoreAttr),
+        96 => Some(Opcode::DeleteAttr),
+        97 => Some(Opcode::StoreGlobal),
+        98 => Some(Opcode::DeleteGlobal),
+        99 => Some(Opcode::Swap),
+        100 => Some(Opcode::LoadConst),
+        101 => Some(Opcode::LoadName),
+        102 => Some(Opcode::BuildTuple),
+        103 => Some(Opcode::BuildList),
+        104 => Some(Opcode::BuildSet),
+        105 => Some(Opcode::BuildMap),
+        106 => Some(Opcode::LoadAttr),
+        107 => Some(Opcode::CompareOp),
+        108 => Some(Opcode::ImportName),
+        109 => Some(Opcode::ImportFrom),
+        110 => Some(Opcode::JumpForward),
+        114 => Some(Opcode::PopJumpIfFalse),
+        115 => Some(Opcode::PopJumpIfTrue),
+        116 => Some(Opcode::LoadGlobal),
+        117 => Some(Opcode::IsOp),
+        118 => Some(Opcode::ContainsOp),
+        119 => Some(Opcode::Reraise),
+        120 => Some(Opcode::Copy),
+        121 => Some(Opcode::ReturnConst),
+        122 => Some(Opcode::BinaryOp),
+        123 => Some(Opcode::Send),
+        124 => Some(Opcode::LoadFast),
+        125 => Some(Opcode::StoreFast),
+        126 => Some(Opcode::DeleteFast),
+        127 => Some(Opcode::LoadFastCheck),
+        128 => Some(Opcode::PopJumpIfNotNone),
+        129 => Some(Opcode::PopJumpIfNone),
+        130 => Some(Opcode::RaiseVarargs),
+        131 => Some(Opcode::GetAwaitable),
+        132 => Some(Opcode::MakeFunction),
+        133 => Some(Opcode::BuildSlice),
+        134 => Some(Opcode::JumpBackwardNoInterrupt),
+        135 => Some(Opcode::MakeCell),
+        136 => Some(Opcode::LoadClosure),
+        137 => Some(Opcode::LoadDeref),
+        138 => Some(Opcode::StoreDeref),
+        139 => Some(Opcode::DeleteDeref),
+        140 => Some(Opcode::JumpBackward),
+        141 => Some(Opcode::LoadSuperAttr),
+        142 => Some(Opcode::CallFunctionEx),
+        143 => Some(Opcode::LoadFastAndClear),
+        145 => Some(Opcode::ListAppend),
+        146 => Some(Opcode::SetAdd),
+        147 => Some(Opcode::MapAdd),
+        149 => Some(Opcode::CopyFreeVars),
+        150 => Some(Opcode::YieldValue),
+        151 => Some(Opcode::Resume),
+        152 => Some(Opcode::MatchClass),
+        155 => Some(Opcode::FormatValue),
+        156 => Some(Opcode::BuildConstKeyMap),
+        157 => Some(Opcode::BuildString),
+        162 => Some(Opcode::ListExtend),
+        163 => Some(Opcode::SetUpdate),
+        164 => Some(Opcode::DictMerge),
+        165 => Some(Opcode::DictUpdate),
+        171 => Some(Opcode::Call),
+        172 => Some(Opcode::KwNames),
+        173 => Some(Opcode::CallIntrinsic1),
+        174 => Some(Opcode::CallIntrinsic2),
+        175 => Some(Opcode::LoadFromDictOrGlobals),
+        176 => Some(Opcode::LoadFromDictOrDeref),
+        _ => None,
+    }
+}
+
+fn decode_3_13(opcode: u8) -> Option<Opcode> {
+    match opcode {
+        0 => Some(Opcode::Cache),
+        1 => Some(Opcode::BeforeAsyncWith),
+        2 => Some(Opcode::BeforeWith),
+        4 => Some(Opcode::BinarySlice),
+        5 => Some(Opcode::BinarySubscr),
+        6 => Some(Opcode::CheckEgMatch),
+        7 => Some(Opcode::CheckExcMatch),
+        8 => Some(Opcode::CleanupThrow),
+        9 => Some(Opcode::DeleteSubscr),
+        10 => Some(Opcode::EndAsyncFor),
+        11 => Some(Opcode::EndFor),
+        12 => Some(Opcode::EndSend),
+        13 => Some(Opcode::ExitInitCheck),
+        14 => Some(Opcode::FormatSimple),
+        15 => Some(Opcode::FormatWithSpec),
+        16 => Some(Opcode::GetAiter),
+        17 => Some(Opcode::Reserved),
+        18 => Some(Opcode::GetAnext),
+        19 => Some(Opcode::GetIter),
+        20 => Some(Opcode::GetLen),
+        21 => Some(Opcode::GetYieldFromIter),
+        22 => Some(Opcode::InterpreterExit),
+        23 => Some(Opcode::LoadAssertionError),
+        24 => Some(Opcode::LoadBuildClass),
+        25 => Some(Opcode::LoadLocals),
+        26 => Some(Opcode::MakeFunction),
+        27 => Some(Opcode::MatchKeys),
+        28 => Some(Opcode::MatchMapping),
+        29 => Some(Opcode::MatchSequence),
+        30 => Some(Opcode::Nop),
+        31 => Some(Opcode::PopExcept),
+        32 => Some(Opcode::PopTop),
+        33 => Some(Opcode::PushExcInfo),
+        34 => Some(Opcode::PushNull),
+        35 => Some(Opcode::ReturnGenerator),
+        36 => Some(Opcode::ReturnValue),
+        37 => Some(Opcode::SetupAnnotations),
+        38 => Some(Opcode::StoreSlice),
+        39 => Some(Opcode::StoreSubscr),
+        40 => Some(Opcode::ToBool),
+        41 => Some(Opcode::UnaryInvert),
+        42 => Some(Opcode::UnaryNegative),
+        43 => Some(Opcode::UnaryNot),
+        44 => Some(Opcode::WithExceptStart),
+        45 => Some(Opcode::BinaryOp),
+        46 => Some(Opcode::BuildConstKeyMap),
+        47 => Some(Opcode::BuildList),
+        48 => Some(Opcode::BuildMap),
+        49 => Some(Opcode::BuildSet),
+        50 => Some(Opcode::BuildSlice),
+        51 => Some(Opcode::BuildString),
+        52 => Some(Opcode::BuildTuple),
+        53 => Some(Opcode::Call),
+        54 => Some(Opcode::CallFunctionEx),
+        55 => Some(Opcode::CallIntrinsic1),
+        56 => Some(Opcode::CallIntrinsic2),
+        57 => Some(Opcode::CallKw),
+        58 => Some(Opcode::CompareOp),
+        59 => Some(Opcode::ContainsOp),
+        60 => Some(Opcode::ConvertValue),
+        61 => Some(Opcode::Copy),
+        62 => Some(Opcode::CopyFreeVars),
+        63 => Some(Opcode::DeleteAttr),
+        64 => Some(Opcode::DeleteDeref),
+        65 => Some(Opcode::DeleteFast),
+        66 => Some(Opcode::DeleteGlobal),
+        67 => Some(Opcode::DeleteName),
+        68 => Some(Opcode::DictMerge),
+        69 => Some(Opcode::DictUpdate),
+        70 => Some(Opcode::EnterExecutor),
+        72 => Some(Opcode::ForIter),
+        73 => Some(Opcode::GetAwaitable),
+        74 => Some(Opcode::ImportFrom),
+        75 => Some(Opcode::ImportName),
+        76 => Some(Opcode::IsOp),
+        77 => Some(Opcode::JumpBackward),
+        78 => Some(Opcode::JumpBackwardNoInterrupt),
+        79 => Some(Opcode::JumpForward),
+        80 => Some(Opcode::ListAppend),
+        81 => Some(Opcode::ListExtend),
+        82 => Some(Opcode::LoadAttr),
+        83 => Some(Opcode::LoadConst),
+        84 => Some(Opcode::LoadDeref),
+        85 => Some(Opcode::LoadFast),
+        86 => Some(Opcode::LoadFastAndClear),
+        87 => Some(Opcode::LoadFastCheck),
+        88 => Some(Opcode::LoadFastLoadFast),
+        89 => Some(Opcode::LoadFromDictOrDeref),
+        90 => Some(Opcode::LoadFromDictOrGlobals),
+        91 => Some(Opcode::LoadGlobal),
+        92 => Some(Opcode::LoadName),
+        93 => Some(Opcode::LoadSuperAttr),
+        94 => Some(Opcode::MakeCell),
+        95 => Some(Opcode::MapAdd),
+        96 => Some(Opcode::MatchClass),
+        97 => Some(Opcode::PopJumpIfFalse),
+        98 => Some(Opcode::PopJumpIfNone),
+        99 => Some(Opcode::PopJumpIfNotNone),
+        100 => Some(Opcode::PopJumpIfTrue),
+        101 => Some(Opcode::RaiseVarargs),
+        102 => Some(Opcode::Reraise),
+        103 => Some(Opcode::ReturnConst),
+        104 => Some(Opcode::Send),
+        105 => Some(Opcode::SetAdd),
+        106 => Some(Opcode::SetFunctionAttribute),
+        107 => Some(Opcode::SetUpdate),
+        108 => Some(Opcode::StoreAttr),
+        109 => Some(Opcode::StoreDeref),
+        110 => Some(Opcode::StoreFast),
+        111 => Some(Opcode::StoreFastLoadFast),
+        112 => Some(Opcode::StoreFastStoreFast),
+        113 => Some(Opcode::StoreGlobal),
+        114 => Some(Opcode::StoreName),
+        115 => Some(Opcode::Swap),
+        116 => Some(Opcode::UnpackEx),
+        117 => Some(Opcode::UnpackSequence),
+        118 => Some(Opcode::YieldValue),
+        149 => Some(Opcode::Resume),
+        _ => None,
+    }
+}
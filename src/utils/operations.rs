@@ -1,429 +1,532 @@
-use super::pycachereader::PyCacheReader;
-use std::str;
-
-#[derive(Debug, Clone)]
-pub enum Operation {
-    // === OPERATIONS WITH NO ARGUMENTS (argument byte is ignored) ===
-    StopCode,              // 0 - end of bytecode
-    PopTop,
-    RotTwo,
-    RotThree,
-    DupTop,
-    DupTopTwo,
-    RotFour,
-    Nop,
-    UnaryPositive,
-    UnaryNegative,
-    UnaryNot,
-    UnaryInvert,
-    BinaryMatrixMultiply,
-    InplaceMatrixMultiply,
-    BinaryPower,
-    BinaryMultiply,
-    BinaryModulo,
-    BinaryAdd,           // 23 - addition operation
-    BinarySubtract,      // 24 - subtraction operation
-    BinarySubscr,
-    BinaryFloorDivide,
-    BinaryTrueDivide,
-    InplaceFloorDivide,
-    InplaceTrueDivide,
-    GetLen,
-    MatchMapping,
-    MatchSequence,
-    MatchKeys,
-    CopyDictWithoutKeys,
-    WithExceptStart,
-    GetAiter,
-    GetAnext,
-    BeforeAsyncWith,
-    EndAsyncFor,
-    InplaceAdd,
-    InplaceSubtract,
-    InplaceMultiply,
-    InplaceModulo,
-    StoreSubscr,
-    DeleteSubscr,
-    BinaryLshift,
-    BinaryRshift,
-    BinaryAnd,
-    BinaryXor,
-    BinaryOr,
-    InplacePower,
-    GetIter,
-    GetYieldFromIter,
-    PrintExpr,
-    LoadBuildClass,
-    YieldFrom,
-    GetAwaitable,
-    LoadAssertionError,
-    InplaceLshift,
-    InplaceRshift,
-    InplaceAnd,
-    InplaceXor,
-    InplaceOr,
-    ListToTuple,
-    ReturnValue,         // 83 - return from function
-    ImportStar,
-    SetupAnnotations,
-    YieldValue,
-    PopBlock,
-    PopExcept,
-    StoreName,
-    DeleteName,
-    UnpackSequence,
-    ForIter,
-    UnpackEx,
-    StoreAttr,
-    DeleteAttr,
-    StoreGlobal,
-    DeleteGlobal,
-    RotN,
-    LoadConst,
-    LoadName,
-    BuildTuple,
-    BuildList,
-    BuildSet,
-    BuildMap,
-    LoadAttr,
-    CompareOp,
-    ImportName,
-    ImportFrom,
-    JumpForward,
-    JumpIfFalseOrPop,
-    JumpIfTrueOrPop,
-    JumpAbsolute,
-    PopJumpIfFalse,
-    PopJumpIfTrue,
-    LoadGlobal,
-    IsOp,
-    ContainsOp,
-    Reraise,
-    JumpIfNotExcMatch,
-    LoadFast,
-    StoreFast,
-    DeleteFast,
-    GenStart,
-    RaiseVarargs,
-    CallFunction,        // 131 - call function with positional arguments
-    MakeFunction,
-    BuildSlice,
-    LoadClosure,
-    LoadDeref,
-    StoreDeref,
-    DeleteDeref,
-    CallFunctionKw,
-    CallFunctionEx,
-    SetupWith,
-    ListAppend,
-    SetAdd,
-    MapAdd,
-    LoadClassderef,
-    MatchClass,
-    SetupAsyncWith,
-    FormatValue,
-    BuildConstKeyMap,
-    BuildString,
-    LoadMethod,
-    CallMethod,
-    ListExtend,
-    SetUpdate,
-    DictMerge,
-    DictUpdate,
-
-    // === OPERATIONS WITH 1-BYTE ARGUMENT ===
-    // These take a single u8 argument (index, offset, etc.)
-    PopTopArg(u8),
-    RotTwoArg(u8),
-    RotThreeArg(u8),
-    DupTopArg(u8),
-    DupTopTwoArg(u8),
-    RotFourArg(u8),
-    NopArg(u8),
-    UnaryPositiveArg(u8),
-    UnaryNegativeArg(u8),
-    UnaryNotArg(u8),
-    UnaryInvertArg(u8),
-    BinaryMatrixMultiplyArg(u8),
-    InplaceMatrixMultiplyArg(u8),
-    BinaryPowerArg(u8),
-    BinaryMultiplyArg(u8),
-    BinaryModuloArg(u8),
-    BinaryAddArg(u8),
-    BinarySubtractArg(u8),
-    BinarySubscrArg(u8),
-    BinaryFloorDivideArg(u8),
-    BinaryTrueDivideArg(u8),
-    InplaceFloorDivideArg(u8),
-    InplaceTrueDivideArg(u8),
-    GetLenArg(u8),
-    MatchMappingArg(u8),
-    MatchSequenceArg(u8),
-    MatchKeysArg(u8),
-    CopyDictWithoutKeysArg(u8),
-    WithExceptStartArg(u8),
-    GetAiterArg(u8),
-    GetAnextArg(u8),
-    BeforeAsyncWithArg(u8),
-    EndAsyncForArg(u8),
-    InplaceAddArg(u8),
-    InplaceSubtractArg(u8),
-    InplaceMultiplyArg(u8),
-    InplaceModuloArg(u8),
-    StoreSubscrArg(u8),
-    DeleteSubscrArg(u8),
-    BinaryLshiftArg(u8),
-    BinaryRshiftArg(u8),
-    BinaryAndArg(u8),
-    BinaryXorArg(u8),
-    BinaryOrArg(u8),
-    InplacePowerArg(u8),
-    GetIterArg(u8),
-    GetYieldFromIterArg(u8),
-    PrintExprArg(u8),
-    LoadBuildClassArg(u8),
-    YieldFromArg(u8),
-    GetAwaitableArg(u8),
-    LoadAssertionErrorArg(u8),
-    InplaceLshiftArg(u8),
-    InplaceRshiftArg(u8),
-    InplaceAndArg(u8),
-    InplaceXorArg(u8),
-    InplaceOrArg(u8),
-    ListToTupleArg(u8),
-    ReturnValueArg(u8),
-    ImportStarArg(u8),
-    SetupAnnotationsArg(u8),
-    YieldValueArg(u8),
-    PopBlockArg(u8),
-    PopExceptArg(u8),
-    StoreNameArg(u8),
-    DeleteNameArg(u8),
-    UnpackSequenceArg(u8),
-    ForIterArg(u8),
-    UnpackExArg(u8),
-    StoreAttrArg(u8),
-    DeleteAttrArg(u8),
-    StoreGlobalArg(u8),
-    DeleteGlobalArg(u8),
-    RotNArg(u8),
-    LoadConstArg(u8),
-    LoadNameArg(u8),
-    BuildTupleArg(u8),
-    BuildListArg(u8),
-    BuildSetArg(u8),
-    BuildMapArg(u8),
-    LoadAttrArg(u8),
-    CompareOpArg(u8),
-    ImportNameArg(u8),
-    ImportFromArg(u8),
-    JumpForwardArg(u8),
-    JumpIfFalseOrPopArg(u8),
-    JumpIfTrueOrPopArg(u8),
-    JumpAbsoluteArg(u8),
-    PopJumpIfFalseArg(u8),
-    PopJumpIfTrueArg(u8),
-    LoadGlobalArg(u8),
-    IsOpArg(u8),
-    ContainsOpArg(u8),
-    ReraiseArg(u8),
-    JumpIfNotExcMatchArg(u8),
-    LoadFastArg(u8),
-    StoreFastArg(u8),
-    DeleteFastArg(u8),
-    GenStartArg(u8),
-    RaiseVarargsArg(u8),
-    CallFunctionArg(u8),
-    MakeFunctionArg(u8),
-    BuildSliceArg(u8),
-    LoadClosureArg(u8),
-    LoadDerefArg(u8),
-    StoreDerefArg(u8),
-    DeleteDerefArg(u8),
-    CallFunctionKwArg(u8),
-    CallFunctionExArg(u8),
-    SetupWithArg(u8),
-    ListAppendArg(u8),
-    SetAddArg(u8),
-    MapAddArg(u8),
-    LoadClassderefArg(u8),
-    MatchClassArg(u8),
-    SetupAsyncWithArg(u8),
-    FormatValueArg(u8),
-    BuildConstKeyMapArg(u8),
-    BuildStringArg(u8),
-    LoadMethodArg(u8),
-    CallMethodArg(u8),
-    ListExtendArg(u8),
-    SetUpdateArg(u8),
-    DictMergeArg(u8),
-    DictUpdateArg(u8),
-
-    // === OPERATIONS WITH 2-BYTE ARGUMENT ===
-    ExtendedArg(u16), // 2 bytes for extended argument
-
-    // === OPERATIONS WITH 4-BYTE ARGUMENT ===
-    SetupFinally(u32), // 4 bytes for offset in bytes
-}
-
-impl Operation {
-    pub fn from_byte(byte: &u8, reader: &mut PyCacheReader) -> Option<Self> {
-        match byte {
-            // === OPERATIONS WITH NO ARGUMENTS ===
-            0 => Some(Operation::StopCode),
-            1 => Some(Operation::PopTop),
-            23 => Some(Operation::BinaryAdd),
-            24 => Some(Operation::BinarySubtract),
-            83 => Some(Operation::ReturnValue),
-            
-            // === OPERATIONS WITH 1-BYTE ARGUMENT ===
-            // These take a single u8 argument (index, offset, etc.)
-            2 => Some(Operation::RotTwoArg(reader.read_byte())),
-            3 => Some(Operation::RotThreeArg(reader.read_byte())),
-            4 => Some(Operation::DupTopArg(reader.read_byte())),
-            5 => Some(Operation::DupTopTwoArg(reader.read_byte())),
-            6 => Some(Operation::RotFourArg(reader.read_byte())),
-            9 => Some(Operation::NopArg(reader.read_byte())),
-            10 => Some(Operation::UnaryPositiveArg(reader.read_byte())),
-            11 => Some(Operation::UnaryNegativeArg(reader.read_byte())),
-            12 => Some(Operation::UnaryNotArg(reader.read_byte())),
-            15 => Some(Operation::UnaryInvertArg(reader.read_byte())),
-            16 => Some(Operation::BinaryMatrixMultiplyArg(reader.read_byte())),
-            17 => Some(Operation::InplaceMatrixMultiplyArg(reader.read_byte())),
-            19 => Some(Operation::BinaryPowerArg(reader.read_byte())),
-            20 => Some(Operation::BinaryMultiplyArg(reader.read_byte())),
-            22 => Some(Operation::BinaryModuloArg(reader.read_byte())),
-            25 => Some(Operation::BinarySubscrArg(reader.read_byte())),
-            26 => Some(Operation::BinaryFloorDivideArg(reader.read_byte())),
-            27 => Some(Operation::BinaryTrueDivideArg(reader.read_byte())),
-            28 => Some(Operation::InplaceFloorDivideArg(reader.read_byte())),
-            29 => Some(Operation::InplaceTrueDivideArg(reader.read_byte())),
-            30 => Some(Operation::GetLenArg(reader.read_byte())),
-            31 => Some(Operation::MatchMappingArg(reader.read_byte())),
-            32 => Some(Operation::MatchSequenceArg(reader.read_byte())),
-            33 => Some(Operation::MatchKeysArg(reader.read_byte())),
-            34 => Some(Operation::CopyDictWithoutKeysArg(reader.read_byte())),
-            49 => Some(Operation::WithExceptStartArg(reader.read_byte())),
-            50 => Some(Operation::GetAiterArg(reader.read_byte())),
-            51 => Some(Operation::GetAnextArg(reader.read_byte())),
-            52 => Some(Operation::BeforeAsyncWithArg(reader.read_byte())),
-            54 => Some(Operation::EndAsyncForArg(reader.read_byte())),
-            55 => Some(Operation::InplaceAddArg(reader.read_byte())),
-            56 => Some(Operation::InplaceSubtractArg(reader.read_byte())),
-            57 => Some(Operation::InplaceMultiplyArg(reader.read_byte())),
-            59 => Some(Operation::InplaceModuloArg(reader.read_byte())),
-            60 => Some(Operation::StoreSubscrArg(reader.read_byte())),
-            61 => Some(Operation::DeleteSubscrArg(reader.read_byte())),
-            62 => Some(Operation::BinaryLshiftArg(reader.read_byte())),
-            63 => Some(Operation::BinaryRshiftArg(reader.read_byte())),
-            64 => Some(Operation::BinaryAndArg(reader.read_byte())),
-            65 => Some(Operation::BinaryXorArg(reader.read_byte())),
-            66 => Some(Operation::BinaryOrArg(reader.read_byte())),
-            67 => Some(Operation::InplacePowerArg(reader.read_byte())),
-            68 => Some(Operation::GetIterArg(reader.read_byte())),
-            69 => Some(Operation::GetYieldFromIterArg(reader.read_byte())),
-            70 => Some(Operation::PrintExprArg(reader.read_byte())),
-            71 => Some(Operation::LoadBuildClassArg(reader.read_byte())),
-            72 => Some(Operation::YieldFromArg(reader.read_byte())),
-            73 => Some(Operation::GetAwaitableArg(reader.read_byte())),
-            74 => Some(Operation::LoadAssertionErrorArg(reader.read_byte())),
-            75 => Some(Operation::InplaceLshiftArg(reader.read_byte())),
-            76 => Some(Operation::InplaceRshiftArg(reader.read_byte())),
-            77 => Some(Operation::InplaceAndArg(reader.read_byte())),
-            78 => Some(Operation::InplaceXorArg(reader.read_byte())),
-            79 => Some(Operation::InplaceOrArg(reader.read_byte())),
-            82 => Some(Operation::ListToTupleArg(reader.read_byte())),
-            84 => Some(Operation::ImportStarArg(reader.read_byte())),
-            85 => Some(Operation::SetupAnnotationsArg(reader.read_byte())),
-            86 => Some(Operation::YieldValueArg(reader.read_byte())),
-            87 => Some(Operation::PopBlockArg(reader.read_byte())),
-            89 => Some(Operation::PopExceptArg(reader.read_byte())),
-            90 => Some(Operation::StoreNameArg(reader.read_byte())),
-            91 => Some(Operation::DeleteNameArg(reader.read_byte())),
-            92 => Some(Operation::UnpackSequenceArg(reader.read_byte())),
-            93 => Some(Operation::ForIterArg(reader.read_byte())),
-            94 => Some(Operation::UnpackExArg(reader.read_byte())),
-            95 => Some(Operation::StoreAttrArg(reader.read_byte())),
-            96 => Some(Operation::DeleteAttrArg(reader.read_byte())),
-            97 => Some(Operation::StoreGlobalArg(reader.read_byte())),
-            98 => Some(Operation::DeleteGlobalArg(reader.read_byte())),
-            99 => Some(Operation::RotNArg(reader.read_byte())),
-            100 => Some(Operation::LoadConstArg(reader.read_byte())),
-            101 => Some(Operation::LoadNameArg(reader.read_byte())),
-            102 => Some(Operation::BuildTupleArg(reader.read_byte())),
-            103 => Some(Operation::BuildListArg(reader.read_byte())),
-            104 => Some(Operation::BuildSetArg(reader.read_byte())),
-            105 => Some(Operation::BuildMapArg(reader.read_byte())),
-            106 => Some(Operation::LoadAttrArg(reader.read_byte())),
-            107 => Some(Operation::CompareOpArg(reader.read_byte())),
-            108 => Some(Operation::ImportNameArg(reader.read_byte())),
-            109 => Some(Operation::ImportFromArg(reader.read_byte())),
-            110 => Some(Operation::JumpForwardArg(reader.read_byte())),
-            111 => Some(Operation::JumpIfFalseOrPopArg(reader.read_byte())),
-            112 => Some(Operation::JumpIfTrueOrPopArg(reader.read_byte())),
-            113 => Some(Operation::JumpAbsoluteArg(reader.read_byte())),
-            114 => Some(Operation::PopJumpIfFalseArg(reader.read_byte())),
-            115 => Some(Operation::PopJumpIfTrueArg(reader.read_byte())),
-            116 => Some(Operation::LoadGlobalArg(reader.read_byte())),
-            117 => Some(Operation::IsOpArg(reader.read_byte())),
-            118 => Some(Operation::ContainsOpArg(reader.read_byte())),
-            119 => Some(Operation::ReraiseArg(reader.read_byte())),
-            121 => Some(Operation::JumpIfNotExcMatchArg(reader.read_byte())),
-            124 => Some(Operation::LoadFastArg(reader.read_byte())),
-            125 => Some(Operation::StoreFastArg(reader.read_byte())),
-            126 => Some(Operation::DeleteFastArg(reader.read_byte())),
-            129 => Some(Operation::GenStartArg(reader.read_byte())),
-            130 => Some(Operation::RaiseVarargsArg(reader.read_byte())),
-            131 => Some(Operation::CallFunctionArg(reader.read_byte())),
-            132 => Some(Operation::MakeFunctionArg(reader.read_byte())),
-            133 => Some(Operation::BuildSliceArg(reader.read_byte())),
-            135 => Some(Operation::LoadClosureArg(reader.read_byte())),
-            136 => Some(Operation::LoadDerefArg(reader.read_byte())),
-            137 => Some(Operation::StoreDerefArg(reader.read_byte())),
-            138 => Some(Operation::DeleteDerefArg(reader.read_byte())),
-            141 => Some(Operation::CallFunctionKwArg(reader.read_byte())),
-            142 => Some(Operation::CallFunctionExArg(reader.read_byte())),
-            143 => Some(Operation::SetupWithArg(reader.read_byte())),
-            145 => Some(Operation::ListAppendArg(reader.read_byte())),
-            146 => Some(Operation::SetAddArg(reader.read_byte())),
-            147 => Some(Operation::MapAddArg(reader.read_byte())),
-            148 => Some(Operation::LoadClassderefArg(reader.read_byte())),
-            152 => Some(Operation::MatchClassArg(reader.read_byte())),
-            154 => Some(Operation::SetupAsyncWithArg(reader.read_byte())),
-            155 => Some(Operation::FormatValueArg(reader.read_byte())),
-            156 => Some(Operation::BuildConstKeyMapArg(reader.read_byte())),
-            157 => Some(Operation::BuildStringArg(reader.read_byte())),
-            160 => Some(Operation::LoadMethodArg(reader.read_byte())),
-            161 => Some(Operation::CallMethodArg(reader.read_byte())),
-            162 => Some(Operation::ListExtendArg(reader.read_byte())),
-            163 => Some(Operation::SetUpdateArg(reader.read_byte())),
-            164 => Some(Operation::DictMergeArg(reader.read_byte())),
-            165 => Some(Operation::DictUpdateArg(reader.read_byte())),
-
-            // === OPERATIONS WITH 2-BYTE ARGUMENT ===
-            144 => Some(Operation::ExtendedArg(u16::from_le_bytes([
-                reader.read_byte(),
-                reader.read_byte(),
-            ]))),
-
-            // === OPERATIONS WITH 4-BYTE ARGUMENT ===
-            122 => Some(Operation::SetupFinally(u32::from_le_bytes([
-                reader.read_byte(),
-                reader.read_byte(),
-                reader.read_byte(),
-                0,
-            ]))),
-
-            _ => {
-                let bytes = vec![*byte];
-                todo!("type {} (value {})", str::from_utf8(&bytes).unwrap(), byte)
-            }
-        }
-    }
-}
-
-impl Default for Operation {
-    fn default() -> Self {
-        Operation::ReturnValue
-    }
-}
+use super::{opcodes::Opcode, var::Var, version::PythonVersion};
+
+// Operation as stored in co_code, with the argument widened by any preceding EXTENDED_ARG prefixes.
+// The offset is the one of the first prefix, as this is where jumps to the operation land,
+// while the end is the offset after the operation and its inline caches.
+#[derive(Debug, Clone)]
+pub struct RawInstruction {
+    pub offset: u32,
+    pub end: u32,
+    pub opcode: Opcode,
+    pub arg: u32,
+}
+
+// Name of a variable, attribute or module the operation refers to
+pub type Name = String;
+
+// Index of the jump target in the instructions of the code object
+pub type InstructionIndex = usize;
+
+// Constant from co_consts along with its index there
+#[derive(Debug, Clone)]
+pub struct ConstRef {
+    pub index: u32,
+    pub value: Var,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Positive,
+    Negative,
+    Not,
+    Invert,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    And,
+    FloorDivide,
+    Lshift,
+    MatrixMultiply,
+    Multiply,
+    Modulo,
+    Or,
+    Power,
+    Rshift,
+    Subtract,
+    TrueDivide,
+    Xor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpKind {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    ExceptionMatch, // 3.8 only, later versions have separate operations for it
+}
+
+// Flags of MAKE_FUNCTION, and of SET_FUNCTION_ATTRIBUTE since 3.13, for the values set on the function
+pub const FUNCTION_DEFAULTS: u32 = 0x01;
+pub const FUNCTION_KWDEFAULTS: u32 = 0x02;
+pub const FUNCTION_ANNOTATIONS: u32 = 0x04;
+pub const FUNCTION_CLOSURE: u32 = 0x08;
+
+// Operation with its argument resolved into what it means, the same for all the supported versions
+// where the semantics are the same. Operations of different versions doing the same thing share
+// a variant, e.g. DUP_TOP is Copy(1) and BINARY_OP with NB_ADD is BinaryOp(BinOp::Add).
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Nop, // also RESUME
+
+    // Stack manipulation
+    PopTop,
+    Copy(u32), // pushes the n-th item, counting from 1 at the top
+    DupTopTwo,
+    Rotate(u32), // moves the top item n - 1 places down (ROT_TWO, ROT_THREE, ROT_FOUR, ROT_N)
+    Swap(u32),   // swaps the top item with the n-th one
+    PushNull,
+
+    // Constants and variables
+    LoadConst(ConstRef),
+    LoadName(Name),
+    StoreName(Name),
+    DeleteName(Name),
+    LoadGlobal { name: Name, push_null: bool }, // since 3.11 may push NULL along with the global
+    StoreGlobal(Name),
+    DeleteGlobal(Name),
+    LoadFast(Name),
+    LoadFastAndClear(Name),
+    StoreFast(Name),
+    DeleteFast(Name),
+    LoadDeref(Name),
+    StoreDeref(Name),
+    DeleteDeref(Name),
+    LoadClosure(Name),
+    MakeCell(Name),
+    LoadAttr { name: Name, method: bool }, // since 3.12 may load a method like LoadMethod
+    LoadMethod(Name),
+    StoreAttr(Name),
+    DeleteAttr(Name),
+    ImportName(Name),
+    ImportFrom(Name),
+    ImportStar, // also CALL_INTRINSIC_1 with INTRINSIC_IMPORT_STAR since 3.12
+
+    // Operators
+    UnaryOp(UnaryOp),
+    BinaryOp(BinOp),
+    InplaceOp(BinOp),
+    CompareOp(CmpKind),
+    IsOp { negated: bool },
+    ContainsOp { negated: bool },
+    ToBool,
+    BinarySubscr,
+    StoreSubscr,
+    DeleteSubscr,
+
+    // Containers
+    BuildTuple(u32),
+    BuildList(u32),
+    BuildSet(u32),
+    BuildMap(u32),
+    BuildConstKeyMap(u32),
+    BuildString(u32),
+    BuildSlice(u32),
+    ListAppend(u32),
+    ListExtend(u32),
+    ListToTuple,
+    SetAdd(u32),
+    SetUpdate(u32),
+    MapAdd(u32),
+    DictMerge(u32),
+    DictUpdate(u32),
+    UnpackSequence(u32),
+    UnpackEx(u32),
+
+    // Control flow
+    Jump(InstructionIndex),
+    PopJumpIfFalse(InstructionIndex),
+    PopJumpIfTrue(InstructionIndex),
+    PopJumpIfNone(InstructionIndex),
+    PopJumpIfNotNone(InstructionIndex),
+    JumpIfFalseOrPop(InstructionIndex),
+    JumpIfTrueOrPop(InstructionIndex),
+    JumpIfNotExcMatch(InstructionIndex),
+    GetIter,
+    ForIter(InstructionIndex), // target reached once the iterator is exhausted
+    EndFor,
+    Send(InstructionIndex),
+    SetupFinally(InstructionIndex),
+    SetupWith(InstructionIndex),
+    SetupAsyncWith(InstructionIndex),
+    CallFinally(InstructionIndex),
+    PopBlock,
+    ReturnValue,
+    ReturnConst(ConstRef),
+    RaiseVarargs(u32),
+    Reraise,
+    PushExcInfo,
+    PopExcept,
+    CheckExcMatch,
+
+    // Calls and functions, argument counts exclude keyword names
+    CallFunction(u32),         // up to 3.10
+    CallFunctionKw(u32),       // up to 3.10, keyword names in a tuple on top of the stack
+    CallMethod(u32),           // up to 3.10, after LoadMethod
+    Precall(u32),              // 3.11 only, precedes Call
+    Call(u32),                 // since 3.11, with NULL or self next to the callable
+    KwNames(ConstRef),         // 3.11 and 3.12, keyword names of the following Call
+    CallKw(u32),               // since 3.13, keyword names in a tuple on top of the stack
+    CallFunctionEx { has_kwargs: bool },
+    MakeFunction(u32),         // flags of the values on the stack, 0 since 3.13
+    SetFunctionAttribute(u32), // since 3.13, flag of the value set on the function
+
+    // Operation not interpreted by the compiler yet
+    Other { opcode: Opcode, arg: u32 },
+}
+
+impl UnaryOp {
+    // Operand of CALL_INTRINSIC_1 (3.12+), which replaced UNARY_POSITIVE
+    pub fn from_intrinsic(intrinsic: u32) -> Option<Self> {
+        match intrinsic {
+            5 => Some(UnaryOp::Positive),
+            _ => None,
+        }
+    }
+}
+
+impl BinOp {
+    const NB_OPERATORS: [BinOp; 13] = [
+        BinOp::Add,
+        BinOp::And,
+        BinOp::FloorDivide,
+        BinOp::Lshift,
+        BinOp::MatrixMultiply,
+        BinOp::Multiply,
+        BinOp::Modulo,
+        BinOp::Or,
+        BinOp::Power,
+        BinOp::Rshift,
+        BinOp::Subtract,
+        BinOp::TrueDivide,
+        BinOp::Xor,
+    ];
+
+    // Since 3.11 binary operations share the BINARY_OP opcode, with the operator (NB_* in CPython)
+    // as its argument: the 13 operators followed by their in-place versions.
+    // Returns the operator and whether it's the in-place one.
+    pub fn from_binary_op(op: u32) -> Option<(Self, bool)> {
+        let count = Self::NB_OPERATORS.len();
+        let op = op as usize;
+        if op >= 2 * count {
+            return None;
+        }
+        Some((Self::NB_OPERATORS[op % count], op >= count))
+    }
+
+    // Operators of the BINARY_* and INPLACE_* opcodes of 3.8 - 3.10
+    pub fn from_opcode(opcode: Opcode) -> Option<(Self, bool)> {
+        let operator = match opcode {
+            Opcode::BinaryAdd => (BinOp::Add, false),
+            Opcode::BinaryAnd => (BinOp::And, false),
+            Opcode::BinaryFloorDivide => (BinOp::FloorDivide, false),
+            Opcode::BinaryLshift => (BinOp::Lshift, false),
+            Opcode::BinaryMatrixMultiply => (BinOp::MatrixMultiply, false),
+            Opcode::BinaryMultiply => (BinOp::Multiply, false),
+            Opcode::BinaryModulo => (BinOp::Modulo, false),
+            Opcode::BinaryOr => (BinOp::Or, false),
+            Opcode::BinaryPower => (BinOp::Power, false),
+            Opcode::BinaryRshift => (BinOp::Rshift, false),
+            Opcode::BinarySubtract => (BinOp::Subtract, false),
+            Opcode::BinaryTrueDivide => (BinOp::TrueDivide, false),
+            Opcode::BinaryXor => (BinOp::Xor, false),
+            Opcode::InplaceAdd => (BinOp::Add, true),
+            Opcode::InplaceAnd => (BinOp::And, true),
+            Opcode::InplaceFloorDivide => (BinOp::FloorDivide, true),
+            Opcode::InplaceLshift => (BinOp::Lshift, true),
+            Opcode::InplaceMatrixMultiply => (BinOp::MatrixMultiply, true),
+            Opcode::InplaceMultiply => (BinOp::Multiply, true),
+            Opcode::InplaceModulo => (BinOp::Modulo, true),
+            Opcode::InplaceOr => (BinOp::Or, true),
+            Opcode::InplacePower => (BinOp::Power, true),
+            Opcode::InplaceRshift => (BinOp::Rshift, true),
+            Opcode::InplaceSubtract => (BinOp::Subtract, true),
+            Opcode::InplaceTrueDivide => (BinOp::TrueDivide, true),
+            Opcode::InplaceXor => (BinOp::Xor, true),
+            _ => return None,
+        };
+        Some(operator)
+    }
+}
+
+impl CmpKind {
+    // Index into cmp_op of CPython
+    pub fn from_cmp_op(cmp_op: u32) -> Option<Self> {
+        match cmp_op {
+            0 => Some(CmpKind::Lt),
+            1 => Some(CmpKind::Le),
+            2 => Some(CmpKind::Eq),
+            3 => Some(CmpKind::Ne),
+            4 => Some(CmpKind::Gt),
+            5 => Some(CmpKind::Ge),
+            10 => Some(CmpKind::ExceptionMatch),
+            _ => None,
+        }
+    }
+}
+
+impl Operation {
+    // Index of the instruction the operation may jump to
+    pub fn jump_target(&self) -> Option<InstructionIndex> {
+        match self {
+            Operation::Jump(target)
+            | Operation::PopJumpIfFalse(target)
+            | Operation::PopJumpIfTrue(target)
+            | Operation::PopJumpIfNone(target)
+            | Operation::PopJumpIfNotNone(target)
+            | Operation::JumpIfFalseOrPop(target)
+            | Operation::JumpIfTrueOrPop(target)
+            | Operation::JumpIfNotExcMatch(target)
+            | Operation::ForIter(target)
+            | Operation::Send(target)
+            | Operation::SetupFinally(target)
+            | Operation::SetupWith(target)
+            | Operation::SetupAsyncWith(target)
+            | Operation::CallFinally(target) => Some(*target),
+            _ => None,
+        }
+    }
+
+    // Change of the stack depth caused by the operation, when it jumps or when it continues with the next one,
+    // in the same model as the compiler of CPython (dis.stack_effect). Handlers of exceptions aren't covered,
+    // as their depth comes from the exception table, except for the SETUP_* operations setting them up up to 3.10.
+    // None for operations never found in .pyc files.
+    pub fn stack_effect(&self, version: &PythonVersion, jump: bool) -> Option<i32> {
+        let at_least = |minor: u8| version.at_least(3, minor);
+        let effect = match self {
+            Operation::Nop => 0,
+
+            Operation::PopTop => -1,
+            Operation::Copy(_) => 1,
+            Operation::DupTopTwo => 2,
+            Operation::Rotate(_) | Operation::Swap(_) => 0,
+            Operation::PushNull => 1,
+
+            Operation::LoadConst(_) | Operation::LoadName(_) | Operation::LoadFast(_) => 1,
+            Operation::LoadFastAndClear(_) | Operation::LoadDeref(_) | Operation::LoadClosure(_) => 1,
+            Operation::StoreName(_) | Operation::StoreGlobal(_) | Operation::StoreFast(_) | Operation::StoreDeref(_) => -1,
+            Operation::DeleteName(_) | Operation::DeleteGlobal(_) | Operation::DeleteFast(_) | Operation::DeleteDeref(_) => 0,
+            Operation::LoadGlobal { push_null, .. } => 1 + *push_null as i32,
+            Operation::MakeCell(_) => 0,
+            Operation::LoadAttr { method, .. } => *method as i32,
+            Operation::LoadMethod(_) => 1,
+            Operation::StoreAttr(_) => -2,
+            Operation::DeleteAttr(_) => -1,
+            Operation::ImportName(_) => -1,
+            Operation::ImportFrom(_) => 1,
+            // Since 3.12 it's an intrinsic function pushing None in place of the module
+            Operation::ImportStar if version.at_least(3, 12) => 0,
+            Operation::ImportStar => -1,
+
+            Operation::UnaryOp(_) | Operation::ToBool => 0,
+            Operation::BinaryOp(_) | Operation::InplaceOp(_) | Operation::CompareOp(_) => -1,
+            Operation::IsOp { .. } | Operation::ContainsOp { .. } => -1,
+            Operation::BinarySubscr => -1,
+            Operation::StoreSubscr => -3,
+            Operation::DeleteSubscr => -2,
+
+            Operation::BuildTuple(count)
+            | Operation::BuildList(count)
+            | Operation::BuildSet(count)
+            | Operation::BuildString(count)
+            | Operation::BuildSlice(count) => 1 - *count as i32,
+            Operation::BuildMap(count) => 1 - 2 * *count as i32,
+            Operation::BuildConstKeyMap(count) => -(*count as i32),
+            Operation::ListAppend(_) | Operation::ListExtend(_) | Operation::SetAdd(_) | Operation::SetUpdate(_) => -1,
+            Operation::DictMerge(_) | Operation::DictUpdate(_) => -1,
+            Operation::MapAdd(_) => -2,
+            Operation::ListToTuple => 0,
+            Operation::UnpackSequence(count) => *count as i32 - 1,
+            // Counts of the items before (low byte) and after the starred one
+            Operation::UnpackEx(counts) => (counts & 0xFF) as i32 + (counts >> 8) as i32,
+
+            Operation::Jump(_) => 0,
+            Operation::PopJumpIfFalse(_)
+            | Operation::PopJumpIfTrue(_)
+            | Operation::PopJumpIfNone(_)
+            | Operation::PopJumpIfNotNone(_) => -1,
+            Operation::JumpIfFalseOrPop(_) | Operation::JumpIfTrueOrPop(_) => {
+                if jump {
+                    0
+                } else {
+                    -1
+                }
+            }
+            Operation::JumpIfNotExcMatch(_) => -2,
+            Operation::GetIter => 0,
+            // Since 3.12 the iterator is popped by END_FOR at the target
+            Operation::ForIter(_) => {
+                if jump && !at_least(12) {
+                    -1
+                } else {
+                    1
+                }
+            }
+            Operation::EndFor => {
+                if at_least(13) {
+                    -1
+                } else {
+                    -2
+                }
+            }
+            Operation::Send(_) => {
+                if jump && !at_least(12) {
+                    -1
+                } else {
+                    0
+                }
+            }
+            // The handler gets the state of the interpreter: 3 values of the exception being handled,
+            // followed by 3 of the one raised
+            Operation::SetupFinally(_) => {
+                if jump {
+                    6
+                } else {
+                    0
+                }
+            }
+            Operation::SetupWith(_) => {
+                if jump {
+                    6
+                } else {
+                    1
+                }
+            }
+            Operation::SetupAsyncWith(_) => {
+                if jump {
+                    5
+                } else {
+                    0
+                }
+            }
+            Operation::CallFinally(_) => jump as i32,
+            Operation::PopBlock => 0,
+            Operation::ReturnValue => -1,
+            Operation::ReturnConst(_) => 0,
+            Operation::RaiseVarargs(count) => -(*count as i32),
+            // Up to 3.10 exceptions take 3 values on the stack, since 3.11 only one
+            Operation::Reraise | Operation::PopExcept => {
+                if at_least(11) {
+                    -1
+                } else {
+                    -3
+                }
+            }
+            Operation::PushExcInfo => 1,
+            Operation::CheckExcMatch => 0,
+
+            Operation::CallFunction(count) => -(*count as i32),
+            Operation::CallFunctionKw(count) | Operation::CallMethod(count) => -1 - *count as i32,
+            Operation::Precall(count) => -(*count as i32),
+            // In 3.11 the arguments are popped by PRECALL
+            Operation::Call(count) => {
+                if at_least(12) {
+                    -1 - *count as i32
+                } else {
+                    -1
+                }
+            }
+            Operation::KwNames(_) => 0,
+            Operation::CallKw(count) => -2 - *count as i32,
+            Operation::CallFunctionEx { has_kwargs } => {
+                let callable = if at_least(11) { -2 } else { -1 };
+                callable - *has_kwargs as i32
+            }
+            // One value for every flag, up to 3.10 also the qualified name
+            Operation::MakeFunction(flags) => {
+                let qualname = if at_least(11) { 0 } else { 1 };
+                -((flags & 0x0F).count_ones() as i32) - qualname
+            }
+            Operation::SetFunctionAttribute(_) => -1,
+
+            Operation::Other { opcode, arg } => return Self::other_stack_effect(version, *opcode, *arg),
+        };
+        Some(effect)
+    }
+
+    fn other_stack_effect(version: &PythonVersion, opcode: Opcode, arg: u32) -> Option<i32> {
+        let at_least = |minor: u8| version.at_least(3, minor);
+        let effect = match opcode {
+            Opcode::CallIntrinsic1
+            | Opcode::CheckEgMatch
+            | Opcode::ConvertValue
+            | Opcode::CopyDictWithoutKeys
+            | Opcode::CopyFreeVars
+            | Opcode::FormatSimple
+            | Opcode::GetAiter
+            | Opcode::GetAwaitable
+            | Opcode::GetYieldFromIter
+            | Opcode::LoadFromDictOrDeref
+            | Opcode::LoadFromDictOrGlobals
+            | Opcode::AsyncGenWrap
+            | Opcode::SetupAnnotations
+            | Opcode::YieldValue => 0,
+            Opcode::BeforeAsyncWith
+            | Opcode::BeforeWith
+            | Opcode::GetAnext
+            | Opcode::GetLen
+            | Opcode::LoadAssertionError
+            | Opcode::LoadBuildClass
+            | Opcode::LoadLocals
+            | Opcode::MatchMapping
+            | Opcode::MatchSequence
+            | Opcode::WithExceptStart => 1,
+            Opcode::CallIntrinsic2
+            | Opcode::CleanupThrow
+            | Opcode::EndSend
+            | Opcode::ExitInitCheck
+            | Opcode::FormatWithSpec
+            | Opcode::GenStart
+            | Opcode::PrepReraiseStar
+            | Opcode::PrintExpr
+            | Opcode::YieldFrom => -1,
+            Opcode::BinarySlice => -2,
+            Opcode::StoreSlice => -4,
+            // Pushes the value sent to the generator when it starts, which CPython 3.11 and 3.12
+            // leave out as it's inserted after computing co_stacksize
+            Opcode::ReturnGenerator => 1,
+            // Bit 2 of the argument tells whether a format spec is on the stack
+            Opcode::FormatValue => -(((arg & 0x04) != 0) as i32),
+            Opcode::LoadSuperAttr => -2 + (arg & 1) as i32,
+            Opcode::MatchClass => {
+                if at_least(11) {
+                    -2
+                } else {
+                    -1
+                }
+            }
+            Opcode::MatchKeys => {
+                if at_least(11) {
+                    1
+                } else {
+                    2
+                }
+            }
+            Opcode::EndAsyncFor => {
+                if at_least(11) {
+                    -2
+                } else {
+                    -7
+                }
+            }
+
+            // Blocks of the finally statement of 3.8
+            Opcode::BeginFinally => 6,
+            Opcode::EndFinally | Opcode::PopFinally => -6,
+            Opcode::WithCleanupStart => 2,
+            Opcode::WithCleanupFinish => -3,
+            Opcode::BuildListUnpack
+            | Opcode::BuildMapUnpack
+            | Opcode::BuildMapUnpackWithCall
+            | Opcode::BuildSetUnpack
+            | Opcode::BuildTupleUnpack
+            | Opcode::BuildTupleUnpackWithCall => 1 - arg as i32,
+            _ => return None,
+        };
+        Some(effect)
+    }
+}
@@ -1,17 +1,36 @@
-use super::{code::CodeBlock, operations::Operation, var::Var};
+use super::{
+    code::CodeBlock,
+    exceptiontable::{self, ExceptionTableEntry},
+    instruction::InstructionBuilder,
+    linetable,
+    long::{self, PyLong},
+    marshalerror::{MarshalError, MarshalErrorKind, MarshalResult},
+    opcodes,
+    operations::RawInstruction,
+    pycheader::{PycHeader, HEADER_SIZE},
+    var::Var,
+};
 use std::fs;
 use std::path::Path;
-use std::io::Result;
 
 // TODO: Consider pushing this, code and var modules as a single, separate package
 // making it a dependency of llvm and transpiler modules
 
+// Single entry of the stack describing what the reader is currently reading
+#[derive(Debug, Clone)]
+struct ReadFrame {
+    what: String,
+    type_code: Option<u8>,
+}
+
 #[derive(Debug)]
 pub struct PyCacheReader {
     current_idx: usize,
     contents: Vec<u8>,
-    last_operation: String,
+    context: Vec<ReadFrame>,
     refs: Vec<Var>,
+    ref_ends: Vec<usize>, // offsets in the file where the objects of refs end
+    header: Option<PycHeader>,
 }
 
 impl PyCacheReader {
@@ -19,35 +38,57 @@ impl PyCacheReader {
         PyCacheReader {
             current_idx: 0,
             contents,
-            last_operation: "init".to_string(),
+            context: Vec::new(),
             refs: Vec::new(),
+            ref_ends: Vec::new(),
+            header: None,
         }
     }
 
-    pub fn from_file(pyc_path: &Path) -> Result<Self> {
+    pub fn from_file(pyc_path: &Path) -> std::io::Result<Self> {
         let contents = fs::read(pyc_path)?;
         Ok(PyCacheReader::new(contents))
     }
 
+    pub fn get_contents(&self) -> &Vec<u8> {
+        &self.contents
+    }
+
     pub fn get_refs(&self) -> &Vec<Var> {
         &self.refs
     }
 
-    pub fn get_refs_len(&self) -> usize {
-        self.refs.len()
+    pub fn get_header(&self) -> Option<&PycHeader> {
+        self.header.as_ref()
     }
 
-    pub fn push_ref(&mut self, var: Var) -> usize {
-        self.refs.push(var);
+    // Reserves the slot for an object flagged with FLAG_REF, before the object is read.
+    // Until it's set, the slot holds Null, which never gets flagged.
+    pub fn reserve_ref(&mut self) -> usize {
+        self.refs.push(Var::Null);
+        self.ref_ends.push(0);
         self.refs.len() - 1
     }
 
+    pub fn set_ref(&mut self, idx: usize, var: Var) {
+        self.refs[idx] = var;
+        self.ref_ends[idx] = self.current_idx;
+    }
+
+    // TYPE_REF can only point to an object which has already been read
+    pub fn read_ref(&mut self) -> MarshalResult<u32> {
+        let idx = self.read_ulong()?;
+        match self.refs.get(idx as usize) {
+            Some(Var::Null) | None => Err(self.error(MarshalErrorKind::InvalidRef(idx))),
+            Some(_) => Ok(idx),
+        }
+    }
+
     pub fn get_current_idx(&self) -> usize {
         self.current_idx
     }
 
     pub fn get(&self) -> Option<&u8> {
-        // TODO: EOF Case
         self.contents.get(self.current_idx)
     }
 
@@ -55,15 +96,19 @@ impl PyCacheReader {
         self.contents.get(idx)
     }
 
-    pub fn jump(&mut self, jump: usize) {
+    pub fn jump(&mut self, jump: usize) -> MarshalResult<()> {
         // > instead of >= to be able to read the last byte from file
-        if self.is_eof() || self.current_idx + jump > self.contents.len() {
-            panic!("Attempting to move the cursor beyond the file")
+        if self.current_idx + jump > self.contents.len() {
+            return Err(self.error(MarshalErrorKind::UnexpectedEof {
+                needed: jump,
+                available: self.contents.len().saturating_sub(self.current_idx),
+            }));
         }
-        self.current_idx += jump
+        self.current_idx += jump;
+        Ok(())
     }
 
-    pub fn next(&mut self) {
+    pub fn next(&mut self) -> MarshalResult<()> {
         self.jump(1)
     }
 
@@ -71,231 +116,407 @@ impl PyCacheReader {
         self.current_idx >= self.contents.len()
     }
 
-    // TODO: Make the last_operation a Vec containing the history of operations and calls within Reader
-    pub fn get_error_msg(&self) -> String {
-        let byte = self.contents[self.current_idx];
-        format!(
-            "Attempting to {} b'{}' (char: {}, hex: {:x?}) on idx {}",
-            self.last_operation, byte, byte as char, byte, self.current_idx
-        )
+    pub fn error(&self, kind: MarshalErrorKind) -> MarshalError {
+        self.error_at(self.current_idx, kind)
     }
 
-    fn set_last_operation(&mut self, last_op: &str) {
-        self.last_operation = last_op.to_string();
+    pub fn error_at(&self, offset: usize, kind: MarshalErrorKind) -> MarshalError {
+        MarshalError {
+            kind,
+            offset,
+            type_code: self.context.iter().rev().find_map(|frame| frame.type_code),
+            context: self.context.iter().map(|frame| frame.what.clone()).collect(),
+        }
     }
 
-    pub fn read_bytes(&mut self, bytes_amnt: i32) -> &[u8] {
-        // TODO: EOF case
-        self.set_last_operation("read bytes");
-        &self.contents[self.current_idx..self.current_idx + bytes_amnt as usize]
+    // Runs the read function with the given description pushed onto the context stack,
+    // so that errors raised within it tell what was being read
+    pub fn in_context<T>(
+        &mut self,
+        what: &str,
+        type_code: Option<u8>,
+        read: impl FnOnce(&mut Self) -> MarshalResult<T>,
+    ) -> MarshalResult<T> {
+        self.context.push(ReadFrame {
+            what: what.to_string(),
+            type_code,
+        });
+        let result = read(self);
+        self.context.pop();
+        result
     }
 
-    pub fn read_byte(&mut self) -> u8 {
-        // TODO: EOF case
-        self.set_last_operation("read byte");
-        let byte = self.contents[self.current_idx];
-        self.next();
-        byte
+    pub fn read_bytes(&mut self, bytes_amnt: usize) -> MarshalResult<&[u8]> {
+        let start = self.current_idx;
+        self.jump(bytes_amnt)?;
+        Ok(&self.contents[start..self.current_idx])
     }
 
-    pub fn read_char(&mut self) -> char {
-        // TODO: EOF case
-        self.set_last_operation("read char");
-        let chr = self.contents[self.current_idx] as char;
-        self.next();
-        chr
+    pub fn read_byte(&mut self) -> MarshalResult<u8> {
+        Ok(self.read_bytes(1)?[0])
     }
 
-    pub fn read_long(&mut self) -> i32 {
-        self.set_last_operation("read long or int");
-
+    pub fn read_long(&mut self) -> MarshalResult<i32> {
         // 4 bytes in 32-bit C and within CPython .pyc object
-        // TODO: EOF case
-        let long = i32::from_le_bytes([
-            self.contents[self.current_idx],
-            self.contents[self.current_idx + 1],
-            self.contents[self.current_idx + 2],
-            self.contents[self.current_idx + 3],
-        ]);
-        self.jump(4);
-        long
+        let bytes = self.read_bytes(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
     }
 
-    pub fn read_ulong(&mut self) -> u32 {
-        self.set_last_operation("read u_long");
-
+    pub fn read_ulong(&mut self) -> MarshalResult<u32> {
         // 4 bytes in 32-bit C and within CPython .pyc object
-        // TODO: EOF case
-        let long = u32::from_le_bytes([
-            self.contents[self.current_idx],
-            self.contents[self.current_idx + 1],
-            self.contents[self.current_idx + 2],
-            self.contents[self.current_idx + 3],
-        ]);
-        self.jump(4);
-        long
-    }
-
-    pub fn read_int(&mut self) -> i32 {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn read_int(&mut self) -> MarshalResult<i32> {
         // 4 bytes in 32-bit C and within CPython .pyc object
         self.read_long()
     }
 
-    pub fn read_short_string(&mut self) -> String {
-        self.set_last_operation("read short string");
-        let len = self.read_byte();
-        let mut str_res: String = String::from("");
-        for _ in 0..len {
-            let char: char = self.read_char();
-            str_res.push(char);
+    pub fn read_int64(&mut self) -> MarshalResult<i64> {
+        let bytes = self.read_bytes(8)?;
+        Ok(i64::from_le_bytes(bytes.try_into().expect("8 bytes to be read")))
+    }
+
+    // TYPE_LONG: signed amount of 15-bit digits (the sign being the one of the number),
+    // followed by the 2-byte digits, least significant first
+    pub fn read_py_long(&mut self) -> MarshalResult<PyLong> {
+        let size = self.read_long()?;
+        let mut digits: Vec<u16> = Vec::new();
+        for _ in 0..size.unsigned_abs() {
+            let bytes = self.read_bytes(2)?;
+            let digit = u16::from_le_bytes([bytes[0], bytes[1]]);
+            if digit > long::DIGIT_MASK {
+                return Err(self.error(MarshalErrorKind::InvalidLongDigit(digit)));
+            }
+            digits.push(digit);
+        }
+        if digits.last() == Some(&0) {
+            return Err(self.error(MarshalErrorKind::InvalidLongDigit(0)));
+        }
+
+        Ok(PyLong {
+            negative: size < 0,
+            digits,
+        })
+    }
+
+    // Size of a sized object, e.g. a tuple or a string, stored as a signed 4-byte integer
+    pub fn read_size(&mut self) -> MarshalResult<usize> {
+        let size = self.read_long()?;
+        usize::try_from(size).map_err(|_| self.error(MarshalErrorKind::InvalidSize(size as i64)))
+    }
+
+    pub fn read_binary_float(&mut self) -> MarshalResult<f64> {
+        let bytes = self.read_bytes(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().expect("8 bytes to be read")))
+    }
+
+    // Float written as its repr, preceded by a 1-byte length
+    pub fn read_float_str(&mut self) -> MarshalResult<f64> {
+        let text = self.read_ascii(true)?;
+        text.parse::<f64>()
+            .map_err(|_| self.error(MarshalErrorKind::InvalidFloat(text)))
+    }
+
+    // TYPE_STRING, i.e. a bytes object: 4-byte size followed by raw bytes
+    pub fn read_byte_string(&mut self) -> MarshalResult<Vec<u8>> {
+        let len = self.read_size()?;
+        Ok(self.read_bytes(len)?.to_vec())
+    }
+
+    // TYPE_UNICODE or TYPE_INTERNED: 4-byte size followed by UTF-8 encoded text
+    pub fn read_string(&mut self) -> MarshalResult<String> {
+        let len = self.read_size()?;
+        let start = self.current_idx;
+        let bytes = self.read_bytes(len)?.to_vec();
+        String::from_utf8(bytes).map_err(|e| {
+            self.error_at(
+                start + e.utf8_error().valid_up_to(),
+                MarshalErrorKind::InvalidString("UTF-8"),
+            )
+        })
+    }
+
+    // Short ASCII types have a 1-byte size, other ASCII types a 4-byte one
+    pub fn read_ascii(&mut self, short: bool) -> MarshalResult<String> {
+        let len = if short {
+            self.read_byte()? as usize
+        } else {
+            self.read_size()?
+        };
+        let start = self.current_idx;
+        let bytes = self.read_bytes(len)?.to_vec();
+        match bytes.iter().position(|byte| !byte.is_ascii()) {
+            Some(idx) => Err(self.error_at(start + idx, MarshalErrorKind::InvalidString("ASCII"))),
+            None => Ok(bytes.into_iter().map(|byte| byte as char).collect()),
         }
-        str_res
     }
 
-    pub fn read_string(&mut self) -> String {
-        self.set_last_operation("read string");
-        let len = self.read_ulong();
-        let mut str_res: String = String::from("");
-        for _ in 0..len {
-            let char: char = self.read_char();
-            str_res.push(char);
+    // Decodes co_code, which starts at the given offset in the file
+    pub fn decode_operations(&self, code: &[u8], file_offset: usize) -> MarshalResult<Vec<RawInstruction>> {
+        if !code.len().is_multiple_of(2) {
+            return Err(self.error(MarshalErrorKind::InvalidCodeSize(code.len())));
+        }
+        let version = self
+            .header
+            .as_ref()
+            .expect("the .pyc header to be read before operations")
+            .version;
+        let extended_arg_opcode = opcodes::extended_arg(&version);
+
+        let mut raw_instructions: Vec<RawInstruction> = Vec::new();
+        let mut extended_arg: u32 = 0;
+        let mut prefix_offset: Option<u32> = None;
+        let mut caches_left: usize = 0;
+        for (unit_idx, unit) in code.chunks_exact(2).enumerate() {
+            // Since 3.11 some operations are followed by inline CACHE entries used by the
+            // specialising interpreter, which aren't operations on their own
+            if caches_left > 0 {
+                caches_left -= 1;
+                continue;
+            }
+
+            let offset = (unit_idx * 2) as u32;
+            let opcode = unit[0];
+            let arg = extended_arg | unit[1] as u32;
+
+            // EXTENDED_ARG is folded into the argument of the next operation
+            if Some(opcode) == extended_arg_opcode {
+                extended_arg = arg << 8;
+                prefix_offset.get_or_insert(offset);
+                continue;
+            }
+
+            let decoded = opcodes::decode(&version, opcode).ok_or_else(|| {
+                self.error_at(
+                    file_offset + offset as usize,
+                    MarshalErrorKind::UnknownOpcode { opcode, version },
+                )
+            })?;
+            caches_left = opcodes::inline_cache_entries(&version, opcode);
+            raw_instructions.push(RawInstruction {
+                offset: prefix_offset.take().unwrap_or(offset),
+                end: offset + 2 * (1 + caches_left as u32),
+                opcode: decoded,
+                arg,
+            });
+            extended_arg = 0;
         }
-        str_res
+        Ok(raw_instructions)
     }
 
-    pub fn read_operation(&mut self) -> Option<Operation> {
-        // TODO: EOF case
-        self.set_last_operation("read operation");
-        let byte = self.read_byte();
-        Operation::from_byte(&byte, self)
+    // Checks that the exception table entries are ordered, don't overlap and point at instructions
+    fn check_exception_table(&self, entries: &[ExceptionTableEntry], code: &CodeBlock) -> MarshalResult<()> {
+        let code_len = code.co_code.resolve(&self.refs).as_bytes().map_or(0, |bytes| bytes.len()) as u32;
+        let is_operation = |offset: u32| {
+            code.instructions
+                .binary_search_by_key(&offset, |instruction| instruction.offset)
+                .is_ok()
+        };
+
+        let mut previous_end: u32 = 0;
+        for (i, entry) in entries.iter().enumerate() {
+            let msg = if entry.start >= entry.end || entry.end > code_len {
+                Some(format!("range {}..{} not within co_code of size {}", entry.start, entry.end, code_len))
+            } else if entry.start < previous_end {
+                Some(format!("range {}..{} overlaps the previous entry", entry.start, entry.end))
+            } else if !is_operation(entry.start) || !(entry.end == code_len || is_operation(entry.end)) {
+                Some(format!("range {}..{} doesn't start and end at operations", entry.start, entry.end))
+            } else if !is_operation(entry.target) {
+                Some(format!("target {} is not an operation", entry.target))
+            } else if entry.depth > code.co_stacksize as u32 {
+                Some(format!("depth {} exceeds co_stacksize {}", entry.depth, code.co_stacksize))
+            } else {
+                None
+            };
+            if let Some(msg) = msg {
+                return Err(self.error(MarshalErrorKind::InvalidExceptionTable(format!("entry {}: {}", i, msg))));
+            }
+            previous_end = entry.end;
+        }
+        Ok(())
     }
 
-    pub fn read_var(&mut self) -> Option<Var> {
-        self.set_last_operation("read var");
-        let byte = self.read_byte();
-        Var::from_byte(&byte, self)
+    pub fn read_var(&mut self) -> MarshalResult<Var> {
+        let byte = self.read_byte()?;
+        self.in_context("object", Some(byte), |reader| Var::from_byte(&byte, reader))
     }
 
-    pub fn read_tuple(&mut self) -> Vec<Var> {
-        self.set_last_operation("read tuple");
-        let len = self.read_byte();
+    // Reads the items of a tuple, list, set or frozenset
+    pub fn read_tuple(&mut self, len: usize) -> MarshalResult<Vec<Var>> {
         let mut tuple: Vec<Var> = Vec::new();
-        for _ in 0..len {
-            let byte = self.read_byte();
-            let var =
-                Var::from_byte(&byte, self).unwrap_or_else(|| panic!("{}", self.get_error_msg()));
+        for i in 0..len {
+            let var = self.in_context(&format!("item {}", i), None, |reader| reader.read_var())?;
             tuple.push(var);
         }
-        tuple
+        Ok(tuple)
     }
 
-    pub fn read_code(&mut self) -> CodeBlock {
+    pub fn read_dict(&mut self) -> MarshalResult<Vec<(Var, Var)>> {
+        let mut dict: Vec<(Var, Var)> = Vec::new();
+        loop {
+            let key = self.in_context(&format!("key {}", dict.len()), None, |reader| reader.read_var())?;
+            if let Var::Null = key {
+                break;
+            }
+            let value = self.in_context(&format!("value {}", dict.len()), None, |reader| reader.read_var())?;
+            dict.push((key, value));
+        }
+        Ok(dict)
+    }
+
+    fn read_long_field(&mut self, name: &str) -> MarshalResult<i32> {
+        self.in_context(name, None, |reader| reader.read_long())
+    }
+
+    fn read_var_field(&mut self, name: &str) -> MarshalResult<Box<Var>> {
+        Ok(Box::new(self.in_context(name, None, |reader| reader.read_var())?))
+    }
+
+    pub fn read_code(&mut self) -> MarshalResult<CodeBlock> {
         let mut code = CodeBlock {
             ..Default::default()
         };
 
+        // 3.11 merged the tables of variable names into co_localsplusnames
+        let version = self
+            .header
+            .as_ref()
+            .expect("the .pyc header to be read before code objects")
+            .version;
+        let has_localsplus = version.at_least(3, 11);
+
         // Static params
-        code.co_argcount = self.read_long();
-        code.co_kwonlyargcount = self.read_long();
-        code.co_nlocals = self.read_long();
-        code.co_posonlyargcount = self.read_long();
-        code.co_stacksize = self.read_long();
-        code.co_flags = self.read_long();
-        self.next(); // skip 1 byte flag representing the co_code_size, i.e. 's' (string)
-        let co_code_size = self.read_long();
-
-        // Operations (next co_code_size bytes)
-        let mut co_code: Vec<Operation> = Vec::new();
-        let limit = self.current_idx + co_code_size as usize;
-        while self.current_idx < limit {
-            let operation = self
-                .read_operation()
-                .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-            co_code.push(operation);
+        code.co_argcount = self.read_long_field("co_argcount")?;
+        code.co_posonlyargcount = self.read_long_field("co_posonlyargcount")?;
+        code.co_kwonlyargcount = self.read_long_field("co_kwonlyargcount")?;
+        if !has_localsplus {
+            code.co_nlocals = self.read_long_field("co_nlocals")?;
         }
-        code.co_code = co_code;
+        code.co_stacksize = self.read_long_field("co_stacksize")?;
+        code.co_flags = self.read_long_field("co_flags")?;
+
+        // Operations, stored as a bytes object
+        code.co_code = self.read_var_field("co_code")?;
+        let (raw_instructions, code_file_offset) = self.in_context("co_code", None, |reader| {
+            let co_code = code.co_code.resolve(&reader.refs);
+            let bytes = co_code.as_bytes().ok_or_else(|| {
+                reader.error(MarshalErrorKind::UnexpectedVar {
+                    expected: "bytes",
+                    found: format!("{:?}", co_code),
+                })
+            })?;
+            // The bytes end where co_code was just read, or for a TYPE_REF where the object it refers to was
+            let end = match *code.co_code {
+                Var::Ref(idx) => reader.ref_ends[idx as usize],
+                _ => reader.current_idx,
+            };
+            let file_offset = end.saturating_sub(bytes.len());
+            Ok((reader.decode_operations(&bytes, file_offset)?, file_offset))
+        })?;
 
         // co_const - tuple of typed variables, including CodeBlocks
-        let co_const = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-        code.co_const = Box::new(co_const);
+        code.co_const = self.read_var_field("co_consts")?;
 
         // co_names - tuple of strings
-        let co_names = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-        code.co_names = Box::new(co_names);
-
-        // co_varnames
-        let co_varnames = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-        code.co_varnames = Box::new(co_varnames);
-
-        // co_freevars
-        let co_freevars = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-        code.co_freevars = Box::new(co_freevars);
-
-        // co_cellvars
-        let co_cellvars = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-        code.co_cellvars = Box::new(co_cellvars);
-
-        // co_filename
-        let co_filename = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-        code.co_filename = Box::new(co_filename);
-
-        // co_name
-        let co_name = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-        code.co_name = Box::new(co_name);
-
-        // co_firstlineno
-        let co_firstlineno = self.read_long();
-        code.co_firstlineno = co_firstlineno;
-
-        // co_lnotab
-        // Probably something wrong got read here, but this doesn't matter - it's unused
-        // This should be a mapping of bytecode offset to line locations in Python file
-        let co_lnotab = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
-        code.co_lnotab = Box::new(co_lnotab);
-
-        code
-    }
-
-    pub fn read_file(&mut self) -> Option<CodeBlock> {
-        // Skip the Python bytecode file header:
-        // - 4 bytes: Magic number
-        // - 4 bytes: Bit field
-        // - 4 bytes: Timestamp
-        // - 4 bytes: Source size
-        self.current_idx = 16;
+        code.co_names = self.read_var_field("co_names")?;
+
+        if has_localsplus {
+            code.co_localsplusnames = self.read_var_field("co_localsplusnames")?;
+            code.co_localspluskinds = self.read_var_field("co_localspluskinds")?;
+        } else {
+            code.co_varnames = self.read_var_field("co_varnames")?;
+            code.co_freevars = self.read_var_field("co_freevars")?;
+            code.co_cellvars = self.read_var_field("co_cellvars")?;
+        }
+        code.co_filename = self.read_var_field("co_filename")?;
+        code.co_name = self.read_var_field("co_name")?;
+        if has_localsplus {
+            code.co_qualname = self.read_var_field("co_qualname")?;
+        }
+        code.co_firstlineno = self.read_long_field("co_firstlineno")?;
+
+        // co_lnotab - mapping of bytecode offsets to locations in the Python file,
+        // in a format depending on the version
+        code.co_lnotab = self.read_var_field("co_lnotab")?;
+        code.lines = self.in_context("co_lnotab", None, |reader| {
+            let table = code.co_lnotab.resolve(&reader.refs).as_bytes().unwrap_or_default();
+            let code_len = code.co_code.resolve(&reader.refs).as_bytes().map_or(0, |bytes| bytes.len());
+            linetable::decode(&version, &table, code.co_firstlineno, code_len as u32)
+                .map_err(|msg| reader.error(MarshalErrorKind::InvalidLineTable(msg)))
+        })?;
+
+        if has_localsplus {
+            code.co_exceptiontable = self.read_var_field("co_exceptiontable")?;
+
+            let localsplusnames = code.co_localsplusnames.resolve(&self.refs);
+            let localspluskinds = code.co_localspluskinds.resolve(&self.refs);
+            code.derive_from_localsplus(&localsplusnames, &localspluskinds);
+        }
+
+        // Once all the tables are read, the operations can be resolved into instructions
+        code.instructions = self.in_context("co_code", None, |reader| {
+            let resolved = code.resolve(&reader.refs);
+            InstructionBuilder::new(version, &resolved)
+                .build(&raw_instructions)
+                .map_err(|(offset, msg)| {
+                    reader.error_at(code_file_offset + offset as usize, MarshalErrorKind::InvalidOperand(msg))
+                })
+        })?;
+
+        if has_localsplus {
+            code.exception_table = self.in_context("co_exceptiontable", None, |reader| {
+                let table = code.co_exceptiontable.resolve(&reader.refs).as_bytes().unwrap_or_default();
+                let entries = exceptiontable::decode(&table)
+                    .map_err(|msg| reader.error(MarshalErrorKind::InvalidExceptionTable(msg.to_string())))?;
+                reader.check_exception_table(&entries, &code)?;
+                Ok(entries)
+            })?;
+        }
+
+        Ok(code)
+    }
+
+    pub fn read_header(&mut self) -> MarshalResult<PycHeader> {
+        let header = PycHeader::from_bytes(&self.contents)
+            .map_err(|msg| self.error_at(0, MarshalErrorKind::InvalidHeader(msg)))?;
+        self.current_idx = HEADER_SIZE;
+        self.header = Some(header.clone());
+        Ok(header)
+    }
+
+    // Reads the object following the header as it is stored, i.e. with FlagRef markers and Refs
+    pub fn read_file_raw(&mut self) -> MarshalResult<Var> {
+        // The header determines the Python version, and thus the opcode table used to decode operations
+        self.current_idx = 0;
+        self.refs.clear();
+        self.ref_ends.clear();
+        self.read_header()?;
 
         // Read the main block of code in the .pyc file
-        let code = self
-            .read_var()
-            .unwrap_or_else(|| panic!("{}", self.get_error_msg()));
+        let code = self.in_context("module", None, |reader| reader.read_var())?;
 
         // Go back to the beginning of the file
         self.current_idx = 0;
 
-        // Proper .pyc file has to start with either a code block or a reference to it
+        Ok(code)
+    }
+
+    // Reads the module code object, with all references resolved
+    pub fn read_file(&mut self) -> MarshalResult<CodeBlock> {
+        let code = self.read_file_raw()?.resolve(&self.refs);
+
+        // Proper .pyc file has to start with a code block
         match code {
-            Var::Code(code_block) => Some(code_block),
-            Var::FlagRef(boxed_var) => match *boxed_var {
-                Var::Code(code_block) => Some(code_block),
-                _ => None,
-            },
-            _ => None,
+            Var::Code(code_block) => Ok(code_block),
+            var => Err(self.error_at(
+                HEADER_SIZE,
+                MarshalErrorKind::UnexpectedVar {
+                    expected: "a code object",
+                    found: format!("{:?}", var),
+                },
+            )),
         }
     }
 }
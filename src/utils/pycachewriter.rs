@@ -0,0 +1,198 @@
+use super::{code::CodeBlock, pycheader::PycHeader, var::Var, version::PythonVersion};
+use std::fs;
+use std::path::Path;
+
+const FLAG_REF: u8 = 0x80;
+
+// Serialises code objects the way CPython's marshal does.
+// Objects wrapped in Var::FlagRef are written with FLAG_REF and Var::Ref as TYPE_REF,
+// so a tree read with PyCacheReader::read_file_raw is written back byte for byte.
+#[derive(Debug)]
+pub struct PyCacheWriter {
+    contents: Vec<u8>,
+    version: PythonVersion, // determines the layout of code objects
+}
+
+impl PyCacheWriter {
+    pub fn new(version: PythonVersion) -> PyCacheWriter {
+        PyCacheWriter {
+            contents: Vec::new(),
+            version,
+        }
+    }
+
+    pub fn get_contents(&self) -> &Vec<u8> {
+        &self.contents
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.contents.extend_from_slice(bytes);
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        self.contents.push(byte);
+    }
+
+    pub fn write_long(&mut self, long: i32) {
+        self.write_bytes(&long.to_le_bytes());
+    }
+
+    pub fn write_ulong(&mut self, long: u32) {
+        self.write_bytes(&long.to_le_bytes());
+    }
+
+    // Size of a sized object, e.g. a tuple or a string
+    pub fn write_size(&mut self, size: usize) {
+        let size = i32::try_from(size).expect("object size to fit in 4 bytes");
+        self.write_long(size);
+    }
+
+    fn write_type(&mut self, type_code: u8, flag: bool) {
+        self.write_byte(if flag { type_code | FLAG_REF } else { type_code });
+    }
+
+    fn write_items(&mut self, vars: &[Var]) {
+        for var in vars {
+            self.write_var(var);
+        }
+    }
+
+    pub fn write_var(&mut self, var: &Var) {
+        self.write_var_flagged(var, false)
+    }
+
+    fn write_var_flagged(&mut self, var: &Var, flag: bool) {
+        match var {
+            Var::FlagRef(var) => self.write_var_flagged(var, true),
+            Var::Null => self.write_type(b'0', flag),
+            Var::None => self.write_type(b'N', flag),
+            Var::False => self.write_type(b'F', flag),
+            Var::True => self.write_type(b'T', flag),
+            Var::StopIteration => self.write_type(b'S', flag),
+            Var::Ellipsis => self.write_type(b'.', flag),
+            Var::Int(i) => {
+                self.write_type(b'i', flag);
+                self.write_long(*i);
+            }
+            Var::Int64(i) => {
+                self.write_type(b'I', flag);
+                self.write_bytes(&i.to_le_bytes());
+            }
+            Var::Long(long) => {
+                self.write_type(b'l', flag);
+                let size = long.digits.len() as i32;
+                self.write_long(if long.negative { -size } else { size });
+                for digit in &long.digits {
+                    self.write_bytes(&digit.to_le_bytes());
+                }
+            }
+            Var::Float(f) => {
+                self.write_type(b'g', flag);
+                self.write_bytes(&f.to_le_bytes());
+            }
+            Var::Complex(real, imag) => {
+                self.write_type(b'y', flag);
+                self.write_bytes(&real.to_le_bytes());
+                self.write_bytes(&imag.to_le_bytes());
+            }
+            Var::Bytes(bytes) => {
+                self.write_type(b's', flag);
+                self.write_size(bytes.len());
+                self.write_bytes(bytes);
+            }
+            Var::Interned(s) | Var::Unicode(s) | Var::Ascii(s) | Var::AsciiInterned(s) => {
+                let type_code = match var {
+                    Var::Interned(_) => b't',
+                    Var::Unicode(_) => b'u',
+                    Var::Ascii(_) => b'a',
+                    _ => b'A',
+                };
+                self.write_type(type_code, flag);
+                self.write_size(s.len());
+                self.write_bytes(s.as_bytes());
+            }
+            Var::ShortAscii(s) | Var::ShortAsciiInterned(s) => {
+                let type_code = if let Var::ShortAscii(_) = var { b'z' } else { b'Z' };
+                self.write_type(type_code, flag);
+                self.write_byte(u8::try_from(s.len()).expect("short string to be shorter than 256 bytes"));
+                self.write_bytes(s.as_bytes());
+            }
+            Var::Ref(idx) => {
+                self.write_type(b'r', flag);
+                self.write_ulong(*idx);
+            }
+            Var::SmallTuple(vars) => {
+                self.write_type(b')', flag);
+                self.write_byte(u8::try_from(vars.len()).expect("small tuple to have less than 256 items"));
+                self.write_items(vars);
+            }
+            Var::Tuple(vars) | Var::List(vars) | Var::Set(vars) | Var::FrozenSet(vars) => {
+                let type_code = match var {
+                    Var::Tuple(_) => b'(',
+                    Var::List(_) => b'[',
+                    Var::Set(_) => b'<',
+                    _ => b'>',
+                };
+                self.write_type(type_code, flag);
+                self.write_size(vars.len());
+                self.write_items(vars);
+            }
+            Var::Dict(pairs) => {
+                self.write_type(b'{', flag);
+                for (key, value) in pairs {
+                    self.write_var(key);
+                    self.write_var(value);
+                }
+                self.write_var(&Var::Null);
+            }
+            Var::Code(code) => {
+                self.write_type(b'c', flag);
+                self.write_code(code);
+            }
+        }
+    }
+
+    pub fn write_code(&mut self, code: &CodeBlock) {
+        let has_localsplus = self.version.at_least(3, 11);
+
+        self.write_long(code.co_argcount);
+        self.write_long(code.co_posonlyargcount);
+        self.write_long(code.co_kwonlyargcount);
+        if !has_localsplus {
+            self.write_long(code.co_nlocals);
+        }
+        self.write_long(code.co_stacksize);
+        self.write_long(code.co_flags);
+        self.write_var(&code.co_code);
+        self.write_var(&code.co_const);
+        self.write_var(&code.co_names);
+        if has_localsplus {
+            self.write_var(&code.co_localsplusnames);
+            self.write_var(&code.co_localspluskinds);
+        } else {
+            self.write_var(&code.co_varnames);
+            self.write_var(&code.co_freevars);
+            self.write_var(&code.co_cellvars);
+        }
+        self.write_var(&code.co_filename);
+        self.write_var(&code.co_name);
+        if has_localsplus {
+            self.write_var(&code.co_qualname);
+        }
+        self.write_long(code.co_firstlineno);
+        self.write_var(&code.co_lnotab);
+        if has_localsplus {
+            self.write_var(&code.co_exceptiontable);
+        }
+    }
+
+    // The module code is either a Var::Code or, as written by CPython, a Var::FlagRef wrapping it
+    pub fn write_file(&mut self, header: &PycHeader, code: &Var) {
+        self.write_bytes(&header.to_bytes());
+        self.write_var(code);
+    }
+
+    pub fn save_to_file(&self, pyc_path: &Path) -> std::io::Result<()> {
+        fs::write(pyc_path, &self.contents)
+    }
+}
@@ -0,0 +1,92 @@
+use super::version::PythonVersion;
+
+pub const HEADER_SIZE: usize = 16;
+
+// PEP 552 bit field
+const FLAG_HASH_BASED: u32 = 0b01; // source is validated by a hash instead of mtime and size
+const FLAG_CHECK_SOURCE: u32 = 0b10; // hash-based .pyc should be validated against the source
+
+// How the .pyc file is checked against its source to determine whether it's stale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PycValidation {
+    Timestamp { mtime: u32, source_size: u32 },
+    Hash { source_hash: [u8; 8], check_source: bool },
+}
+
+// Header preceding the marshalled code object in a .pyc file:
+// - 4 bytes: Magic number (2 bytes identifying the Python version, followed by b"\r\n")
+// - 4 bytes: Bit field (PEP 552)
+// - 8 bytes: Timestamp and source size, or source hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PycHeader {
+    pub magic: u16,
+    pub version: PythonVersion,
+    pub flags: u32,
+    pub validation: PycValidation,
+}
+
+impl PycHeader {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(format!(
+                "File is too short to be a .pyc file ({} bytes, header alone takes {})",
+                bytes.len(),
+                HEADER_SIZE
+            ));
+        }
+
+        let word = |idx: usize| u32::from_le_bytes([bytes[idx], bytes[idx + 1], bytes[idx + 2], bytes[idx + 3]]);
+
+        let magic = u16::from_le_bytes([bytes[0], bytes[1]]);
+        if bytes[2..4] != *b"\r\n" {
+            return Err(format!("Invalid .pyc magic number {:x?}", &bytes[0..4]));
+        }
+        let version = PythonVersion::from_magic(magic).ok_or_else(|| {
+            format!(
+                "Unsupported .pyc magic number {} - only bytecode of CPython 3.8 to 3.13 can be read",
+                magic
+            )
+        })?;
+
+        let flags = word(4);
+        if flags & !(FLAG_HASH_BASED | FLAG_CHECK_SOURCE) != 0 {
+            return Err(format!("Invalid .pyc flags {:#b}", flags));
+        }
+
+        let validation = if flags & FLAG_HASH_BASED != 0 {
+            let mut source_hash = [0; 8];
+            source_hash.copy_from_slice(&bytes[8..16]);
+            PycValidation::Hash {
+                source_hash,
+                check_source: flags & FLAG_CHECK_SOURCE != 0,
+            }
+        } else {
+            PycValidation::Timestamp {
+                mtime: word(8),
+                source_size: word(12),
+            }
+        };
+
+        Ok(PycHeader {
+            magic,
+            version,
+            flags,
+            validation,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        bytes[0..2].copy_from_slice(&self.magic.to_le_bytes());
+        bytes[2..4].copy_from_slice(b"\r\n");
+        bytes[4..8].copy_from_slice(&self.flags.to_le_bytes());
+        match &self.validation {
+            PycValidation::Timestamp { mtime, source_size } => {
+                bytes[8..12].copy_from_slice(&mtime.to_le_bytes());
+                bytes[12..16].copy_from_slice(&source_size.to_le_bytes());
+            }
+            PycValidation::Hash { source_hash, .. } => bytes[8..16].copy_from_slice(source_hash),
+        }
+        bytes
+    }
+}
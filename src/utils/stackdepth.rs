@@ -0,0 +1,211 @@
+use super::{
+    cfg::{BlockIndex, ControlFlowGraph, EdgeKind},
+    code::CodeBlock,
+    opcodes::Opcode,
+    operations::{InstructionIndex, Operation},
+    version::PythonVersion,
+};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StackDepthError {
+    UnknownEffect { offset: u32, operation: String }, // operation not expected in a .pyc file
+    Underflow { offset: u32 },                         // popping from an empty stack
+    Mismatch { offset: u32, depth: u32, other: u32 },  // reached by paths with different depths
+    BelowHandlerDepth { offset: u32, depth: u32, handler_depth: u32 }, // handler would pop values it doesn't have
+    ExceedsStackSize { max_depth: u32, stacksize: i32 },
+}
+
+impl fmt::Display for StackDepthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StackDepthError::UnknownEffect { offset, operation } => {
+                write!(f, "unknown stack effect of {} at offset {}", operation, offset)
+            }
+            StackDepthError::Underflow { offset } => write!(f, "stack underflow at offset {}", offset),
+            StackDepthError::Mismatch { offset, depth, other } => write!(
+                f,
+                "offset {} reached with stack depths {} and {}",
+                offset, depth, other
+            ),
+            StackDepthError::BelowHandlerDepth {
+                offset,
+                depth,
+                handler_depth,
+            } => write!(
+                f,
+                "stack depth {} at offset {} is below the depth {} of its exception handler",
+                depth, offset, handler_depth
+            ),
+            StackDepthError::ExceedsStackSize { max_depth, stacksize } => write!(
+                f,
+                "maximum stack depth {} exceeds co_stacksize {}",
+                max_depth, stacksize
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StackDepthError {}
+
+// Depth of the value stack before every instruction of a code object, over all paths of its control-flow graph
+#[derive(Debug, Clone)]
+pub struct StackDepths {
+    depths: Vec<Option<u32>>, // None for unreachable instructions
+    pub max_depth: u32,
+}
+
+struct DepthWalker<'a> {
+    version: PythonVersion,
+    code: &'a CodeBlock,
+    cfg: &'a ControlFlowGraph,
+    block_depths: Vec<Option<u32>>, // depth at the start of every block
+    worklist: Vec<BlockIndex>,
+    max_depth: u32,
+}
+
+impl DepthWalker<'_> {
+    // Depths are checked as they are reached, which also bounds the re-walks of 3.8 finally blocks
+    fn record(&mut self, depth: u32) -> Result<(), StackDepthError> {
+        self.max_depth = self.max_depth.max(depth);
+        if depth as i64 > self.code.co_stacksize as i64 {
+            return Err(StackDepthError::ExceedsStackSize {
+                max_depth: depth,
+                stacksize: self.code.co_stacksize,
+            });
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, block: BlockIndex, depth: u32) -> Result<(), StackDepthError> {
+        self.record(depth)?;
+        match self.block_depths[block] {
+            None => {
+                self.block_depths[block] = Some(depth);
+                self.worklist.push(block);
+                Ok(())
+            }
+            Some(other) if other == depth => Ok(()),
+            // Finally blocks of 3.8 are entered with 1 value by CALL_FINALLY and with 6 by BEGIN_FINALLY
+            // or an exception, END_FINALLY popping whichever is there. Like CPython, the largest depth is kept.
+            Some(other) if !self.version.at_least(3, 9) => {
+                if depth > other {
+                    self.block_depths[block] = Some(depth);
+                    self.worklist.push(block);
+                }
+                Ok(())
+            }
+            Some(other) => {
+                let first = self.cfg.blocks[block].instructions.start;
+                Err(StackDepthError::Mismatch {
+                    offset: self.code.get_instructions()[first].offset,
+                    depth,
+                    other,
+                })
+            }
+        }
+    }
+
+    fn apply(depth: u32, effect: i32, offset: u32) -> Result<u32, StackDepthError> {
+        u32::try_from(depth as i64 + effect as i64).map_err(|_| StackDepthError::Underflow { offset })
+    }
+
+    // Walks the block from its start depth, returning the depth before each of its instructions
+    fn walk(&mut self, block: BlockIndex) -> Result<Vec<u32>, StackDepthError> {
+        let instructions = self.code.get_instructions();
+        let range = self.cfg.blocks[block].instructions.clone();
+        let mut depth = self.block_depths[block].expect("blocks to be walked once their depth is known");
+        let mut depths: Vec<u32> = Vec::new();
+
+        for instruction in &instructions[range] {
+            let offset = instruction.offset;
+            depths.push(depth);
+
+            // Handlers of 3.11+ start at the depth given by the exception table,
+            // with the exception and, if lasti is set, the offset of the raising operation
+            if let Some(entry) = self.code.exception_handler_for_offset(offset) {
+                if depth < entry.depth {
+                    return Err(StackDepthError::BelowHandlerDepth {
+                        offset,
+                        depth,
+                        handler_depth: entry.depth,
+                    });
+                }
+                let handler = instructions.partition_point(|instruction| instruction.offset < entry.target);
+                let handler_depth = entry.depth + 1 + entry.lasti as u32;
+                self.push(self.cfg.block_of(handler), handler_depth)?;
+            }
+
+            let version = self.version;
+            let effect = |jump: bool| {
+                instruction
+                    .op
+                    .stack_effect(&version, jump)
+                    .ok_or_else(|| StackDepthError::UnknownEffect {
+                        offset,
+                        operation: format!("{:?}", instruction.op),
+                    })
+            };
+            if let Some(target) = instruction.op.jump_target() {
+                let target_depth = Self::apply(depth, effect(true)?, offset)?;
+                self.push(self.cfg.block_of(target), target_depth)?;
+            }
+            depth = Self::apply(depth, effect(false)?, offset)?;
+            self.record(depth)?;
+        }
+
+        let fallthrough = self.cfg.blocks[block]
+            .successors
+            .iter()
+            .find(|(_, kind)| *kind == EdgeKind::Fallthrough)
+            .map(|(successor, _)| *successor);
+        if let Some(successor) = fallthrough {
+            self.push(successor, depth)?;
+        }
+        Ok(depths)
+    }
+}
+
+impl StackDepths {
+    pub fn analyze(
+        version: PythonVersion,
+        code: &CodeBlock,
+        cfg: &ControlFlowGraph,
+    ) -> Result<Self, StackDepthError> {
+        let instructions = code.get_instructions();
+        let mut walker = DepthWalker {
+            version,
+            code,
+            cfg,
+            block_depths: vec![None; cfg.blocks.len()],
+            worklist: Vec::new(),
+            max_depth: 0,
+        };
+        let mut depths: Vec<Option<u32>> = vec![None; instructions.len()];
+
+        // Generators of 3.10 start with the value sent to them on the stack, popped by GEN_START
+        let starts_generator = matches!(
+            instructions.first(),
+            Some(instruction) if matches!(instruction.op, Operation::Other { opcode: Opcode::GenStart, .. })
+        );
+        if !cfg.blocks.is_empty() {
+            walker.push(cfg.entry(), starts_generator as u32)?;
+        }
+        while let Some(block) = walker.worklist.pop() {
+            let start = cfg.blocks[block].instructions.start;
+            for (i, depth) in walker.walk(block)?.into_iter().enumerate() {
+                depths[start + i] = Some(depth);
+            }
+        }
+
+        Ok(StackDepths {
+            depths,
+            max_depth: walker.max_depth,
+        })
+    }
+
+    // Depth before the instruction, None if it's unreachable
+    pub fn before(&self, instruction: InstructionIndex) -> Option<u32> {
+        self.depths[instruction]
+    }
+}
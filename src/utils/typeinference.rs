@@ -0,0 +1,504 @@
+use super::{
+    cfg::{BlockIndex, ControlFlowGraph, EdgeKind},
+    code::CodeBlock,
+    opcodes::Opcode,
+    operations::{BinOp, CmpKind, InstructionIndex, Name, Operation, UnaryOp},
+    stackdepth::StackDepths,
+    var::Var,
+    version::PythonVersion,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Flags of code objects returning a generator or a coroutine instead of their return value
+const CO_GENERATOR: i32 = 0x20;
+const CO_COROUTINE: i32 = 0x80;
+const CO_ITERABLE_COROUTINE: i32 = 0x100;
+const CO_ASYNC_GENERATOR: i32 = 0x200;
+
+// Type of a Python value as far as it can be told from the bytecode. Dynamic stands for any type,
+// e.g. where paths with different types meet, so the value has to be handled at run time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InferredType {
+    Int,
+    Float,
+    Bool,
+    Str,
+    None,
+    List,
+    Range,         // result of range(), only told apart in the IR
+    RangeIterator, // iterator over a range, only told apart in the IR
+    Dynamic,
+}
+
+impl InferredType {
+    pub fn join(self, other: InferredType) -> InferredType {
+        if self == other {
+            self
+        } else {
+            InferredType::Dynamic
+        }
+    }
+
+    pub fn of_constant(var: &Var) -> InferredType {
+        match var {
+            Var::Int(_) | Var::Int64(_) | Var::Long(_) => InferredType::Int,
+            Var::Float(_) => InferredType::Float,
+            Var::True | Var::False => InferredType::Bool,
+            Var::None => InferredType::None,
+            _ if var.as_string().is_some() => InferredType::Str,
+            _ => InferredType::Dynamic,
+        }
+    }
+
+    // Bools take part in arithmetic as the ints 0 and 1
+    pub fn is_integral(self) -> bool {
+        matches!(self, InferredType::Int | InferredType::Bool)
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, InferredType::Int | InferredType::Bool | InferredType::Float)
+    }
+
+    pub fn unary(op: UnaryOp, operand: InferredType) -> InferredType {
+        match (op, operand) {
+            (UnaryOp::Not, _) => InferredType::Bool,
+            (UnaryOp::Positive | UnaryOp::Negative, InferredType::Float) => InferredType::Float,
+            (UnaryOp::Positive | UnaryOp::Negative | UnaryOp::Invert, operand) if operand.is_integral() => {
+                InferredType::Int
+            }
+            _ => InferredType::Dynamic,
+        }
+    }
+
+    // Result of the operator for the builtin types, where it's known without the values.
+    // Power is left out as its result depends on the sign of the exponent.
+    pub fn binary(op: BinOp, inplace: bool, a: InferredType, b: InferredType) -> InferredType {
+        use InferredType::*;
+        match op {
+            BinOp::And | BinOp::Or | BinOp::Xor if a == Bool && b == Bool => Bool,
+            BinOp::And | BinOp::Or | BinOp::Xor | BinOp::Lshift | BinOp::Rshift
+                if a.is_integral() && b.is_integral() =>
+            {
+                Int
+            }
+            BinOp::Add | BinOp::Subtract | BinOp::Multiply | BinOp::FloorDivide | BinOp::Modulo
+                if a.is_integral() && b.is_integral() =>
+            {
+                Int
+            }
+            BinOp::Add | BinOp::Subtract | BinOp::Multiply | BinOp::FloorDivide | BinOp::Modulo | BinOp::TrueDivide
+                if a.is_numeric() && b.is_numeric() =>
+            {
+                Float
+            }
+            BinOp::Add if a == Str && b == Str => Str,
+            BinOp::Add if a == List && b == List => List,
+            // list += takes any iterable
+            BinOp::Add if inplace && a == List => List,
+            BinOp::Multiply if (a == Str && b.is_integral()) || (a.is_integral() && b == Str) => Str,
+            BinOp::Multiply if (a == List && b.is_integral()) || (a.is_integral() && b == List) => List,
+            // printf-style formatting
+            BinOp::Modulo if a == Str => Str,
+            _ => Dynamic,
+        }
+    }
+}
+
+// Types of the values on the stack, from the bottom, and of the variables assigned in the code object
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeState {
+    pub stack: Vec<InferredType>,
+    pub variables: BTreeMap<Name, InferredType>,
+}
+
+impl TypeState {
+    fn push(&mut self, value_type: InferredType) {
+        self.stack.push(value_type);
+    }
+
+    fn pop(&mut self) -> InferredType {
+        self.stack.pop().unwrap_or(InferredType::Dynamic)
+    }
+
+    fn pop_n(&mut self, count: usize) {
+        let len = self.stack.len().saturating_sub(count);
+        self.stack.truncate(len);
+    }
+
+    // n-th value from the top, counting from 1
+    fn peek(&self, n: usize) -> InferredType {
+        self.stack
+            .len()
+            .checked_sub(n)
+            .map_or(InferredType::Dynamic, |idx| self.stack[idx])
+    }
+
+    // Merges the state of another path reaching the same point, returning whether this one changed.
+    // A variable assigned on one of the paths only keeps its type, as using it on the other one raises.
+    // Stacks only differ in length in the finally blocks of 3.8, whose extra values are left as Dynamic.
+    fn join(&mut self, other: &TypeState) -> bool {
+        let before = self.clone();
+        for (idx, value_type) in other.stack.iter().enumerate() {
+            match self.stack.get_mut(idx) {
+                Some(own) => *own = own.join(*value_type),
+                None => self.stack.push(InferredType::Dynamic),
+            }
+        }
+        for own in self.stack.iter_mut().skip(other.stack.len()) {
+            *own = InferredType::Dynamic;
+        }
+        for (name, value_type) in &other.variables {
+            self.variables
+                .entry(name.clone())
+                .and_modify(|own| *own = own.join(*value_type))
+                .or_insert(*value_type);
+        }
+        *self != before
+    }
+}
+
+// Types of the values of a code object before every instruction, found by a forward data-flow analysis
+// over its control-flow graph. Arguments, globals and the results of calls are Dynamic.
+#[derive(Debug, Clone)]
+pub struct TypeInference {
+    states: Vec<Option<TypeState>>, // None for unreachable instructions
+    pub return_type: Option<InferredType>, // None if the code never returns
+}
+
+struct TypeWalker {
+    version: PythonVersion,
+    global_stores: BTreeSet<Name>, // names that nested code objects may change at any call
+    return_type: Option<InferredType>,
+}
+
+impl TypeWalker {
+    fn variable(&self, state: &TypeState, name: &Name) -> InferredType {
+        if self.global_stores.contains(name) {
+            return InferredType::Dynamic;
+        }
+        state
+            .variables
+            .get(name)
+            .copied()
+            .unwrap_or(InferredType::Dynamic)
+    }
+
+    fn effect(&self, op: &Operation, jump: bool) -> i32 {
+        op.stack_effect(&self.version, jump).unwrap_or(0)
+    }
+
+    // Applies the stack effect of the operation, its new values being Dynamic
+    fn apply_effect(&self, state: &mut TypeState, op: &Operation, jump: bool) {
+        let effect = self.effect(op, jump);
+        state.pop_n((-effect).max(0) as usize);
+        for _ in 0..effect.max(0) {
+            state.push(InferredType::Dynamic);
+        }
+    }
+
+    // Pops the operands of an operation pushing a single value, counted from its stack effect
+    fn replace(&self, state: &mut TypeState, op: &Operation, jump: bool, result: InferredType) {
+        state.pop_n((1 - self.effect(op, jump)).max(0) as usize);
+        state.push(result);
+    }
+
+    fn return_value(&mut self, value_type: InferredType) {
+        self.return_type = Some(match self.return_type {
+            Some(return_type) => return_type.join(value_type),
+            None => value_type,
+        });
+    }
+
+    // Applies the operation to the state, along the jump to its target or to the next instruction
+    fn step(&mut self, state: &mut TypeState, op: &Operation, jump: bool) {
+        use InferredType::*;
+        match op {
+            Operation::LoadConst(constant) => state.push(InferredType::of_constant(&constant.value)),
+            Operation::LoadName(name) | Operation::LoadFast(name) => {
+                let value_type = self.variable(state, name);
+                state.push(value_type);
+            }
+            Operation::LoadFastAndClear(name) => {
+                let value_type = self.variable(state, name);
+                state.variables.remove(name);
+                state.push(value_type);
+            }
+            Operation::StoreName(name) | Operation::StoreFast(name) => {
+                let value_type = state.pop();
+                state.variables.insert(name.clone(), value_type);
+            }
+            Operation::DeleteName(name) | Operation::DeleteFast(name) => {
+                state.variables.remove(name);
+            }
+            // Values coming from cells, objects, modules and iterators, which may have any type
+            Operation::LoadDeref(_)
+            | Operation::LoadClosure(_)
+            | Operation::LoadGlobal { .. }
+            | Operation::LoadAttr { .. }
+            | Operation::LoadMethod(_)
+            | Operation::ImportFrom(_)
+            | Operation::PushNull
+            | Operation::ForIter(_)
+            | Operation::Send(_)
+            | Operation::CallFinally(_) => self.apply_effect(state, op, jump),
+            // The exception is swapped with the one being handled before, both pushed back
+            Operation::PushExcInfo => {
+                state.pop();
+                state.push(Dynamic);
+                state.push(Dynamic);
+            }
+
+            Operation::Copy(n) => state.push(state.peek(*n as usize)),
+            Operation::DupTopTwo => {
+                state.push(state.peek(2));
+                state.push(state.peek(2));
+            }
+            Operation::Rotate(n) => {
+                let top = state.pop();
+                let idx = state.stack.len().saturating_sub(*n as usize - 1);
+                state.stack.insert(idx, top);
+            }
+            Operation::Swap(n) => {
+                let len = state.stack.len();
+                if (*n as usize) <= len {
+                    state.stack.swap(len - 1, len - *n as usize);
+                }
+            }
+
+            Operation::UnaryOp(unary) => {
+                let operand = state.pop();
+                state.push(InferredType::unary(*unary, operand));
+            }
+            Operation::BinaryOp(binary) | Operation::InplaceOp(binary) => {
+                let b = state.pop();
+                let a = state.pop();
+                let inplace = matches!(op, Operation::InplaceOp(_));
+                state.push(InferredType::binary(*binary, inplace, a, b));
+            }
+            // Comparisons of builtin types give bools, others may return anything
+            Operation::CompareOp(kind) => {
+                let b = state.pop();
+                let a = state.pop();
+                let known = a != Dynamic && b != Dynamic;
+                state.push(if known || *kind == CmpKind::ExceptionMatch { Bool } else { Dynamic });
+            }
+            Operation::IsOp { .. } | Operation::ContainsOp { .. } | Operation::ToBool | Operation::CheckExcMatch => {
+                self.replace(state, op, jump, Bool)
+            }
+            Operation::BinarySubscr => {
+                state.pop();
+                let container = state.pop();
+                state.push(if container == Str { Str } else { Dynamic });
+            }
+            Operation::Other {
+                opcode: Opcode::BinarySlice,
+                ..
+            } => {
+                state.pop_n(2);
+                let container = state.pop();
+                state.push(if matches!(container, Str | List) { container } else { Dynamic });
+            }
+
+            Operation::BuildList(_) => self.replace(state, op, jump, List),
+            Operation::BuildString(_) => self.replace(state, op, jump, Str),
+            Operation::Other {
+                opcode: Opcode::FormatValue | Opcode::FormatSimple | Opcode::FormatWithSpec | Opcode::ConvertValue,
+                ..
+            } => self.replace(state, op, jump, Str),
+            Operation::Other {
+                opcode: Opcode::GetLen, ..
+            } => state.push(Int),
+            Operation::UnpackSequence(count) => {
+                let item = if state.pop() == Str { Str } else { Dynamic };
+                for _ in 0..*count {
+                    state.push(item);
+                }
+            }
+            // Items after the starred one are pushed first, the first item ends up on top
+            Operation::UnpackEx(counts) => {
+                state.pop();
+                for _ in 0..(counts >> 8) {
+                    state.push(Dynamic);
+                }
+                state.push(List);
+                for _ in 0..(counts & 0xFF) {
+                    state.push(Dynamic);
+                }
+            }
+
+            Operation::ReturnValue => {
+                let value_type = state.pop();
+                self.return_value(value_type);
+            }
+            Operation::ReturnConst(constant) => self.return_value(InferredType::of_constant(&constant.value)),
+
+            // Operations only popping values, or changing the containers below them in place
+            Operation::Nop
+            | Operation::PopTop
+            | Operation::DeleteDeref(_)
+            | Operation::DeleteGlobal(_)
+            | Operation::MakeCell(_)
+            | Operation::StoreDeref(_)
+            | Operation::StoreGlobal(_)
+            | Operation::StoreAttr(_)
+            | Operation::DeleteAttr(_)
+            | Operation::ImportStar
+            | Operation::StoreSubscr
+            | Operation::DeleteSubscr
+            | Operation::ListAppend(_)
+            | Operation::ListExtend(_)
+            | Operation::SetAdd(_)
+            | Operation::SetUpdate(_)
+            | Operation::MapAdd(_)
+            | Operation::DictMerge(_)
+            | Operation::DictUpdate(_)
+            | Operation::Jump(_)
+            | Operation::PopJumpIfFalse(_)
+            | Operation::PopJumpIfTrue(_)
+            | Operation::PopJumpIfNone(_)
+            | Operation::PopJumpIfNotNone(_)
+            | Operation::JumpIfFalseOrPop(_)
+            | Operation::JumpIfTrueOrPop(_)
+            | Operation::JumpIfNotExcMatch(_)
+            | Operation::EndFor
+            | Operation::PopBlock
+            | Operation::PopExcept
+            | Operation::RaiseVarargs(_)
+            | Operation::Reraise
+            | Operation::Precall(_)
+            | Operation::KwNames(_)
+            | Operation::SetFunctionAttribute(_) => self.apply_effect(state, op, jump),
+
+            // Operations pushing a single new value
+            Operation::ImportName(_)
+            | Operation::BuildTuple(_)
+            | Operation::BuildSet(_)
+            | Operation::BuildMap(_)
+            | Operation::BuildConstKeyMap(_)
+            | Operation::BuildSlice(_)
+            | Operation::ListToTuple
+            | Operation::GetIter
+            | Operation::CallFunction(_)
+            | Operation::CallFunctionKw(_)
+            | Operation::CallMethod(_)
+            | Operation::Call(_)
+            | Operation::CallKw(_)
+            | Operation::CallFunctionEx { .. }
+            | Operation::MakeFunction(_) => self.replace(state, op, jump, Dynamic),
+
+            // Anything else may replace any of the values, so nothing is known about the stack afterwards
+            _ => {
+                self.apply_effect(state, op, jump);
+                state.stack.fill(Dynamic);
+            }
+        }
+    }
+}
+
+// Joins the state into the start of the block, queueing it to be walked again if it changed
+fn join_into(
+    block_states: &mut [Option<TypeState>],
+    worklist: &mut Vec<BlockIndex>,
+    block: BlockIndex,
+    state: &TypeState,
+) {
+    let changed = match &mut block_states[block] {
+        Some(own) => own.join(state),
+        None => {
+            block_states[block] = Some(state.clone());
+            true
+        }
+    };
+    if changed && !worklist.contains(&block) {
+        worklist.push(block);
+    }
+}
+
+impl TypeInference {
+    pub fn analyze(
+        version: PythonVersion,
+        code: &CodeBlock,
+        cfg: &ControlFlowGraph,
+        depths: &StackDepths,
+    ) -> Self {
+        let instructions = code.get_instructions();
+        // Names assigned with a global statement in nested code objects
+        let global_stores: BTreeSet<Name> = code
+            .get_nested_operations()
+            .into_iter()
+            .filter_map(|op| match op {
+                Operation::StoreGlobal(name) | Operation::DeleteGlobal(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        let mut walker = TypeWalker {
+            version,
+            global_stores,
+            return_type: None,
+        };
+
+        let mut states: Vec<Option<TypeState>> = vec![None; instructions.len()];
+        let mut block_states: Vec<Option<TypeState>> = vec![None; cfg.blocks.len()];
+        let mut worklist: Vec<BlockIndex> = Vec::new();
+        // The value sent to 3.10 generators is already on the stack at the start
+        if !cfg.blocks.is_empty() {
+            let entry_state = TypeState {
+                stack: vec![InferredType::Dynamic; depths.before(0).unwrap_or(0) as usize],
+                variables: BTreeMap::new(),
+            };
+            join_into(&mut block_states, &mut worklist, cfg.entry(), &entry_state);
+        }
+
+        while let Some(block) = worklist.pop() {
+            let mut state = block_states[block].clone().expect("blocks to be walked once reached");
+            let range = cfg.blocks[block].instructions.clone();
+            let successors = cfg.blocks[block].successors.clone();
+
+            for idx in range.clone() {
+                let op = &instructions[idx].op;
+                states[idx] = Some(state.clone());
+
+                // Handlers start with the values the stack is unwound to and the exception,
+                // reached from any of the instructions of the block
+                for (handler, _) in successors.iter().filter(|(_, kind)| *kind == EdgeKind::Exception) {
+                    let handler_start = cfg.blocks[*handler].instructions.start;
+                    let depth = depths.before(handler_start).unwrap_or(0) as usize;
+                    let handler_state = TypeState {
+                        stack: vec![InferredType::Dynamic; depth],
+                        variables: state.variables.clone(),
+                    };
+                    join_into(&mut block_states, &mut worklist, *handler, &handler_state);
+                }
+
+                if idx == range.end - 1 {
+                    for (successor, _) in successors.iter().filter(|(_, kind)| *kind == EdgeKind::Jump) {
+                        let mut jump_state = state.clone();
+                        walker.step(&mut jump_state, op, true);
+                        join_into(&mut block_states, &mut worklist, *successor, &jump_state);
+                    }
+                }
+                walker.step(&mut state, op, false);
+            }
+
+            for (successor, _) in successors.iter().filter(|(_, kind)| *kind == EdgeKind::Fallthrough) {
+                join_into(&mut block_states, &mut worklist, *successor, &state);
+            }
+        }
+
+        let returns_generator =
+            code.co_flags & (CO_GENERATOR | CO_COROUTINE | CO_ITERABLE_COROUTINE | CO_ASYNC_GENERATOR) != 0;
+        TypeInference {
+            states,
+            return_type: if returns_generator {
+                Some(InferredType::Dynamic)
+            } else {
+                walker.return_type
+            },
+        }
+    }
+
+    // State before the instruction, None if it's unreachable
+    pub fn before(&self, instruction: InstructionIndex) -> Option<&TypeState> {
+        self.states[instruction].as_ref()
+    }
+}
@@ -1,117 +1,180 @@
-use super::{code::CodeBlock, pycachereader::PyCacheReader};
-
-const FLAG_REF: u8 = 0x80; // with a type, add obj to index
-
-// TODO: Finish translating types into an enum
-#[derive(Debug, Default, Clone)]
-pub enum Var {  
-    #[default]
-    Null,                       // 0
-    None,                       // N
-    True,                       // T
-    False,                      // F
-    Int(i32),                   // i
-    Long(i32),                  // l
-    Code(CodeBlock),            // c
-    Ref(u32),                   // r - seems to be an address
-    FlagRef(Box<Var>),          // '\x80' with a type, points to external refs vector, used to determine whether a serialized object should be tracked for potential future references within the serialized data stream
-    String(String),             // s, also used for coded objects
-    ShortAscii(String),         // \xfa (250) or z
-    ShortAsciiInterned(String), // \xda (218) or Z
-    SmallTuple(Vec<Var>),       // )
-    // STOPITER           'S'
-    // ELLIPSIS           '.'
-    // INT64              'I'
-    // FLOAT              'f'
-    // BINARY_FLOAT       'g'
-    // COMPLEX            'x'
-    // BINARY_COMPLEX     'y'
-    // INTERNED           't'
-    // TUPLE              '('
-    // LIST               '['
-    // DICT               '{'
-    // UNICODE            'u'
-    // UNKNOWN            '?'
-    // SET                '<'
-    // FROZENSET          '>'
-    // ASCII              'a'
-    // ASCII_INTERNED     'A'
-    // SMALL_TUPLE        ')'
-    // SHORT_ASCII_INTERNED 'Z'
-    // WFERR_OK 0
-    // WFERR_UNMARSHALLABLE 1
-    // WFERR_NESTEDTOODEEP 2
-    // WFERR_NOMEMORY 3
-}
-
-impl Var {
-    pub fn from_byte(byte: &u8, reader: &mut PyCacheReader) -> Option<Self> {
-        // FLAG_REF is set
-        if byte & FLAG_REF != 0 {
-            // Get current amount of refs
-            // If there is one, it means it's the main CodeBlock
-            // In that case returning Var::Ref shouldn't happen
-            // TODO: Replace this dirty fix with something appropriate
-            let is_main_code_block = reader.get_refs_len() == 0;
-            if is_main_code_block {
-                reader.push_ref(Var::None);
-            }
-
-            // Extract the type the flag references from lower 7 bits
-            let var = Var::from_byte(&(byte & 0x7F), reader)
-                .expect("flag ref to be correctly read as var it points to");
-
-            // Push the var to refs vector and get the index of last element
-            // if it isn't the main CodeBlock
-            //
-            // This is backwards from original CPython implementation,
-            // as it stores the var in a vector and then returns a Ref
-            // instead of returning the var wrapped in FlagRef and pushing the reference to the refs vector
-            if !is_main_code_block {
-                let idx = reader.push_ref(var);
-                return Some(Var::Ref(idx as u32));
-            }
-
-            // Return the var, as it is the main CodeBlock
-            Some(var)
-        } else {
-            match byte {
-                b'0' => Some(Var::Null),
-                b'N' => Some(Var::None),
-                b'T' => Some(Var::True),
-                b'F' => Some(Var::False),
-                b'i' => Some(Var::Int(reader.read_int())),
-                b'l' => Some(Var::Long(reader.read_long())),
-                b'c' => Some(Var::Code(reader.read_code())),
-                &b'r' => Some(Var::Ref(reader.read_ulong())),
-                &b's' => Some(Var::String(reader.read_string())),
-                0xfa | b'z' => Some(Var::ShortAscii(reader.read_short_string())), // TODO: Check why this gets caught by FlagRef and if it should
-                0xda | b'Z' => Some(Var::ShortAsciiInterned(reader.read_short_string())), // TODO: Check why this gets caught by FlagRef and if it should
-                &b')' => Some(Var::SmallTuple(reader.read_tuple())),
-                _ => None, // Return None for bytecode instructions and other unhandled types
-            }
-        }
-    }
-
-    // pub fn get_type(&self, ctx: &Context) -> BasicTypeEnum {
-    //     match self {
-    //         Var::Int(_) => ctx.i32_type().as_basic_type_enum(),
-    //         _ => todo!("can't get type of var {:?}", self),
-    //     }
-    // }
-
-    pub fn as_int(&self) -> Option<i32> {
-        match self {
-            Var::Int(i) => Some(*i),
-            Var::None | Var::Null => Some(0),
-            _ => None,
-        }
-    }
-
-    pub fn as_string(&self) -> Option<String> {
-        match self {
-            Var::String(s) | Var::ShortAscii(s) | Var::ShortAsciiInterned(s) => Some(s.clone()),
-            _ => None,
-        }
-    }
-}
+use super::{
+    code::CodeBlock,
+    long::PyLong,
+    marshalerror::{MarshalErrorKind, MarshalResult},
+    pycachereader::PyCacheReader,
+};
+
+const FLAG_REF: u8 = 0x80; // with a type, add obj to index
+
+#[derive(Debug, Default, Clone)]
+pub enum Var {  
+    #[default]
+    Null,                       // 0
+    None,                       // N
+    False,                      // F
+    True,                       // T
+    StopIteration,              // S
+    Ellipsis,                   // .
+    Int(i32),                   // i
+    Int64(i64),                 // I - only written by marshal versions older than 1
+    Long(PyLong),               // l - arbitrary-precision integer
+    Float(f64),                 // f (as text) or g (binary)
+    Complex(f64, f64),          // x (as text) or y (binary)
+    Bytes(Vec<u8>),             // s - bytes object, also used for co_code and other tables of code objects
+    Interned(String),           // t
+    Ref(u32),                   // r - index of an object flagged earlier in the data stream
+    FlagRef(Box<Var>),          // '\x80' with a type, marks an object that can be referenced later in the data stream
+    Tuple(Vec<Var>),            // (
+    SmallTuple(Vec<Var>),       // )
+    List(Vec<Var>),             // [
+    Dict(Vec<(Var, Var)>),      // { - key/value pairs terminated by a Null key
+    Code(CodeBlock),            // c
+    Unicode(String),            // u
+    Set(Vec<Var>),              // <
+    FrozenSet(Vec<Var>),        // >
+    Ascii(String),              // a
+    AsciiInterned(String),      // A
+    ShortAscii(String),         // \xfa (250) or z
+    ShortAsciiInterned(String), // \xda (218) or Z
+    // UNKNOWN            '?' - only used by the writer for unmarshallable objects
+}
+
+impl Var {
+    pub fn from_byte(byte: &u8, reader: &mut PyCacheReader) -> MarshalResult<Self> {
+        // FLAG_REF is set
+        if byte & FLAG_REF != 0 {
+            // Like CPython's r_ref_reserve, the slot is reserved before reading the object itself,
+            // so that objects nested in it (e.g. tuple items or code object fields) get the following indices
+            let idx = reader.reserve_ref();
+
+            // Extract the type the flag references from lower 7 bits
+            let var = Var::from_byte(&(byte & 0x7F), reader)?;
+            reader.set_ref(idx, var.clone());
+
+            // The var stays in place, so that the structure of the data stream is kept
+            Ok(Var::FlagRef(Box::new(var)))
+        } else {
+            match byte {
+                b'0' => Ok(Var::Null),
+                b'N' => Ok(Var::None),
+                b'F' => Ok(Var::False),
+                b'T' => Ok(Var::True),
+                b'S' => Ok(Var::StopIteration),
+                b'.' => Ok(Var::Ellipsis),
+                b'i' => Ok(Var::Int(reader.read_int()?)),
+                b'I' => Ok(Var::Int64(reader.read_int64()?)),
+                b'l' => Ok(Var::Long(reader.read_py_long()?)),
+                b'f' => Ok(Var::Float(reader.read_float_str()?)),
+                b'g' => Ok(Var::Float(reader.read_binary_float()?)),
+                b'x' => Ok(Var::Complex(reader.read_float_str()?, reader.read_float_str()?)),
+                b'y' => Ok(Var::Complex(reader.read_binary_float()?, reader.read_binary_float()?)),
+                b's' => Ok(Var::Bytes(reader.read_byte_string()?)),
+                b't' => Ok(Var::Interned(reader.read_string()?)),
+                b'r' => Ok(Var::Ref(reader.read_ref()?)),
+                b'(' => {
+                    let len = reader.read_size()?;
+                    Ok(Var::Tuple(reader.read_tuple(len)?))
+                }
+                b')' => {
+                    let len = reader.read_byte()? as usize;
+                    Ok(Var::SmallTuple(reader.read_tuple(len)?))
+                }
+                b'[' => {
+                    let len = reader.read_size()?;
+                    Ok(Var::List(reader.read_tuple(len)?))
+                }
+                b'{' => Ok(Var::Dict(reader.read_dict()?)),
+                b'c' => Ok(Var::Code(reader.read_code()?)),
+                b'u' => Ok(Var::Unicode(reader.read_string()?)),
+                b'<' => {
+                    let len = reader.read_size()?;
+                    Ok(Var::Set(reader.read_tuple(len)?))
+                }
+                b'>' => {
+                    let len = reader.read_size()?;
+                    Ok(Var::FrozenSet(reader.read_tuple(len)?))
+                }
+                b'a' => Ok(Var::Ascii(reader.read_ascii(false)?)),
+                b'A' => Ok(Var::AsciiInterned(reader.read_ascii(false)?)),
+                b'z' => Ok(Var::ShortAscii(reader.read_ascii(true)?)),
+                b'Z' => Ok(Var::ShortAsciiInterned(reader.read_ascii(true)?)),
+                _ => Err(reader.error_at(reader.get_current_idx() - 1, MarshalErrorKind::UnknownType)),
+            }
+        }
+    }
+
+    // pub fn get_type(&self, ctx: &Context) -> BasicTypeEnum {
+    //     match self {
+    //         Var::Int(_) => ctx.i32_type().as_basic_type_enum(),
+    //         _ => todo!("can't get type of var {:?}", self),
+    //     }
+    // }
+
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            Var::Int(i) => Some(*i),
+            Var::True => Some(1),
+            Var::None | Var::Null | Var::False => Some(0),
+            _ => None,
+        }
+    }
+
+    // Copy of the var with FlagRef markers dropped and Refs replaced by the objects they point to
+    pub fn resolve(&self, refs: &[Var]) -> Var {
+        let resolve_all = |vars: &Vec<Var>| vars.iter().map(|var| var.resolve(refs)).collect();
+        match self {
+            Var::FlagRef(var) => var.resolve(refs),
+            Var::Ref(i) => refs[*i as usize].resolve(refs),
+            Var::Tuple(vars) => Var::Tuple(resolve_all(vars)),
+            Var::SmallTuple(vars) => Var::SmallTuple(resolve_all(vars)),
+            Var::List(vars) => Var::List(resolve_all(vars)),
+            Var::Set(vars) => Var::Set(resolve_all(vars)),
+            Var::FrozenSet(vars) => Var::FrozenSet(resolve_all(vars)),
+            Var::Dict(pairs) => Var::Dict(
+                pairs
+                    .iter()
+                    .map(|(key, value)| (key.resolve(refs), value.resolve(refs)))
+                    .collect(),
+            ),
+            Var::Code(code) => Var::Code(code.resolve(refs)),
+            var => var.clone(),
+        }
+    }
+
+    // Any integer constant that fits in 64 bits
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Var::Int(i) => Some(*i as i64),
+            Var::Int64(i) => Some(*i),
+            Var::Long(long) => long.to_i64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_string(&self) -> Option<String> {
+        match self {
+            Var::Interned(s)
+            | Var::Unicode(s)
+            | Var::Ascii(s)
+            | Var::AsciiInterned(s)
+            | Var::ShortAscii(s)
+            | Var::ShortAsciiInterned(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            Var::Bytes(bytes) => Some(bytes.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple(&self) -> Option<&Vec<Var>> {
+        match self {
+            Var::Tuple(vars) | Var::SmallTuple(vars) => Some(vars),
+            _ => None,
+        }
+    }
+}
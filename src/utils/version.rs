@@ -0,0 +1,30 @@
+use super::opcodes;
+use std::fmt;
+
+// CPython version the bytecode was compiled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PythonVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl PythonVersion {
+    // Every CPython release changing the bytecode bumps the magic number stored
+    // in the first 2 bytes of a .pyc file (little-endian, followed by b"\r\n")
+    pub fn from_magic(magic: u16) -> Option<Self> {
+        opcodes::MAGIC_NUMBERS
+            .iter()
+            .find(|(version_magic, _)| *version_magic == magic)
+            .map(|(_, version)| *version)
+    }
+
+    pub fn at_least(&self, major: u8, minor: u8) -> bool {
+        *self >= PythonVersion { major, minor }
+    }
+}
+
+impl fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
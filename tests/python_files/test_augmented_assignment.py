@@ -0,0 +1,4 @@
+x = 10
+x += 5
+x -= 3
+print(x)
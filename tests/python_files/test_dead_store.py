@@ -0,0 +1,7 @@
+x = 5
+x = 7
+y = x + 1
+print(y)
+x = y - x
+unused = 100
+print(x)
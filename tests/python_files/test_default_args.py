@@ -0,0 +1,65 @@
+def scale(base, times=2):
+    result = 0
+    for i in range(times):
+        result = result + base
+    return result
+
+
+def describe(name, greeting="hello", *, punctuation="!"):
+    print(greeting)
+    print(name)
+    print(punctuation)
+
+
+def clamp(value, /, low=0, high=10, *, wrap):
+    if wrap:
+        return value - high
+    if value < low:
+        return low
+    if value > high:
+        return high
+    return value
+
+
+def total(a, b, c):
+    return a + b + c
+
+
+print(scale(3))
+print(scale(2, 10))
+print(scale(times=3, base=4))
+describe("world")
+describe("there", punctuation="?", greeting="hi")
+print(clamp(-5, wrap=False))
+print(clamp(15, high=12, wrap=False))
+print(clamp(20, 5, 15, wrap=True))
+
+args = (1, 2)
+kwargs = {"c": 3}
+print(total(*args, **kwargs))
+print(total(10, *args))
+print(total(*[4, 5, 6]))
+print(total(100, **{"c": 1, "b": 2}))
+print(total(1, b=2, **kwargs))
+
+
+def count(*args):
+    return 1
+
+
+def options(**kw):
+    return 1
+
+
+def tagged(first, *rest, sep="-", **extra):
+    print(first)
+    print(sep)
+    return 2
+
+
+print(count(1, 2))
+print(count())
+print(options(a=1))
+print(options())
+print(tagged(1, 2, 3, sep="+", flag=True))
+print(tagged(first=4, other=5))
@@ -0,0 +1,27 @@
+total = 0
+for i in range(5):
+    total = total + i
+print(total)
+
+for i in range(2, 5):
+    print(i)
+
+for i in range(10, 0, -3):
+    print(i)
+
+for i in range(3, 3):
+    print(i)
+
+count = 0
+for i in range(0, 20, 4):
+    if i == 8:
+        continue
+    if i > 14:
+        break
+    count += 1
+print(count)
+print(i)
+
+step = -1
+for j in range(2, -1, step):
+    print(j)
@@ -0,0 +1,32 @@
+def add(a, b):
+    return a + b
+
+
+def countdown(n):
+    while n > 0:
+        print(n)
+        n = n - 1
+
+
+def fib(n):
+    if n < 2:
+        return n
+    return fib(n - 1) + fib(n - 2)
+
+
+def sum_to(n):
+    total = 0
+    for i in range(n + 1):
+        total = add(total, i)
+    return total
+
+
+def show(x):
+    print(x)
+
+
+print(add(2, 3))
+countdown(3)
+print(fib(15))
+print(sum_to(10))
+show(add(40, 2))
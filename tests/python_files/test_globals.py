@@ -0,0 +1,43 @@
+counter = 0
+limit = 3
+
+
+def increment():
+    global counter
+    counter = counter + 1
+
+
+def below_limit(n):
+    return n < limit
+
+
+def reset():
+    global counter
+    counter = 0
+
+
+def setup():
+    global greeting
+    greeting = "hello"
+
+
+def main():
+    print(__name__)
+    setup()
+    print(greeting)
+
+
+for i in range(5):
+    increment()
+print(counter)
+print(below_limit(counter))
+reset()
+print(counter)
+print(below_limit(counter))
+limit = 10
+print(below_limit(5))
+
+if __name__ == "__main__":
+    main()
+if __name__ != "__main__":
+    print("imported")
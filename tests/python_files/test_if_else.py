@@ -0,0 +1,22 @@
+x = 7
+if x < 5:
+    print(1)
+elif x == 7:
+    print(2)
+else:
+    print(3)
+
+y = 10
+if x > y:
+    y = y - x
+else:
+    y = x - y
+print(y)
+
+if x >= 7 and y != 0:
+    print(x + y)
+if not x <= 6:
+    print(x)
+print(x == 7)
+big = x > 100 or y < 0
+print(big)
@@ -0,0 +1,68 @@
+def divide(a, b):
+    print(a // b)
+    print(a % b)
+
+
+def shift_left(a, b):
+    print(a << b)
+
+
+def shift_right(a, b):
+    print(a >> b)
+
+
+def bitwise(a, b):
+    print(a & b)
+    print(a | b)
+    print(a ^ b)
+
+
+def both(a, b):
+    print(a & b)
+    print(a | b)
+
+
+def digits(n):
+    count = 0
+    while n > 0:
+        n = n // 10
+        count = count + 1
+    return count
+
+
+def parity(n):
+    if n % 2 == 0:
+        return "even"
+    return "odd"
+
+
+divide(17, 5)
+divide(-17, 5)
+divide(17, -5)
+divide(-17, -5)
+divide(15, 5)
+divide(-15, 5)
+shift_left(1, 10)
+shift_left(-3, 2)
+shift_right(-1024, 3)
+shift_right(1024, 40)
+shift_right(-1, 40)
+bitwise(12, 10)
+bitwise(-12, 10)
+both(True, False)
+
+total = 0
+for i in range(1, 6):
+    total = total + i * i
+print(total)
+
+bits = 0
+value = 45
+while value:
+    bits = bits + (value & 1)
+    value = value >> 1
+print(bits)
+
+print(digits(12345))
+print(parity(7))
+print(parity(10))
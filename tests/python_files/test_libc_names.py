@@ -0,0 +1,38 @@
+# Functions named after the C library functions the compiled code calls
+
+
+def strcmp(a, b):
+    return a - b
+
+
+def printf(value):
+    print(value)
+
+
+def exit(code):
+    print("exit")
+    return code
+
+
+def puts(text):
+    print(text)
+
+
+def order(a, b):
+    if a < b:
+        return "less"
+    if a == b:
+        return "equal"
+    return "greater"
+
+
+def divide(a, b):
+    return a // b
+
+
+printf(strcmp(7, 3))
+puts("hello")
+print(exit(3))
+print(order("apple", "banana"))
+print(order("pear", "pear"))
+print(divide(7, 2))
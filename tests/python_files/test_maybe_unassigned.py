@@ -0,0 +1,32 @@
+def pick(flag):
+    if flag:
+        chosen = 7
+    if flag:
+        print(chosen)
+    return flag
+
+
+def last(n):
+    for i in range(n):
+        found = i
+    if n > 0:
+        return found
+    return -1
+
+
+def countdown(n):
+    while n > 0:
+        step = n
+        n = n - 1
+    if step:
+        del step
+    return n
+
+
+if pick(1):
+    label = "picked"
+print(label)
+pick(0)
+print(last(4))
+print(last(0))
+print(countdown(3))
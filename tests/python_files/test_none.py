@@ -0,0 +1,21 @@
+def nothing():
+    pass
+
+
+def report(value):
+    if value > 0:
+        print("positive")
+        return
+    print("not positive")
+
+
+print(None)
+x = None
+print(x)
+print(nothing())
+result = report(1)
+print(result)
+print(report(0))
+print(print("printed"))
+if not x:
+    print("None is false")
@@ -0,0 +1,2 @@
+text = "zażółć gęślą jaźń"
+print(text)
@@ -0,0 +1,16 @@
+i = 0
+total = 0
+while i < 10:
+    i = i + 1
+    if i == 3:
+        continue
+    if i > 7:
+        break
+    total = total + i
+print(total)
+print(i)
+
+n = 5
+while n:
+    n -= 1
+print(n)